@@ -0,0 +1,119 @@
+// NTFS attribute flags: the hidden/system filter the listings go through,
+// and the per attribute counts behind the `attributes` report
+use super::types::FileAttribute;
+use crate::utility::constants::GB_TO_BYTES;
+
+pub const EXCLUDE_HIDDEN_FLAG: &str = "--exclude-hidden";
+pub const EXCLUDE_SYSTEM_FLAG: &str = "--exclude-system";
+
+/// Which entries the listings show, everything unless asked otherwise.
+///
+/// Hidden means the entry's own hidden flag, the way Explorer sees it: a file in a hidden folder
+/// isn't hidden itself. Unix has no such flag, so there a name starting with a dot stands in for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AttributeFilter {
+    pub exclude_hidden: bool,
+    pub exclude_system: bool,
+}
+
+impl AttributeFilter {
+    /// hidden and system files left out, whatever the command's flags say
+    pub const NO_HIDDEN_OR_SYSTEM: Self = AttributeFilter { exclude_hidden: true, exclude_system: true };
+
+    /// what both filters leave out
+    pub fn and(self, other: Self) -> Self {
+        AttributeFilter {
+            exclude_hidden: self.exclude_hidden || other.exclude_hidden,
+            exclude_system: self.exclude_system || other.exclude_system,
+        }
+    }
+
+    /// takes the filter flags out of a command, wherever they appear in it
+    pub fn take_flags(words: &mut Vec<String>) -> Self {
+        let is = |word: &String, flag: &str| word.eq_ignore_ascii_case(flag);
+        let filter = AttributeFilter {
+            exclude_hidden: words.iter().any(|w| is(w, EXCLUDE_HIDDEN_FLAG)),
+            exclude_system: words.iter().any(|w| is(w, EXCLUDE_SYSTEM_FLAG)),
+        };
+        words.retain(|w| !is(w, EXCLUDE_HIDDEN_FLAG) && !is(w, EXCLUDE_SYSTEM_FLAG));
+        filter
+    }
+
+    pub fn allows(&self, attributes: u32) -> bool {
+        let hidden = self.exclude_hidden && FileAttribute::Hidden.is_set(attributes);
+        let system = self.exclude_system && FileAttribute::System.is_set(attributes);
+        !hidden && !system
+    }
+}
+
+/// entries and bytes carrying one attribute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttributeCount {
+    pub attribute: FileAttribute,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Counts entries and bytes per attribute from (attributes, size) pairs.
+/// An entry with several flags counts towards each of them.
+pub fn count_attributes(entries: impl IntoIterator<Item = (u32, u64)>) -> (Vec<AttributeCount>, usize) {
+    let mut counts: Vec<AttributeCount> = FileAttribute::ALL
+        .iter()
+        .map(|&attribute| AttributeCount { attribute, entries: 0, bytes: 0 })
+        .collect();
+    let mut total = 0;
+    for (attributes, size) in entries {
+        total += 1;
+        for count in counts.iter_mut().filter(|c| c.attribute.is_set(attributes)) {
+            count.entries += 1;
+            count.bytes += size;
+        }
+    }
+    (counts, total)
+}
+
+/// Prints one line per attribute, in the order of `FileAttribute::ALL`.
+pub fn print_attribute_counts(counts: &[AttributeCount], total: usize) {
+    println!("{:<22} {:>10} {:>12}", "Attribute", "Entries", "Size");
+    for count in counts {
+        println!(
+            "{:<22} {:>10} {:>9.2} GB",
+            count.attribute.to_string(),
+            count.entries,
+            count.bytes as f64 / GB_TO_BYTES
+        );
+    }
+    println!("\n{} entries checked", total);
+}
+
+#[cfg(test)]
+mod attributes_tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_flags() {
+        let mut words: Vec<String> = ["largest-files", "--exclude-hidden", "c"].map(String::from).to_vec();
+        let filter = AttributeFilter::take_flags(&mut words);
+        assert_eq!(words, ["largest-files", "c"]);
+        assert!(filter.exclude_hidden && !filter.exclude_system);
+
+        let hidden_system = FileAttribute::Hidden.mask() | FileAttribute::System.mask();
+        assert!(AttributeFilter::default().allows(hidden_system));
+        assert!(!filter.allows(FileAttribute::Hidden.mask()));
+        assert!(filter.allows(FileAttribute::System.mask()));
+        assert!(!AttributeFilter { exclude_hidden: false, exclude_system: true }.allows(hidden_system));
+        assert_eq!(filter.and(AttributeFilter::NO_HIDDEN_OR_SYSTEM), AttributeFilter::NO_HIDDEN_OR_SYSTEM);
+        assert_eq!(filter.and(AttributeFilter::default()), filter);
+    }
+
+    #[test]
+    fn test_count_attributes() {
+        let compressed = FileAttribute::Compressed.mask();
+        let (counts, total) = count_attributes([(compressed | FileAttribute::ReadOnly.mask(), 10), (compressed, 5), (0, 7)]);
+        let of = |attribute| counts.iter().find(|c| c.attribute == attribute).unwrap();
+        assert_eq!(total, 3);
+        assert_eq!((of(FileAttribute::Compressed).entries, of(FileAttribute::Compressed).bytes), (2, 15));
+        assert_eq!(of(FileAttribute::ReadOnly).bytes, 10);
+        assert_eq!(of(FileAttribute::Hidden).entries, 0);
+    }
+}
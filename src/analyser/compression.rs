@@ -0,0 +1,320 @@
+// estimates what compression would save without compressing everything.
+//
+// blocks are picked at uniformly random byte offsets across all scanned files, so a file's
+// chance of being sampled follows its size and the mean block ratio estimates the ratio
+// of the whole drive. the same samples are then split up by folder and by file type
+use super::types::ScanEntry;
+use crate::utility::constants::GB_TO_BYTES;
+use flate2::{Compression, write::DeflateEncoder};
+use rand::Rng;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// NTFS compresses in 64 KB units too, so ratios per block are close to what it would get
+pub const BLOCK_SIZE: u64 = 64 * 1024;
+pub const DEFAULT_SAMPLE_BLOCKS: usize = 500;
+// 95% two-sided normal quantile
+const Z_95: f64 = 1.96;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Zstd(i32),
+    Lz4,
+    Deflate,
+}
+
+pub const CODECS: [Codec; 6] = [
+    Codec::Lz4,
+    Codec::Deflate,
+    Codec::Zstd(1),
+    Codec::Zstd(3),
+    Codec::Zstd(9),
+    Codec::Zstd(19),
+];
+// what the per folder / per type tables show: lz4 is about as fast as filesystem
+// compression gets, zstd 19 is what you'd pack an archive with
+const FS_CODEC: usize = 0;
+const ARCHIVE_CODEC: usize = 5;
+
+impl Codec {
+    pub fn name(&self) -> String {
+        match self {
+            Codec::Zstd(level) => format!("zstd -{}", level),
+            Codec::Lz4 => "lz4".to_string(),
+            Codec::Deflate => "deflate".to_string(),
+        }
+    }
+
+    /// compressed size of a block, `None` if the codec failed on it
+    fn compressed_len(&self, data: &[u8]) -> Option<usize> {
+        match self {
+            Codec::Zstd(level) => zstd::bulk::compress(data, *level).ok().map(|c| c.len()),
+            Codec::Lz4 => Some(lz4_flex::block::compress(data).len()),
+            Codec::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).ok()?;
+                encoder.finish().ok().map(|c| c.len())
+            }
+        }
+    }
+}
+
+/// mean compressed/original ratio with a 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatioEstimate {
+    pub mean: f64,
+    pub low: f64,
+    pub high: f64,
+    pub samples: usize,
+}
+
+impl RatioEstimate {
+    /// Normal approximation over the block ratios. With fewer than two samples there's
+    /// no spread to go on, so the bounds are the widest possible (0 to 1).
+    pub fn from_ratios(ratios: &[f64]) -> Self {
+        let n = ratios.len();
+        if n == 0 {
+            return RatioEstimate { mean: 1.0, low: 0.0, high: 1.0, samples: 0 };
+        }
+        let mean = ratios.iter().sum::<f64>() / n as f64;
+        if n < 2 {
+            return RatioEstimate { mean, low: 0.0, high: 1.0, samples: n };
+        }
+        let variance = ratios.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let margin = Z_95 * (variance / n as f64).sqrt();
+        RatioEstimate {
+            mean,
+            low: (mean - margin).max(0.0),
+            high: (mean + margin).min(1.0),
+            samples: n,
+        }
+    }
+
+    /// (estimate, low, high) bytes saved out of `bytes`
+    pub fn saved(&self, bytes: u64) -> (u64, u64, u64) {
+        let saved = |ratio: f64| (bytes as f64 * (1.0 - ratio)) as u64;
+        (saved(self.mean), saved(self.high), saved(self.low))
+    }
+}
+
+pub struct GroupEstimate {
+    pub name: String,
+    pub bytes: u64,
+    pub fs: RatioEstimate,
+    pub archive: RatioEstimate,
+}
+
+pub struct CompressionEstimate {
+    pub total_bytes: u64,
+    pub sampled: usize,
+    pub unreadable: usize,
+    pub codecs: Vec<(Codec, RatioEstimate)>,
+    pub folders: Vec<GroupEstimate>,
+    pub types: Vec<GroupEstimate>,
+}
+
+/// the folder right below the drive root, `(root)` for files sitting in the root itself
+fn top_folder(path: &str) -> String {
+    let parts: Vec<&str> = path.split(['\\', '/']).filter(|p| !p.is_empty()).collect();
+    match parts.as_slice() {
+        [drive, folder, _, ..] => format!("{}\\{}", drive, folder),
+        _ => "(root)".to_string(),
+    }
+}
+
+fn file_type(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
+        .unwrap_or_else(|| "(no extension)".to_string())
+}
+
+/// reads the block starting at `offset`, aligned down to BLOCK_SIZE
+fn read_block(path: &str, offset: u64) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::Start(offset - offset % BLOCK_SIZE)).ok()?;
+    let mut data = Vec::with_capacity(BLOCK_SIZE as usize);
+    file.take(BLOCK_SIZE).read_to_end(&mut data).ok()?;
+    (!data.is_empty()).then_some(data)
+}
+
+/// Groups the samples by `key` and estimates each group from its own samples,
+/// largest groups first. Groups that got no samples are left out.
+fn group_estimates(
+    files: &[&ScanEntry],
+    samples: &[(usize, Vec<f64>)],
+    key: fn(&str) -> String,
+) -> Vec<GroupEstimate> {
+    let mut bytes: HashMap<String, u64> = HashMap::new();
+    for file in files {
+        *bytes.entry(key(&file.path)).or_default() += file.size;
+    }
+    let mut ratios: HashMap<String, (Vec<f64>, Vec<f64>)> = HashMap::new();
+    for (file, block) in samples {
+        let group = ratios.entry(key(&files[*file].path)).or_default();
+        group.0.push(block[FS_CODEC]);
+        group.1.push(block[ARCHIVE_CODEC]);
+    }
+
+    let mut groups: Vec<GroupEstimate> = ratios
+        .into_iter()
+        .map(|(name, (fs, archive))| GroupEstimate {
+            bytes: bytes[&name],
+            name,
+            fs: RatioEstimate::from_ratios(&fs),
+            archive: RatioEstimate::from_ratios(&archive),
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.bytes));
+    groups
+}
+
+/// Samples `blocks` blocks out of the given files and compresses each one with every codec.
+pub fn estimate_compression(files: &[&ScanEntry], blocks: usize) -> CompressionEstimate {
+    let files: Vec<&ScanEntry> = files.iter().copied().filter(|f| !f.is_directory && f.size > 0).collect();
+    // running totals, so a random byte offset maps to a file with a binary search
+    let mut ends = Vec::with_capacity(files.len());
+    let mut total_bytes = 0u64;
+    for file in &files {
+        total_bytes += file.size;
+        ends.push(total_bytes);
+    }
+
+    let blocks = if total_bytes == 0 { 0 } else { blocks };
+    let mut rng = rand::rng();
+    let picks: Vec<(usize, u64)> = (0..blocks)
+        .map(|_| {
+            let offset = rng.random_range(0..total_bytes);
+            let file = ends.partition_point(|&end| end <= offset);
+            let start = if file == 0 { 0 } else { ends[file - 1] };
+            (file, offset - start)
+        })
+        .collect();
+
+    let samples: Vec<(usize, Vec<f64>)> = picks
+        .par_iter()
+        .filter_map(|&(file, offset)| {
+            let data = read_block(&files[file].path, offset)?;
+            let ratios = CODECS
+                .iter()
+                .map(|codec| {
+                    // filesystems store a block as is when compressing doesn't help
+                    let compressed = codec.compressed_len(&data).unwrap_or(data.len());
+                    (compressed as f64 / data.len() as f64).min(1.0)
+                })
+                .collect();
+            Some((file, ratios))
+        })
+        .collect();
+
+    let codecs = CODECS
+        .iter()
+        .enumerate()
+        .map(|(i, codec)| {
+            let ratios: Vec<f64> = samples.iter().map(|(_, r)| r[i]).collect();
+            (*codec, RatioEstimate::from_ratios(&ratios))
+        })
+        .collect();
+
+    CompressionEstimate {
+        total_bytes,
+        sampled: samples.len(),
+        unreadable: picks.len() - samples.len(),
+        codecs,
+        folders: group_estimates(&files, &samples, top_folder),
+        types: group_estimates(&files, &samples, file_type),
+    }
+}
+
+fn gb(bytes: u64) -> f64 {
+    bytes as f64 / GB_TO_BYTES
+}
+
+fn print_groups(title: &str, groups: &[GroupEstimate], count: usize) {
+    println!("\n{} (lz4 ~ filesystem compression, zstd -19 ~ archiving):", title);
+    for group in groups.iter().take(count) {
+        let (fs, fs_low, fs_high) = group.fs.saved(group.bytes);
+        let (archive, archive_low, archive_high) = group.archive.saved(group.bytes);
+        println!(
+            "{:<40} {:>8.2} GB  lz4 saves {:.2} GB ({:.2}-{:.2})  zstd -19 saves {:.2} GB ({:.2}-{:.2})  [{} blocks]",
+            group.name,
+            gb(group.bytes),
+            gb(fs),
+            gb(fs_low),
+            gb(fs_high),
+            gb(archive),
+            gb(archive_low),
+            gb(archive_high),
+            group.fs.samples
+        );
+    }
+}
+
+/// Prints the overall estimate per codec, then the top `count` folders and file types.
+pub fn print_compression_estimate(estimate: &CompressionEstimate, count: usize) {
+    println!(
+        "Sampled {} blocks of {} KB out of {:.2} GB ({} unreadable), 95% bounds in brackets",
+        estimate.sampled,
+        BLOCK_SIZE / 1024,
+        gb(estimate.total_bytes),
+        estimate.unreadable
+    );
+    if estimate.sampled == 0 {
+        println!("Nothing could be sampled.");
+        return;
+    }
+    for (codec, ratio) in &estimate.codecs {
+        let (saved, low, high) = ratio.saved(estimate.total_bytes);
+        println!(
+            "{:<10} ratio {:.1}% ({:.1}-{:.1}%)  saves {:.2} GB ({:.2}-{:.2} GB)",
+            codec.name(),
+            ratio.mean * 100.0,
+            ratio.low * 100.0,
+            ratio.high * 100.0,
+            gb(saved),
+            gb(low),
+            gb(high)
+        );
+    }
+    print_groups("By Folder", &estimate.folders, count);
+    print_groups("By File Type", &estimate.types, count);
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use super::*;
+
+    #[test]
+    fn test_ratio_bounds() {
+        let estimate = RatioEstimate::from_ratios(&[0.4, 0.5, 0.6, 0.5]);
+        assert!((estimate.mean - 0.5).abs() < 1e-9);
+        assert!(estimate.low < 0.5 && estimate.high > 0.5);
+        let (saved, low, high) = estimate.saved(1000);
+        assert!(low < saved && saved < high && (499..=500).contains(&saved));
+
+        let single = RatioEstimate::from_ratios(&[0.3]);
+        assert_eq!((single.low, single.high), (0.0, 1.0));
+        assert_eq!(RatioEstimate::from_ratios(&[]).saved(1000), (0, 0, 1000));
+    }
+
+    #[test]
+    fn test_codecs() {
+        let zeros = vec![0u8; BLOCK_SIZE as usize];
+        for codec in CODECS {
+            assert!(codec.compressed_len(&zeros).unwrap() < 1024, "{}", codec.name());
+        }
+    }
+
+    #[test]
+    fn test_grouping_keys() {
+        assert_eq!(top_folder("C:\\Users\\me\\a.txt"), "C:\\Users");
+        assert_eq!(top_folder("C:/pagefile.sys"), "(root)");
+        assert_eq!(file_type("C:\\a\\Movie.MKV"), ".mkv");
+        assert_eq!(file_type("C:\\a\\Makefile"), "(no extension)");
+    }
+}
@@ -0,0 +1,191 @@
+// finds whole subtrees without files in them, so `a\b\c` with nothing but folders
+// inside gets reported once as `a` instead of only the innermost leaf
+use super::types::EmptyTree;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+/// files the OS (or other OSes) drop into folders on their own
+pub const JUNK_FILES: [&str; 3] = ["thumbs.db", ".ds_store", "desktop.ini"];
+
+pub fn is_junk_file(name: &str, size: u64) -> bool {
+    size == 0 || JUNK_FILES.iter().any(|junk| junk.eq_ignore_ascii_case(name))
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('/', "\\").trim_end_matches('\\').to_string()
+}
+
+fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once('\\').map(|(parent, _)| parent)
+}
+
+/// the root with what's under it, e.g. `C:\a\b  (+2 nested empty folders, 1 junk file)`
+pub fn tree_summary(tree: &EmptyTree) -> String {
+    let mut notes = Vec::new();
+    if !tree.nested.is_empty() {
+        notes.push(format!("+{} nested empty folders", tree.nested.len()));
+    }
+    if !tree.junk_files.is_empty() {
+        notes.push(format!("{} junk files", tree.junk_files.len()));
+    }
+    if notes.is_empty() {
+        tree.root.clone()
+    } else {
+        format!("{}  ({})", tree.root, notes.join(", "))
+    }
+}
+
+/// Checks a folder on disk right now: true when nothing below it is a file, apart from
+/// junk files in junk mode. Whatever can't be read counts as content, it can't be shown empty.
+// only the MFT listing needs the second look, the folder walk reads the disk as it goes
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn still_empty(folder: &Path, junk_as_empty: bool) -> bool {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return false;
+    };
+    entries.into_iter().all(|entry| {
+        let Ok(entry) = entry else {
+            return false;
+        };
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => still_empty(&entry.path(), junk_as_empty),
+            Ok(_) => {
+                junk_as_empty
+                    && entry
+                        .metadata()
+                        .is_ok_and(|m| is_junk_file(&entry.file_name().to_string_lossy(), m.len()))
+            }
+            Err(_) => false,
+        }
+    })
+}
+
+/// walks up from an empty folder for as long as the parent is empty too
+fn root_of<'b>(dir: &'b str, empty: &HashSet<&'b str>) -> &'b str {
+    let mut root = dir;
+    while let Some(up) = parent(root).filter(|up| empty.contains(up)) {
+        root = up;
+    }
+    root
+}
+
+/// Works out the empty subtrees from every folder and file of a scan.
+///
+/// * `dirs` - every folder path
+/// * `files` - every file path with its size
+/// * `junk_as_empty` - treat zero-byte and junk files as if they weren't there
+pub fn find_empty_trees<'a>(
+    dirs: impl IntoIterator<Item = &'a str>,
+    files: impl IntoIterator<Item = (&'a str, u64)>,
+    junk_as_empty: bool,
+) -> Vec<EmptyTree> {
+    // normalized path -> path as the scan had it
+    let dirs: HashMap<String, &str> = dirs.into_iter().map(|d| (normalize(d), d)).collect();
+    let mut non_empty: HashSet<String> = HashSet::new();
+    let mut junk: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (file, size) in files {
+        let path = normalize(file);
+        let Some((dir, name)) = path.rsplit_once('\\') else {
+            continue;
+        };
+        if junk_as_empty && is_junk_file(name, size) {
+            junk.entry(dir.to_string()).or_default().push(file.to_string());
+            continue;
+        }
+        // a real file makes every folder above it non-empty
+        let mut current = Some(dir);
+        while let Some(folder) = current {
+            if !non_empty.insert(folder.to_string()) {
+                break;
+            }
+            current = parent(folder);
+        }
+    }
+
+    let empty: HashSet<&str> = dirs
+        .keys()
+        .filter(|d| !non_empty.contains(*d))
+        .map(String::as_str)
+        .collect();
+    let mut trees: HashMap<&str, EmptyTree> = HashMap::new();
+    for &dir in &empty {
+        let root = root_of(dir, &empty);
+        let tree = trees.entry(root).or_insert_with(|| EmptyTree {
+            root: dirs[root].to_string(),
+            nested: Vec::new(),
+            junk_files: Vec::new(),
+        });
+        if dir != root {
+            tree.nested.push(dirs[dir].to_string());
+        }
+        if let Some(files) = junk.get(dir) {
+            tree.junk_files.extend(files.iter().cloned());
+        }
+    }
+
+    let mut trees: Vec<EmptyTree> = trees.into_values().collect();
+    for tree in &mut trees {
+        tree.nested.sort();
+        tree.junk_files.sort();
+    }
+    trees.sort_by(|a, b| a.root.cmp(&b.root));
+    trees
+}
+
+#[cfg(test)]
+mod empty_tree_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+
+    const DIRS: [&str; 7] = [
+        "C:\\a",
+        "C:\\a\\b",
+        "C:\\a\\b\\c",
+        "C:\\a\\b\\d",
+        "C:\\a\\x",
+        "C:\\photos",
+        "C:\\photos\\2019",
+    ];
+    const FILES: [(&str, u64); 3] = [
+        ("C:\\a\\x\\notes.txt", 120),
+        ("C:\\photos\\2019\\Thumbs.db", 4096),
+        ("C:\\photos\\empty.txt", 0),
+    ];
+
+    #[test]
+    fn test_nested_roots() {
+        let trees = find_empty_trees(DIRS, FILES, false);
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].root, "C:\\a\\b");
+        assert_eq!(trees[0].nested, vec!["C:\\a\\b\\c", "C:\\a\\b\\d"]);
+    }
+
+    #[test]
+    fn test_junk_mode() {
+        let trees = find_empty_trees(DIRS, FILES, true);
+        let roots: Vec<&str> = trees.iter().map(|t| t.root.as_str()).collect();
+        assert_eq!(roots, vec!["C:\\a\\b", "C:\\photos"]);
+        assert_eq!(trees[1].nested, vec!["C:\\photos\\2019"]);
+        assert_eq!(trees[1].junk_files.len(), 2);
+    }
+
+    #[test]
+    fn test_still_empty() {
+        let root = ScratchDir::new("empty-tree");
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        assert!(still_empty(&root, false));
+
+        fs::write(root.join("a/b/Thumbs.db"), b"cache").unwrap();
+        assert!(!still_empty(&root, false));
+        assert!(still_empty(&root, true));
+
+        // a nested folder that gained a real file since the scan
+        fs::write(root.join("a/b/c/notes.txt"), b"notes").unwrap();
+        assert!(!still_empty(&root, true));
+        assert!(!still_empty(&root.join("missing"), true));
+    }
+}
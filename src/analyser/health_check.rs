@@ -0,0 +1,432 @@
+// the `check` command: rules from a config file checked against the drives, so the analyser can
+// run unattended as a disk space monitor and say so through its exit code when something trips.
+//
+// growth and new large files are measured against what the previous check saw, kept in
+// outputs/check-state.tsv, so the first run only sets the baseline for those two.
+// both that and the default config are found next to the executable, not in the working directory
+use crate::utility::constants::GB_TO_BYTES;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io,
+    path::Path,
+};
+
+pub const CHECK_CONFIG_FILE: &str = "analyser.check";
+pub const CHECK_STATE_FILE: &str = "outputs/check-state.tsv";
+
+const CONFIG_KEYS: &str = "drives, min-free-percent, max-folder-gb, max-growth-gb, max-new-large-files, large-file-gb";
+
+/// The rules of a check, every one optional. `key = value` lines, `#` starts a comment:
+///
+/// ```text
+/// drives = C, D
+/// min-free-percent = 10
+/// max-folder-gb = 200
+/// max-growth-gb = 20
+/// max-new-large-files = 5
+/// large-file-gb = 1
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckConfig {
+    /// drive letters to check, every fixed drive when empty
+    pub drives: Vec<String>,
+    pub min_free_percent: Option<f64>,
+    pub max_folder_gb: Option<f64>,
+    pub max_growth_gb: Option<f64>,
+    pub max_new_large_files: Option<usize>,
+    /// how big a file has to be to count for `max-new-large-files`
+    pub large_file_gb: f64,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        CheckConfig {
+            drives: Vec::new(),
+            min_free_percent: None,
+            max_folder_gb: None,
+            max_growth_gb: None,
+            max_new_large_files: None,
+            large_file_gb: 1.0,
+        }
+    }
+}
+
+impl CheckConfig {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = CheckConfig::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected `key = value`, got `{}`", number + 1, line));
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            let number_err = || format!("line {}: {} needs a number, got `{}`", number + 1, key, value);
+            let number_value = || value.parse::<f64>().ok().filter(|n| *n >= 0.0).ok_or_else(number_err);
+            match key.as_str() {
+                "drives" => {
+                    config.drives = value
+                        .split([',', ' '])
+                        .filter(|d| !d.is_empty())
+                        .map(|d| d.trim_end_matches([':', '/', '\\']).to_uppercase())
+                        .collect();
+                    if let Some(bad) = config.drives.iter().find(|d| d.len() != 1 || !d.chars().all(|c| c.is_ascii_alphabetic())) {
+                        return Err(format!("line {}: `{}` isn't a drive letter", number + 1, bad));
+                    }
+                }
+                "min-free-percent" => config.min_free_percent = Some(number_value()?),
+                "max-folder-gb" => config.max_folder_gb = Some(number_value()?),
+                "max-growth-gb" => config.max_growth_gb = Some(number_value()?),
+                "max-new-large-files" => {
+                    config.max_new_large_files = Some(value.parse::<usize>().map_err(|_| number_err())?)
+                }
+                "large-file-gb" => config.large_file_gb = number_value()?,
+                _ => return Err(format!("line {}: unknown setting `{}`, known ones are {}", number + 1, key, CONFIG_KEYS)),
+            }
+        }
+        if !config.has_rules() {
+            return Err(format!("no rules set, give at least one of {}", CONFIG_KEYS));
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn has_rules(&self) -> bool {
+        self.min_free_percent.is_some()
+            || self.max_folder_gb.is_some()
+            || self.max_growth_gb.is_some()
+            || self.max_new_large_files.is_some()
+    }
+
+    /// the folder and new file rules need a scan, the others only the drives' space
+    pub fn needs_scan(&self) -> bool {
+        self.max_folder_gb.is_some() || self.max_new_large_files.is_some()
+    }
+}
+
+/// What the previous check saw, to measure growth and new files against.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckState {
+    pub used_bytes: HashMap<String, u64>,
+    pub large_files: HashSet<String>,
+}
+
+impl CheckState {
+    /// `used <tab> drive <tab> bytes` and `file <tab> path` lines
+    pub fn parse(text: &str) -> Self {
+        let mut state = CheckState::default();
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some("used"), Some(drive), Some(bytes)) => {
+                    if let Ok(bytes) = bytes.parse() {
+                        state.used_bytes.insert(drive.to_string(), bytes);
+                    }
+                }
+                (Some("file"), Some(path), None) => {
+                    state.large_files.insert(path.to_string());
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut drives: Vec<_> = self.used_bytes.iter().collect();
+        drives.sort();
+        let mut files: Vec<_> = self.large_files.iter().collect();
+        files.sort();
+        let mut text = String::new();
+        for (drive, bytes) in drives {
+            text.push_str(&format!("used\t{}\t{}\n", drive, bytes));
+        }
+        for path in files {
+            text.push_str(&format!("file\t{}\n", path));
+        }
+        text
+    }
+
+    /// `None` before the first check
+    pub fn load(path: &Path) -> Option<Self> {
+        fs::read_to_string(path).ok().map(|text| Self::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_text())
+    }
+}
+
+/// Space of one drive at check time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriveMeasure {
+    pub drive: String,
+    pub used_bytes: u64,
+    pub free_percent: f64,
+}
+
+/// Everything the rules look at, gathered by the backend.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measurements {
+    pub drives: Vec<DriveMeasure>,
+    /// (folder, size in GB), only filled in when the config needs a scan
+    pub folders: Vec<(String, f64)>,
+    /// files at least `large-file-gb` big, only filled in when the config needs a scan
+    pub large_files: Vec<String>,
+    pub scanned: bool,
+    /// drives whose scan failed or was cancelled. growth and new files aren't judged on them,
+    /// and the next check keeps comparing against what the last complete one saw
+    pub unscanned: Vec<String>,
+}
+
+impl Measurements {
+    /// the state for the next check, keeping the previous large files when nothing was scanned
+    /// and the previous state of every drive that wasn't scanned in full
+    pub fn to_state(&self, previous: Option<&CheckState>) -> CheckState {
+        let previous_used = |drive: &str| previous.and_then(|p| p.used_bytes.get(drive).copied());
+        let mut used_bytes = HashMap::new();
+        for drive in &self.drives {
+            let bytes = if self.is_unscanned(&drive.drive) { previous_used(&drive.drive) } else { Some(drive.used_bytes) };
+            if let Some(bytes) = bytes {
+                used_bytes.insert(drive.drive.clone(), bytes);
+            }
+        }
+
+        let previous_files = previous.into_iter().flat_map(|p| p.large_files.iter());
+        let large_files = if self.scanned {
+            previous_files
+                .filter(|path| self.unscanned.iter().any(|drive| starts_with_drive(path, drive)))
+                .chain(&self.large_files)
+                .cloned()
+                .collect()
+        } else {
+            previous_files.cloned().collect()
+        };
+        CheckState { used_bytes, large_files }
+    }
+
+    fn is_unscanned(&self, drive: &str) -> bool {
+        self.unscanned.iter().any(|d| d == drive)
+    }
+}
+
+fn starts_with_drive(path: &str, drive: &str) -> bool {
+    path.get(..drive.len()).is_some_and(|start| start.eq_ignore_ascii_case(drive))
+}
+
+/// What a check found: the verdict of every rule, and the drives it couldn't scan in full.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckReport {
+    pub results: Vec<RuleResult>,
+    pub unscanned: Vec<String>,
+}
+
+/// One rule's verdict, with a line per offender (or one saying why it passed).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleResult {
+    pub rule: &'static str,
+    pub tripped: bool,
+    pub details: Vec<String>,
+}
+
+/// Checks every configured rule against the measurements.
+pub fn evaluate(config: &CheckConfig, measured: &Measurements, previous: Option<&CheckState>) -> Vec<RuleResult> {
+    let mut results = Vec::new();
+
+    if let Some(min) = config.min_free_percent {
+        let low: Vec<String> = measured
+            .drives
+            .iter()
+            .filter(|d| d.free_percent < min)
+            .map(|d| format!("{} has {:.2}% free (minimum {}%)", d.drive, d.free_percent, min))
+            .collect();
+        let tripped = !low.is_empty();
+        let details = if tripped {
+            low
+        } else {
+            let lowest = measured.drives.iter().map(|d| d.free_percent).fold(f64::INFINITY, f64::min);
+            vec![format!("lowest free space {:.2}% (minimum {}%)", lowest, min)]
+        };
+        results.push(RuleResult { rule: "free space", tripped, details });
+    }
+
+    if let Some(max) = config.max_folder_gb {
+        let mut big: Vec<&(String, f64)> = measured.folders.iter().filter(|(_, size)| *size > max).collect();
+        big.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let details = if big.is_empty() {
+            vec![format!("no folder above {} GB", max)]
+        } else {
+            big.iter().map(|(folder, size)| format!("{} is {:.2} GB (maximum {} GB)", folder, size, max)).collect()
+        };
+        results.push(RuleResult { rule: "folder size", tripped: !big.is_empty(), details });
+    }
+
+    if let Some(max) = config.max_growth_gb {
+        let result = match previous {
+            None => RuleResult { rule: "growth", tripped: false, details: vec!["no previous run yet, this one is the baseline".to_string()] },
+            Some(previous) => {
+                let growth: Vec<(&str, f64)> = measured
+                    .drives
+                    .iter()
+                    .filter(|d| !measured.is_unscanned(&d.drive))
+                    .filter_map(|d| {
+                        let before = *previous.used_bytes.get(&d.drive)?;
+                        Some((d.drive.as_str(), (d.used_bytes as f64 - before as f64) / GB_TO_BYTES))
+                    })
+                    .collect();
+                let over: Vec<String> = growth
+                    .iter()
+                    .filter(|(_, grown)| *grown > max)
+                    .map(|(drive, grown)| format!("{} grew {:.2} GB since the last run (maximum {} GB)", drive, grown, max))
+                    .collect();
+                let tripped = !over.is_empty();
+                let details = if tripped {
+                    over
+                } else {
+                    let most = growth.iter().map(|(_, grown)| *grown).fold(0.0, f64::max);
+                    vec![format!("most growth {:.2} GB (maximum {} GB)", most, max)]
+                };
+                RuleResult { rule: "growth", tripped, details }
+            }
+        };
+        results.push(result);
+    }
+
+    if let Some(max) = config.max_new_large_files {
+        let result = match previous {
+            Some(previous) if measured.scanned => {
+                let new: Vec<&String> = measured.large_files.iter().filter(|f| !previous.large_files.contains(*f)).collect();
+                let tripped = new.len() > max;
+                let mut details = vec![format!(
+                    "{} new files of {} GB or more since the last run (maximum {})",
+                    new.len(),
+                    config.large_file_gb,
+                    max
+                )];
+                if tripped {
+                    details.extend(new.iter().take(10).map(|f| format!("  {}", f)));
+                }
+                RuleResult { rule: "new large files", tripped, details }
+            }
+            _ => RuleResult {
+                rule: "new large files",
+                tripped: false,
+                details: vec!["no previous run yet, this one is the baseline".to_string()],
+            },
+        };
+        results.push(result);
+    }
+
+    results
+}
+
+/// One line per rule, offenders indented below, then a verdict.
+pub fn print_check_report(report: &CheckReport) {
+    let results = &report.results;
+    let tripped = results.iter().filter(|r| r.tripped).count();
+    for result in results {
+        let mark = if result.tripped { "[!]" } else { "[ok]" };
+        let mut details = result.details.iter();
+        println!("{:<5}{:<16} {}", mark, result.rule, details.next().map(String::as_str).unwrap_or_default());
+        for detail in details {
+            println!("{:<21} {}", "", detail);
+        }
+    }
+    if tripped == 0 {
+        println!("\nAll {} rules passed.", results.len());
+    } else {
+        println!("\n{} of {} rules tripped.", tripped, results.len());
+    }
+    if !report.unscanned.is_empty() {
+        println!("Growth and new files weren't checked on {}, the scan didn't finish.", report.unscanned.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod health_check_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = CheckConfig::parse("# nightly\ndrives = c:, D\nmin-free-percent = 10\nmax-new-large-files = 5 # or so\n").unwrap();
+        assert_eq!(config.drives, ["C", "D"]);
+        assert_eq!(config.min_free_percent, Some(10.0));
+        assert_eq!(config.max_new_large_files, Some(5));
+        assert_eq!(config.large_file_gb, 1.0);
+        assert!(config.needs_scan());
+
+        assert!(CheckConfig::parse("max-folder-gb = lots").is_err());
+        assert!(CheckConfig::parse("max-size = 3").is_err());
+        assert!(CheckConfig::parse("drives = C").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let config = CheckConfig::parse("min-free-percent = 10\nmax-folder-gb = 100\nmax-growth-gb = 5\nmax-new-large-files = 1").unwrap();
+        let measured = Measurements {
+            drives: vec![
+                DriveMeasure { drive: "C:/".into(), used_bytes: (20.0 * GB_TO_BYTES) as u64, free_percent: 30.0 },
+                DriveMeasure { drive: "D:/".into(), used_bytes: (50.0 * GB_TO_BYTES) as u64, free_percent: 4.5 },
+            ],
+            folders: vec![("C:/Videos".into(), 120.0), ("C:/Games".into(), 80.0)],
+            large_files: vec!["C:/a.iso".into(), "C:/b.iso".into(), "C:/c.iso".into()],
+            scanned: true,
+            unscanned: Vec::new(),
+        };
+
+        // the first run has nothing to compare growth and new files with
+        let first = evaluate(&config, &measured, None);
+        let tripped: Vec<_> = first.iter().filter(|r| r.tripped).map(|r| r.rule).collect();
+        assert_eq!(tripped, ["free space", "folder size"]);
+
+        let previous = CheckState {
+            used_bytes: HashMap::from([("C:/".to_string(), (10.0 * GB_TO_BYTES) as u64), ("D:/".to_string(), (49.0 * GB_TO_BYTES) as u64)]),
+            large_files: HashSet::from(["C:/a.iso".to_string()]),
+        };
+        let second = evaluate(&config, &measured, Some(&previous));
+        assert!(second.iter().all(|r| r.tripped));
+        assert_eq!(second[2].details, ["C:/ grew 10.00 GB since the last run (maximum 5 GB)"]);
+
+        let state = measured.to_state(Some(&previous));
+        assert_eq!(CheckState::parse(&state.to_text()), state);
+        assert_eq!(state.large_files.len(), 3);
+    }
+
+    #[test]
+    fn test_unscanned_drive() {
+        let config = CheckConfig::parse("max-growth-gb = 5\nmax-new-large-files = 0").unwrap();
+        let measured = Measurements {
+            drives: vec![
+                DriveMeasure { drive: "C:/".into(), used_bytes: 40, free_percent: 30.0 },
+                DriveMeasure { drive: "D:/".into(), used_bytes: (90.0 * GB_TO_BYTES) as u64, free_percent: 4.5 },
+            ],
+            folders: Vec::new(),
+            large_files: vec!["C:/a.iso".into()],
+            scanned: true,
+            unscanned: vec!["D:/".into()],
+        };
+        let previous = CheckState {
+            used_bytes: HashMap::from([("C:/".to_string(), 40), ("D:/".to_string(), 10)]),
+            large_files: HashSet::from(["C:/a.iso".to_string(), "d:/b.iso".to_string(), "C:/gone.iso".to_string()]),
+        };
+
+        // D grew a lot, but its scan was cut short so it isn't judged
+        let results = evaluate(&config, &measured, Some(&previous));
+        assert!(results.iter().all(|r| !r.tripped));
+
+        // and the next check still compares D against the last complete one
+        let state = measured.to_state(Some(&previous));
+        assert_eq!(state.used_bytes["D:/"], 10);
+        assert_eq!(state.large_files, HashSet::from(["C:/a.iso".to_string(), "d:/b.iso".to_string()]));
+    }
+}
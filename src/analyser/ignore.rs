@@ -0,0 +1,361 @@
+// gitignore-style include/exclude rules for the scans.
+//
+// exclusions come from the config file, `--exclude` flags and `.analyserignore` files in
+// any folder, and `--include` narrows the files down to the ones matching. they're applied
+// while walking (excluded folders are pruned, not walked) and while going over the MFT
+use crate::utility::utils::app_file;
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    sync::{Arc, Mutex},
+};
+
+/// patterns for every scan, relative to the root of whatever is scanned, next to the executable
+pub const IGNORE_CONFIG_FILE: &str = "analyser.ignore";
+/// per folder patterns, relative to the folder holding the file
+pub const IGNORE_FILE_NAME: &str = ".analyserignore";
+
+pub const INCLUDE_FLAG: &str = "--include";
+pub const EXCLUDE_FLAG: &str = "--exclude";
+
+/// NTFS doesn't care about case, so on Windows neither do the patterns
+pub const CASE_INSENSITIVE: bool = cfg!(target_os = "windows");
+
+/// one line of an ignore file
+#[derive(Debug, Clone)]
+pub struct IgnoreRule {
+    regex: Regex,
+    /// `!pattern`, brings back something an earlier rule excluded
+    negated: bool,
+    /// `pattern/`, only matches folders
+    dir_only: bool,
+    /// the folder the pattern is relative to, normalized
+    base: String,
+}
+
+/// backslashes to slashes and no trailing slash, so both backends' paths compare the same
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let trimmed = path.trim_end_matches('/');
+    if CASE_INSENSITIVE { trimmed.to_lowercase() } else { trimmed.to_string() }
+}
+
+/// `path` relative to `base`, both normalized. `None` when it isn't below it
+fn relative<'a>(path: &'a str, base: &str) -> Option<&'a str> {
+    if base.is_empty() {
+        return Some(path.trim_start_matches('/'));
+    }
+    path.strip_prefix(base)?.strip_prefix('/')
+}
+
+/// Turns a gitignore pattern into a regex over relative paths.
+/// A pattern with a slash in it is anchored to the base, one without matches at any depth.
+fn pattern_to_regex(pattern: &str) -> String {
+    let (anchored, body) = match pattern.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (pattern.contains('/'), pattern),
+    };
+    let mut expression = String::from(if CASE_INSENSITIVE { "(?i)^" } else { "^" });
+    if !anchored {
+        expression.push_str("(?:.*/)?");
+    }
+
+    let chars: Vec<char> = body.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                if at_start && chars.get(i + 2) == Some(&'/') {
+                    // `**/`, any number of folders
+                    expression.push_str("(?:.*/)?");
+                    i += 3;
+                } else if at_start && i + 2 == chars.len() {
+                    // trailing `/**`, everything inside
+                    expression.push_str(".*");
+                    i += 2;
+                } else {
+                    // any other `**` is just a `*`
+                    expression.push_str("[^/]*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => expression.push_str("[^/]*"),
+            '?' => expression.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(length) if length > 0 => {
+                    let class: String = chars[i + 1..i + 1 + length].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(rest) => format!("^{}", rest),
+                        None => class,
+                    };
+                    expression.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                    i += length + 1;
+                }
+                _ => expression.push_str("\\["),
+            },
+            '\\' if i + 1 < chars.len() => {
+                expression.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 1;
+            }
+            c => expression.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    expression.push('$');
+    expression
+}
+
+impl IgnoreRule {
+    /// Parses one gitignore line, `None` for blanks, comments and broken patterns.
+    pub fn parse(line: &str, base: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return None;
+        }
+        Some(IgnoreRule {
+            regex: Regex::new(&pattern_to_regex(line)).ok()?,
+            negated,
+            dir_only,
+            base: normalize(base),
+        })
+    }
+
+    /// `path` has to be normalized already
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        relative(path, &self.base).is_some_and(|rel| !rel.is_empty() && self.regex.is_match(rel))
+    }
+}
+
+/// On Windows a backslash in a command line or config pattern is a path separator,
+/// since that's what people type there, rather than gitignore's escape.
+fn native_separators(pattern: &str) -> Cow<'_, str> {
+    if cfg!(target_os = "windows") {
+        Cow::Owned(pattern.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(pattern)
+    }
+}
+
+fn parse_rules(text: &str, base: &str) -> Vec<IgnoreRule> {
+    text.lines().filter_map(|line| IgnoreRule::parse(line, base)).collect()
+}
+
+/// Include and exclude patterns given with a command, on top of the config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanFilter {
+    pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+}
+
+impl ScanFilter {
+    /// Takes `--include pattern` and `--exclude pattern` out of a command.
+    /// The words are as typed, so the patterns keep their case.
+    pub fn take_flags(words: &mut Vec<String>) -> Result<Self, String> {
+        let mut filter = ScanFilter::default();
+        let mut kept = Vec::with_capacity(words.len());
+        let mut iter = words.drain(..);
+        while let Some(word) = iter.next() {
+            let list = match word.to_lowercase().as_str() {
+                INCLUDE_FLAG => &mut filter.includes,
+                EXCLUDE_FLAG => &mut filter.excludes,
+                _ => {
+                    kept.push(word);
+                    continue;
+                }
+            };
+            let pattern = iter.next().ok_or(format!("{} needs a pattern, like '*.log' or node_modules/", word))?;
+            list.push(pattern);
+        }
+        drop(iter);
+        *words = kept;
+        Ok(filter)
+    }
+
+    /// the rules for a scan of `root`, with the config file read fresh
+    pub fn matcher(&self, root: &str) -> IgnoreMatcher {
+        let mut rules = fs::read_to_string(app_file(IGNORE_CONFIG_FILE))
+            .map(|text| parse_rules(&native_separators(&text), root))
+            .unwrap_or_default();
+        let parse = |pattern: &String| IgnoreRule::parse(&native_separators(pattern), root);
+        rules.extend(self.excludes.iter().filter_map(parse));
+        IgnoreMatcher {
+            root: normalize(root),
+            rules: Arc::new(rules),
+            includes: self.includes.iter().filter_map(parse).collect(),
+            folder_rules: Mutex::new(HashMap::new()),
+            verdicts: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Decides what a scan of one root keeps. `.analyserignore` files are read once per folder
+/// as the scan gets to them; the mutexes are there because the walks run on rayon.
+pub struct IgnoreMatcher {
+    root: String,
+    rules: Arc<Vec<IgnoreRule>>,
+    includes: Vec<IgnoreRule>,
+    // folder -> the rules of its .analyserignore (empty when it has none)
+    folder_rules: Mutex<HashMap<String, Arc<Vec<IgnoreRule>>>>,
+    // folder -> excluded, so flat MFT listings don't redo every parent
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    verdicts: Mutex<HashMap<String, bool>>,
+}
+
+impl IgnoreMatcher {
+    fn rules_in(&self, folder: &str) -> Arc<Vec<IgnoreRule>> {
+        if let Some(rules) = self.folder_rules.lock().unwrap().get(folder) {
+            return rules.clone();
+        }
+        let rules = fs::read_to_string(format!("{}/{}", folder, IGNORE_FILE_NAME))
+            .map(|text| parse_rules(&text, folder))
+            .unwrap_or_default();
+        let rules = Arc::new(rules);
+        self.folder_rules.lock().unwrap().insert(folder.to_string(), rules.clone());
+        rules
+    }
+
+    /// the folders from the root down to the parent of `path`
+    fn parents<'a>(&self, path: &'a str) -> Vec<&'a str> {
+        let Some(rel) = relative(path, &self.root) else {
+            return Vec::new();
+        };
+        let start = path.len() - rel.len();
+        let mut folders = vec![&path[..start.saturating_sub(1)]];
+        folders.extend(rel.match_indices('/').map(|(at, _)| &path[..start + at]));
+        folders
+    }
+
+    /// Whether the rules exclude `path` itself, assuming its parents are fine (they are
+    /// during a walk, excluded folders are never entered). Later rules win, and the
+    /// `.analyserignore` files of deeper folders come after the config and the flags.
+    pub fn is_excluded(&self, path: &str, is_dir: bool) -> bool {
+        let path = normalize(path);
+        let mut excluded = false;
+        let folder_rules = self.parents(&path).into_iter().map(|folder| self.rules_in(folder));
+        for rules in std::iter::once(self.rules.clone()).chain(folder_rules) {
+            for rule in rules.iter().filter(|rule| rule.matches(&path, is_dir)) {
+                excluded = !rule.negated;
+            }
+        }
+        excluded
+    }
+
+    /// Like `is_excluded`, but also checks every parent folder, for flat listings like the MFT.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn is_excluded_deep(&self, path: &str, is_dir: bool) -> bool {
+        let normalized = normalize(path);
+        for folder in self.parents(&normalized).into_iter().skip(1) {
+            let cached = self.verdicts.lock().unwrap().get(folder).copied();
+            let excluded = cached.unwrap_or_else(|| {
+                let excluded = self.is_excluded(folder, true);
+                self.verdicts.lock().unwrap().insert(folder.to_string(), excluded);
+                excluded
+            });
+            if excluded {
+                return true;
+            }
+        }
+        self.is_excluded(path, is_dir)
+    }
+
+    /// With `--include` given, only files matching one of them (or inside a matching folder) count.
+    pub fn is_included(&self, path: &str) -> bool {
+        if self.includes.is_empty() {
+            return true;
+        }
+        let path = normalize(path);
+        let parents = self.parents(&path);
+        self.includes.iter().any(|rule| {
+            rule.matches(&path, false) || parents.iter().skip(1).any(|folder| rule.matches(folder, true))
+        })
+    }
+
+    /// what a flat listing keeps: folders that aren't excluded, files that also pass the includes
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn keeps(&self, path: &str, is_dir: bool) -> bool {
+        !self.is_excluded_deep(path, is_dir) && (is_dir || self.is_included(path))
+    }
+}
+
+#[cfg(test)]
+mod ignore_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+
+    fn matcher(excludes: &[&str], includes: &[&str]) -> IgnoreMatcher {
+        let filter = ScanFilter {
+            includes: includes.iter().map(|p| p.to_string()).collect(),
+            excludes: excludes.iter().map(|p| p.to_string()).collect(),
+        };
+        filter.matcher("/nonexistent-root")
+    }
+
+    #[test]
+    fn test_patterns() {
+        let m = matcher(&["*.log", "node_modules/", "/build", "docs/**/*.pdf", "!keep.log"], &[]);
+        let excluded = |path: &str, dir| m.is_excluded_deep(&format!("/nonexistent-root/{}", path), dir);
+
+        assert!(excluded("a/b/debug.log", false));
+        assert!(!excluded("a/b/keep.log", false));
+        assert!(excluded("web/node_modules", true));
+        assert!(!excluded("web/node_modules", false));
+        assert!(excluded("web/node_modules/left-pad/index.js", false));
+        assert!(excluded("build/out.bin", false));
+        assert!(!excluded("src/build/out.bin", false));
+        assert!(excluded("docs/a/b/manual.pdf", false));
+        assert!(excluded("docs/manual.pdf", false));
+        assert!(!excluded("other/docs/manual.pdf", false));
+    }
+
+    #[test]
+    fn test_includes_and_flags() {
+        let m = matcher(&[], &["*.mkv", "photos/"]);
+        assert!(m.keeps("/nonexistent-root/films/a.mkv", false));
+        assert!(m.keeps("/nonexistent-root/photos/2020/a.jpg", false));
+        assert!(!m.keeps("/nonexistent-root/films/a.txt", false));
+        assert!(m.keeps("/nonexistent-root/films", true));
+
+        let mut command: Vec<String> =
+            "largest-files C --Exclude Temp/ --include *.MKV".split_whitespace().map(String::from).collect();
+        let filter = ScanFilter::take_flags(&mut command).unwrap();
+        assert_eq!(command, ["largest-files", "C"]);
+        assert_eq!((filter.excludes, filter.includes), (vec!["Temp/".to_string()], vec!["*.MKV".to_string()]));
+        assert!(ScanFilter::take_flags(&mut vec!["--exclude".to_string()]).is_err());
+
+        let m = matcher(&["build\\out/"], &[]);
+        let windows_style = m.is_excluded_deep("/nonexistent-root/build/out/a.bin", false);
+        assert_eq!(windows_style, cfg!(target_os = "windows"));
+    }
+
+    #[test]
+    fn test_folder_ignore_files() {
+        let root = ScratchDir::new("ignore");
+        fs::create_dir_all(root.join("project/target")).unwrap();
+        fs::write(root.join("project").join(IGNORE_FILE_NAME), "target/\n# comment\n").unwrap();
+
+        let root_str = root.to_string_lossy().to_string();
+        let m = ScanFilter::default().matcher(&root_str);
+        assert!(m.is_excluded(&format!("{}/project/target", root_str), true));
+        assert!(m.is_excluded_deep(&format!("{}/project/target/debug/app", root_str), false));
+        // the rules only apply below the folder holding them
+        assert!(!m.is_excluded(&format!("{}/target", root_str), true));
+    }
+}
@@ -0,0 +1,276 @@
+// classifying symlinks, junctions and other reparse points, and following them safely.
+//
+// scans never descend into links (WalkDir isn't told to follow them and the MFT lists every
+// file once), so whatever a link points to is only counted where it really lives. following
+// is opt-in here and only adds bytes from outside the scanned tree, each target once
+use super::ntfs_raw::{IO_REPARSE_TAG_MOUNT_POINT, IO_REPARSE_TAG_SYMLINK};
+use super::types::{LinkInfo, LinkKind, LinkStatus};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+pub const IO_REPARSE_TAG_DEDUP: u32 = 0x8000_0013;
+pub const IO_REPARSE_TAG_ONEDRIVE: u32 = 0x8000_0021;
+// cloud files tags are 0x9000X01A, with a provider specific nibble at X
+const IO_REPARSE_TAG_CLOUD: u32 = 0x9000_001A;
+const CLOUD_TAG_MASK: u32 = 0xFFFF_0FFF;
+
+/// Mount points and junctions share a tag, a target of `Volume{guid}` means a mounted volume.
+pub fn classify_reparse_tag(tag: u32, target: Option<&str>) -> LinkKind {
+    match tag {
+        IO_REPARSE_TAG_SYMLINK => LinkKind::Symlink,
+        IO_REPARSE_TAG_MOUNT_POINT
+            if target.is_some_and(|t| t.to_lowercase().starts_with("volume{")) =>
+        {
+            LinkKind::MountPoint
+        }
+        IO_REPARSE_TAG_MOUNT_POINT => LinkKind::Junction,
+        IO_REPARSE_TAG_DEDUP => LinkKind::Dedup,
+        IO_REPARSE_TAG_ONEDRIVE => LinkKind::CloudPlaceholder,
+        tag if tag & CLOUD_TAG_MASK == IO_REPARSE_TAG_CLOUD => LinkKind::CloudPlaceholder,
+        tag => LinkKind::Other(tag),
+    }
+}
+
+/// true for kinds that point somewhere else, the rest are files with unusual storage
+pub fn is_path_link(kind: LinkKind) -> bool {
+    matches!(kind, LinkKind::Symlink | LinkKind::Junction | LinkKind::MountPoint)
+}
+
+/// Checks where a link goes on the live file system and, with `follow`, adds up the bytes
+/// behind it that the scan of `root` didn't count already.
+///
+/// `visited` holds the targets followed so far, so two links to the same place count once.
+pub fn check_link(link: &Path, root: &Path, follow: bool, visited: &mut HashSet<PathBuf>) -> (LinkStatus, Option<u64>) {
+    let Ok(target) = fs::canonicalize(link) else {
+        return (LinkStatus::Broken, None);
+    };
+    let parent = link.parent().and_then(|p| fs::canonicalize(p).ok());
+    if parent.is_some_and(|parent| parent.starts_with(&target)) {
+        return (LinkStatus::Cycle, None);
+    }
+    if !follow {
+        return (LinkStatus::Ok, None);
+    }
+
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    if target.starts_with(&root) || !visited.insert(target.clone()) {
+        return (LinkStatus::Ok, Some(0));
+    }
+    (LinkStatus::Ok, Some(followed_size(&target, &root, visited)))
+}
+
+/// Size of everything under `target`, following links inside it too.
+/// WalkDir stops at loops by itself, and links back into the scan or into something
+/// already followed are skipped so nothing is counted twice.
+fn followed_size(target: &Path, root: &Path, visited: &mut HashSet<PathBuf>) -> u64 {
+    WalkDir::new(target)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| {
+            if !entry.path_is_symlink() || entry.depth() == 0 {
+                return true;
+            }
+            match fs::canonicalize(entry.path()) {
+                Ok(resolved) => !resolved.starts_with(root) && visited.insert(resolved),
+                Err(_) => false,
+            }
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// the reparse tag of a path, read from its directory entry
+#[cfg(target_os = "windows")]
+fn reparse_tag(path: &Path) -> Option<u32> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::{
+        fileapi::{FindClose, FindFirstFileW},
+        handleapi::INVALID_HANDLE_VALUE,
+        minwinbase::WIN32_FIND_DATAW,
+        winnt::FILE_ATTRIBUTE_REPARSE_POINT,
+    };
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut data: WIN32_FIND_DATAW = unsafe { std::mem::zeroed() };
+    let handle = unsafe { FindFirstFileW(wide.as_ptr(), &mut data) };
+    if handle == INVALID_HANDLE_VALUE {
+        return None;
+    }
+    unsafe { FindClose(handle) };
+    // dwReserved0 only holds the tag when the reparse attribute is set
+    (data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT != 0).then_some(data.dwReserved0)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn reparse_tag(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Mount points from /proc/self/mounts (bind mounts included), `\040` and friends decoded.
+#[cfg(target_os = "linux")]
+fn mount_points() -> HashSet<PathBuf> {
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    mounts
+        .lines()
+        .filter_map(|line| line.split(' ').nth(1))
+        .map(|field| {
+            let mut path = String::new();
+            let mut rest = field;
+            while let Some(at) = rest.find('\\') {
+                path.push_str(&rest[..at]);
+                match rest.get(at + 1..at + 4).and_then(|o| u8::from_str_radix(o, 8).ok()) {
+                    Some(byte) => {
+                        path.push(byte as char);
+                        rest = &rest[at + 4..];
+                    }
+                    None => {
+                        path.push('\\');
+                        rest = &rest[at + 1..];
+                    }
+                }
+            }
+            path.push_str(rest);
+            PathBuf::from(path)
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mount_points() -> HashSet<PathBuf> {
+    HashSet::new()
+}
+
+/// Finds the links, reparse points and mount points under `root` by walking it.
+/// Used by the walkdir backend, the NTFS backend reads reparse points from the MFT instead.
+pub fn walk_links(root: &str, follow: bool) -> Vec<LinkInfo> {
+    let mounts = mount_points();
+    let root_path = Path::new(root);
+    let mut visited = HashSet::new();
+    let mut links = Vec::new();
+
+    for entry in WalkDir::new(root).min_depth(1).into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        let tag = reparse_tag(path);
+        let kind = if let Some(tag) = tag {
+            let target = fs::read_link(path).ok().map(|t| t.to_string_lossy().to_string());
+            classify_reparse_tag(tag, target.as_deref())
+        } else if entry.path_is_symlink() {
+            LinkKind::Symlink
+        } else if entry.file_type().is_dir() && mounts.contains(path) {
+            LinkKind::MountPoint
+        } else {
+            continue;
+        };
+
+        let target = fs::read_link(path).ok().map(|t| t.to_string_lossy().to_string());
+        let (status, followed_bytes) = if entry.path_is_symlink() {
+            check_link(path, root_path, follow, &mut visited)
+        } else {
+            // Linux mount points are walked like any folder, their files are in the scan
+            (if is_path_link(kind) { LinkStatus::Ok } else { LinkStatus::Unknown }, None)
+        };
+        links.push(LinkInfo {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            target,
+            status,
+            followed_bytes,
+        });
+    }
+    links.sort_by(|a, b| a.path.cmp(&b.path));
+    links
+}
+
+/// Prints up to `count` links with their targets, then counts per kind and the broken ones.
+pub fn print_links(links: &[LinkInfo], count: usize) {
+    for link in links.iter().take(count) {
+        let status = match link.status {
+            LinkStatus::Ok => "",
+            LinkStatus::Broken => "  [broken]",
+            LinkStatus::Cycle => "  [cycle]",
+            LinkStatus::Unknown => "",
+        };
+        let followed = link
+            .followed_bytes
+            .filter(|&bytes| bytes > 0)
+            .map(|bytes| format!("  (+{:.2} MB outside the scan)", bytes as f64 / 1_048_576.0))
+            .unwrap_or_default();
+        println!(
+            "{:<18} {} -> {}{}{}",
+            link.kind.to_string(),
+            link.path,
+            link.target.as_deref().unwrap_or("-"),
+            status,
+            followed
+        );
+    }
+
+    let mut kinds: HashMap<LinkKind, usize> = HashMap::new();
+    for link in links {
+        *kinds.entry(link.kind).or_default() += 1;
+    }
+    let mut kinds: Vec<_> = kinds.into_iter().collect();
+    kinds.sort_by_key(|(_, found)| std::cmp::Reverse(*found));
+
+    println!("\nSummary ({} total):", links.len());
+    for (kind, found) in kinds {
+        println!("{:<18}: {}", kind.to_string(), found);
+    }
+    let broken = links.iter().filter(|l| l.status == LinkStatus::Broken).count();
+    let cycles = links.iter().filter(|l| l.status == LinkStatus::Cycle).count();
+    println!("broken: {}, pointing at their own parent folders: {}", broken, cycles);
+    let followed: u64 = links.iter().filter_map(|l| l.followed_bytes).sum();
+    if links.iter().any(|l| l.followed_bytes.is_some()) {
+        println!("followed links reach {:.2} GB outside the scanned tree", followed as f64 / 1_073_741_824.0);
+    }
+}
+
+#[cfg(test)]
+mod links_tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_reparse_tag() {
+        assert_eq!(classify_reparse_tag(IO_REPARSE_TAG_SYMLINK, Some("D:\\x")), LinkKind::Symlink);
+        assert_eq!(classify_reparse_tag(IO_REPARSE_TAG_MOUNT_POINT, Some("C:\\Users\\me\\AppData\\Roaming")), LinkKind::Junction);
+        assert_eq!(
+            classify_reparse_tag(IO_REPARSE_TAG_MOUNT_POINT, Some("Volume{3c1a0e3d-0000-0000-0000-100000000000}\\")),
+            LinkKind::MountPoint
+        );
+        assert_eq!(classify_reparse_tag(0x9000_601A, None), LinkKind::CloudPlaceholder);
+        assert_eq!(classify_reparse_tag(IO_REPARSE_TAG_DEDUP, None), LinkKind::Dedup);
+        assert_eq!(classify_reparse_tag(0x8000_0017, None), LinkKind::Other(0x8000_0017));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_and_cycles() {
+        use crate::utility::scratch::ScratchDir;
+        use std::os::unix::fs::symlink;
+        let base = ScratchDir::new("links");
+        let (root, outside) = (base.join("root"), base.join("outside"));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("big.bin"), vec![1u8; 4096]).unwrap();
+        symlink(&outside, root.join("to_outside")).unwrap();
+        symlink(&outside, root.join("sub").join("again")).unwrap();
+        symlink(&root, root.join("sub").join("loop")).unwrap();
+        symlink(base.join("missing"), root.join("dangling")).unwrap();
+
+        let links = walk_links(&root.to_string_lossy(), true);
+        let by_name = |name: &str| links.iter().find(|l| l.path.ends_with(name)).unwrap();
+        assert_eq!(links.len(), 4);
+        assert_eq!(by_name("dangling").status, LinkStatus::Broken);
+        assert_eq!(by_name("loop").status, LinkStatus::Cycle);
+        // the outside folder counts once, no matter how many links lead there
+        let followed: u64 = links.iter().filter_map(|l| l.followed_bytes).sum();
+        assert_eq!(followed, 4096);
+    }
+}
@@ -0,0 +1,329 @@
+// the flags every listing command takes: how many rows, which sizes, in what order and from where.
+//
+//   largest-files C --top 50 --min-size 1gb --max-size 10gb --sort modified --reverse --path c:/users
+//
+// both backends hand their rows to `ListingOptions::finish`, so the flags mean the same thing
+// whichever one runs the command, and a bad value is a usage error before anything is scanned
+use super::ignore::CASE_INSENSITIVE;
+use super::query::{Query, Queryable};
+use crate::utility::constants::{OLD_LARGE_FILES_AGE, RECENT_LARGE_FILES_AGE};
+use crate::utility::utils::{parse_duration, parse_size};
+use std::{borrow::Cow, cmp::Ordering};
+
+/// rows a listing shows without `--top`
+pub const DEFAULT_TOP: usize = 20;
+
+pub const TOP_FLAG: &str = "--top";
+pub const MIN_SIZE_FLAG: &str = "--min-size";
+pub const MAX_SIZE_FLAG: &str = "--max-size";
+pub const SORT_FLAG: &str = "--sort";
+pub const REVERSE_FLAG: &str = "--reverse";
+pub const PATH_FLAG: &str = "--path";
+pub const AGE_FLAG: &str = "--age";
+
+/// What a listing is ordered by. Sizes, dates and counts go largest/newest first, names A to Z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Size,
+    Name,
+    Modified,
+    Count,
+}
+
+impl SortKey {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "size" => Some(SortKey::Size),
+            "name" => Some(SortKey::Name),
+            "modified" | "date" => Some(SortKey::Modified),
+            "count" => Some(SortKey::Count),
+            _ => None,
+        }
+    }
+}
+
+/// One row of a listing, as much of it as the flags look at.
+pub struct Fields<'a> {
+    text: Cow<'a, str>,
+    // file types aren't anywhere on disk, --path leaves them alone
+    is_path: bool,
+    size: u64,
+    modified: Option<i64>,
+    count: Option<usize>,
+}
+
+impl<'a> Fields<'a> {
+    /// a file or folder, sorted by name on its last component
+    pub fn path(path: impl Into<Cow<'a, str>>, size: u64) -> Self {
+        Fields { text: path.into(), is_path: true, size, modified: None, count: None }
+    }
+
+    /// a row that isn't a place on disk, like a file type
+    pub fn named(name: impl Into<Cow<'a, str>>, size: u64) -> Self {
+        Fields { text: name.into(), is_path: false, size, modified: None, count: None }
+    }
+
+    /// a scanned file, whichever backend it came from
+    pub fn entry(entry: &'a impl Queryable) -> Self {
+        Fields::path(entry.path(), entry.size()).modified(entry.modified())
+    }
+
+    pub fn modified(mut self, modified: Option<i64>) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    fn name(&self) -> &str {
+        if self.is_path {
+            self.text.rsplit(['/', '\\']).find(|part| !part.is_empty()).unwrap_or(&self.text)
+        } else {
+            &self.text
+        }
+    }
+}
+
+// what a row is sorted on, taken out of it so the row can move
+enum Key {
+    Number(Option<i64>),
+    Text(String),
+}
+
+impl Key {
+    fn of(fields: &Fields, sort: SortKey) -> Self {
+        match sort {
+            SortKey::Size => Key::Number(Some(fields.size as i64)),
+            SortKey::Name => Key::Text(fields.name().to_lowercase()),
+            SortKey::Modified => Key::Number(fields.modified),
+            SortKey::Count => Key::Number(fields.count.map(|count| count as i64)),
+        }
+    }
+
+    // largest first with rows missing the value last, names A to Z
+    fn order(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Key::Number(a), Key::Number(b)) => b.cmp(a),
+            (Key::Text(a), Key::Text(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// The listing flags of a command, taken out of it wherever they appear.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListingOptions {
+    pub top: Option<usize>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub sort: Option<SortKey>,
+    pub reverse: bool,
+    /// lowercase with forward slashes, with the drive (`c:/users`) or without (`/users`)
+    pub path: Option<String>,
+    /// the window of recent-large-files and old-large-files, like `90d`
+    pub age: Option<String>,
+}
+
+impl ListingOptions {
+    /// takes the listing flags out of a command, wherever they appear in it
+    pub fn take_flags(words: &mut Vec<String>) -> Result<Self, String> {
+        let mut options = ListingOptions::default();
+        let mut rest = Vec::with_capacity(words.len());
+        let mut iter = words.drain(..);
+        while let Some(word) = iter.next() {
+            match word.to_lowercase().as_str() {
+                TOP_FLAG => match iter.next().and_then(|n| n.parse::<usize>().ok()) {
+                    Some(top) if top > 0 => options.top = Some(top),
+                    _ => return Err(format!("{} needs a number above 0", TOP_FLAG)),
+                },
+                MIN_SIZE_FLAG | MAX_SIZE_FLAG => match iter.next().as_deref().and_then(parse_size) {
+                    Some(size) if word.eq_ignore_ascii_case(MIN_SIZE_FLAG) => options.min_size = Some(size),
+                    Some(size) => options.max_size = Some(size),
+                    None => return Err(format!("{} needs a size like 500mb or 2gb", word)),
+                },
+                SORT_FLAG => match iter.next().map(|sort| sort.to_lowercase()).as_deref().and_then(SortKey::parse) {
+                    Some(sort) => options.sort = Some(sort),
+                    None => return Err(format!("{} needs size, name, modified or count", SORT_FLAG)),
+                },
+                REVERSE_FLAG => options.reverse = true,
+                PATH_FLAG => match iter.next() {
+                    Some(path) if !path.starts_with("--") => options.path = Some(normalize_prefix(&path)),
+                    _ => return Err(format!("{} needs a folder like c:/users or /users", PATH_FLAG)),
+                },
+                AGE_FLAG => match iter.next().map(|age| age.to_lowercase()) {
+                    Some(age) if parse_duration(&age).is_some_and(|secs| secs > 0) => options.age = Some(age),
+                    _ => return Err(format!("{} needs an age like 12h, 30d, 2w, 6mo or 1y", AGE_FLAG)),
+                },
+                _ => rest.push(word),
+            }
+        }
+        drop(iter);
+        if options.min_size.zip(options.max_size).is_some_and(|(min, max)| min > max) {
+            return Err(format!("{} is larger than {}", MIN_SIZE_FLAG, MAX_SIZE_FLAG));
+        }
+        *words = rest;
+        Ok(options)
+    }
+
+    /// whether anything but `--top` was given, those flags only work on listing commands
+    pub fn filters(&self) -> bool {
+        self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.sort.is_some()
+            || self.reverse
+            || self.path.is_some()
+            || self.age.is_some()
+    }
+
+    /// rows to show, `--top` or the default
+    pub fn top(&self) -> usize {
+        self.top_or(DEFAULT_TOP)
+    }
+
+    /// `--top`, or a command's own default when it isn't given
+    pub fn top_or(&self, top: usize) -> usize {
+        self.top.unwrap_or(top)
+    }
+
+    /// the same flags, with `min_size` when no `--min-size` was given
+    pub fn min_size_or(&self, min_size: u64) -> Self {
+        ListingOptions { min_size: Some(self.min_size.unwrap_or(min_size)), ..self.clone() }
+    }
+
+    /// whether a file or folder is under `--path` (always without one)
+    pub fn under_path(&self, path: &str) -> bool {
+        let Some(prefix) = &self.path else {
+            return true;
+        };
+        let path = fold_case(path.replace('\\', "/"));
+        let path = match path.split_once(':') {
+            Some((_, rest)) if !prefix.contains(':') => rest,
+            _ => &path,
+        };
+        path.strip_prefix(prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'))
+    }
+
+    /// the window of the recent or old large files listing
+    pub fn age(&self, recent: bool) -> &str {
+        match &self.age {
+            Some(age) => age,
+            None if recent => RECENT_LARGE_FILES_AGE,
+            None => OLD_LARGE_FILES_AGE,
+        }
+    }
+
+    /// the recent or old large files listing as a query over the scan
+    pub fn age_query(&self, recent: bool) -> Query {
+        let newer = if recent { ">" } else { "<" };
+        let query = format!("type = file and modified {} -{} order by size desc", newer, self.age(recent));
+        Query::parse(&query).expect("ages are checked when the flag is taken")
+    }
+
+    /// Keeps the rows within the size range and under `--path`, orders them by `--sort`
+    /// (or `sort`) and cuts them to `--top`. `fields` says what a row holds.
+    pub fn finish<T>(&self, rows: Vec<T>, sort: SortKey, fields: impl Fn(&T) -> Fields<'_>) -> Vec<T> {
+        let sort = self.sort.unwrap_or(sort);
+        let mut kept: Vec<(Key, T)> = rows
+            .into_iter()
+            .filter_map(|row| {
+                let (keep, key) = {
+                    let fields = fields(&row);
+                    (self.keeps(&fields), Key::of(&fields, sort))
+                };
+                keep.then_some((key, row))
+            })
+            .collect();
+        kept.sort_by(|a, b| {
+            let order = a.0.order(&b.0);
+            if self.reverse { order.reverse() } else { order }
+        });
+        kept.into_iter().take(self.top()).map(|(_, row)| row).collect()
+    }
+
+    fn keeps(&self, fields: &Fields) -> bool {
+        self.min_size.is_none_or(|min| fields.size >= min)
+            && self.max_size.is_none_or(|max| fields.size <= max)
+            && (!fields.is_path || self.under_path(&fields.text))
+    }
+}
+
+// paths only match case-insensitively where the file system does, like the ignore patterns
+fn fold_case(path: String) -> String {
+    if CASE_INSENSITIVE { path.to_lowercase() } else { path }
+}
+
+// `C:\Users\` and `C:/Users` are the same prefix (and `c:/users` too on Windows),
+// and one without a drive starts at the root
+fn normalize_prefix(path: &str) -> String {
+    let path = fold_case(path.replace('\\', "/"));
+    let trimmed = path.trim_end_matches('/');
+    match (trimmed.contains(':'), trimmed.starts_with('/')) {
+        (true, _) | (false, true) => trimmed.to_string(),
+        (false, false) => format!("/{}", trimmed),
+    }
+}
+
+#[cfg(test)]
+mod listing_tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_listing_flags() {
+        let mut args = words("largest-files c --top 5 --min-size 1mb --sort name --reverse --path C:\\Users\\");
+        let options = ListingOptions::take_flags(&mut args).unwrap();
+        assert_eq!(args, ["largest-files", "c"]);
+        assert_eq!(options.top(), 5);
+        assert_eq!(options.min_size, Some(1_048_576));
+        assert_eq!(options.sort, Some(SortKey::Name));
+        assert!(options.reverse && options.filters());
+        assert_eq!(options.path.as_deref(), Some(if CASE_INSENSITIVE { "c:/users" } else { "C:/Users" }));
+        assert!(options.under_path("C:\\Users\\me\\a.iso"));
+        assert!(!options.under_path("C:\\UsersOld\\a.iso"));
+        assert!(!options.under_path("D:\\Users\\a.iso"));
+
+        let options = ListingOptions::take_flags(&mut words("--path Users")).unwrap();
+        assert!(options.under_path("D:/Users/a.iso") && !options.under_path("D:/Windows/a.iso"));
+        // only a case-insensitive file system takes another spelling of the same folder
+        assert_eq!(options.under_path("D:/users/a.iso"), CASE_INSENSITIVE);
+        let options = ListingOptions::take_flags(&mut words("--path /home/Me")).unwrap();
+        assert!(options.under_path("/home/Me/a.iso"));
+        assert_eq!(options.under_path("/home/me/a.iso"), CASE_INSENSITIVE);
+        assert!(!ListingOptions::take_flags(&mut words("--top 3")).unwrap().filters());
+        assert_eq!(ListingOptions::default().age(true), RECENT_LARGE_FILES_AGE);
+
+        for bad in ["--top 0", "--top", "--min-size lots", "--sort colour", "--age soon", "--path --top",
+                    "--min-size 2gb --max-size 1gb"] {
+            assert!(ListingOptions::take_flags(&mut words(bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_listing_finish() {
+        fn fields<'a>(row: &'a (&str, u64, usize)) -> Fields<'a> {
+            Fields::path(row.0, row.1).count(row.2)
+        }
+        fn names(rows: Vec<(&str, u64, usize)>) -> Vec<&str> {
+            rows.into_iter().map(|row| row.0).collect()
+        }
+        let rows = vec![("c:/a/big.iso", 300, 2), ("c:/b/small.txt", 10, 9), ("d:/a/mid.mkv", 100, 5)];
+
+        let options = ListingOptions::default();
+        assert_eq!(names(options.finish(rows.clone(), SortKey::Size, fields)), ["c:/a/big.iso", "d:/a/mid.mkv", "c:/b/small.txt"]);
+        let options = ListingOptions { top: Some(1), sort: Some(SortKey::Count), ..Default::default() };
+        assert_eq!(names(options.finish(rows.clone(), SortKey::Size, fields)), ["c:/b/small.txt"]);
+        let options = ListingOptions { sort: Some(SortKey::Name), reverse: true, ..Default::default() };
+        assert_eq!(names(options.finish(rows.clone(), SortKey::Size, fields)), ["c:/b/small.txt", "d:/a/mid.mkv", "c:/a/big.iso"]);
+        let options = ListingOptions { min_size: Some(50), path: Some("/a".into()), ..Default::default() };
+        assert_eq!(names(options.finish(rows.clone(), SortKey::Size, fields)), ["c:/a/big.iso", "d:/a/mid.mkv"]);
+        let options = ListingOptions { max_size: Some(50), ..Default::default() }.min_size_or(20);
+        assert!(options.finish(rows, SortKey::Size, fields).is_empty());
+    }
+}
@@ -0,0 +1,668 @@
+// reports read straight from the raw MFT and $Bitmap of a volume or an NTFS image. they only
+// need ntfs_raw, so they run with any backend and, given an image, on any platform
+use super::ntfs_raw::{self, ClusterBitmap, FIRST_USER_RECORD, NtfsTimes, RawRecord, RawVolume};
+use super::path_check;
+use super::types::{
+    ClusterLayout, DeletedFile, FragmentationSummary, FragmentedFile, PathIssue, PathProblem, Recovery,
+    TimestampAnomaly, TimestampFlag,
+};
+use crate::utility::output::note;
+use crate::utility::utils::{
+    cleanup_path, filetime_now, filetime_to_string, filter_filename, format_size, validate_ntfs_source,
+};
+use std::{cmp::Reverse, collections::HashMap, error::Error};
+
+// (folder, average extents, file count)
+type FolderAverages = Vec<(String, f64, usize)>;
+
+/// Works out how much of a deleted record's data is still untouched on disk,
+/// by checking its clusters against the current `$Bitmap`.
+fn estimate_recovery(record: &RawRecord, bitmap: &ClusterBitmap) -> Recovery {
+    if record.resident_data {
+        return Recovery::Resident;
+    }
+
+    let total: u64 = record.data_runs.iter().filter(|r| r.lcn.is_some()).map(|r| r.length).sum();
+    if total == 0 {
+        return Recovery::Unknown;
+    }
+
+    let reallocated: u64 = record.data_runs.iter().map(|r| bitmap.allocated_in(r)).sum();
+    match reallocated {
+        0 => Recovery::Intact,
+        n if n >= total => Recovery::Overwritten,
+        n => Recovery::Partial(n as f64 / total as f64 * 100.0),
+    }
+}
+
+/// Reads the raw MFT of a volume (or image) and returns every file record that is
+/// no longer in use but still has a name, sorted by size.
+fn scan_deleted_files(source: &str) -> Result<Vec<DeletedFile>, Box<dyn Error>> {
+    let mut volume = RawVolume::open(source)?;
+    let bitmap = volume.read_bitmap()?;
+    let records = volume.scan_records()?;
+    let index = ntfs_raw::record_index(&records);
+
+    let mut deleted: Vec<DeletedFile> = records
+        .iter()
+        .filter(|r| !r.in_use && !r.is_directory && r.number >= FIRST_USER_RECORD)
+        .filter_map(|r| {
+            let name = r.file_name.as_ref()?;
+            // $STANDARD_INFORMATION is what explorer shows, $FILE_NAME is the fallback
+            let times = r.si_times.unwrap_or(name.times);
+            Some(DeletedFile {
+                record: r.number,
+                name: name.name.clone(),
+                parent_path: ntfs_raw::resolve_parent_path(&records, &index, r),
+                size: r.size,
+                created: filetime_to_string(times.created),
+                modified: filetime_to_string(times.modified),
+                recovery: estimate_recovery(r, &bitmap),
+            })
+        })
+        .collect();
+
+    deleted.sort_by_key(|f| Reverse(f.size));
+    Ok(deleted)
+}
+
+/// Checks one record's $STANDARD_INFORMATION times against its $FILE_NAME times.
+///
+/// SI times are easy to set from user mode, FN times are only written by the kernel,
+/// so SI being older than FN (or suspiciously round) is the classic timestomping tell.
+fn timestamp_flags(si: &NtfsTimes, fn_times: &NtfsTimes, volume_created: u64, now: u64) -> Vec<TimestampFlag> {
+    // one day of FILETIME ticks
+    const DAY: u64 = 864_000_000_000;
+    let mut flags = Vec::new();
+
+    let pairs = [
+        (si.created, fn_times.created, TimestampFlag::SiCreatedBeforeFn),
+        (si.modified, fn_times.modified, TimestampFlag::SiModifiedBeforeFn),
+        (si.mft_changed, fn_times.mft_changed, TimestampFlag::SiChangedBeforeFn),
+        (si.accessed, fn_times.accessed, TimestampFlag::SiAccessedBeforeFn),
+    ];
+    for (si_time, fn_time, flag) in pairs {
+        if si_time < fn_time {
+            flags.push(flag);
+        }
+    }
+
+    // tools that set times by hand usually only have whole seconds to give
+    if (si.created != 0 && si.created.is_multiple_of(10_000_000))
+        || (si.modified != 0 && si.modified.is_multiple_of(10_000_000))
+    {
+        flags.push(TimestampFlag::ZeroedSubseconds);
+    }
+    if volume_created != 0 && si.created.saturating_add(DAY) < volume_created {
+        flags.push(TimestampFlag::BeforeVolumeCreation);
+    }
+
+    let all = [si.created, si.modified, si.mft_changed, si.accessed];
+    if all.iter().any(|&t| t > now.saturating_add(DAY)) {
+        flags.push(TimestampFlag::InFuture);
+    }
+    if all.iter().any(|&t| t < DAY) {
+        flags.push(TimestampFlag::NullEpoch);
+    }
+    flags
+}
+
+/// Reads the raw MFT and returns every in-use record whose timestamps look tampered with,
+/// the ones with the most flags first.
+fn scan_timestamp_anomalies(source: &str) -> Result<Vec<TimestampAnomaly>, Box<dyn Error>> {
+    let mut volume = RawVolume::open(source)?;
+    let records = volume.scan_records()?;
+    let index = ntfs_raw::record_index(&records);
+
+    // $MFT gets created when the volume is formatted
+    let volume_created = index
+        .get(&0)
+        .and_then(|&i| records[i].si_times)
+        .map(|t| t.created)
+        .unwrap_or(0);
+    let now = filetime_now();
+
+    let mut anomalies: Vec<TimestampAnomaly> = records
+        .iter()
+        .filter(|r| r.in_use && r.number >= FIRST_USER_RECORD)
+        .filter_map(|r| {
+            let si = r.si_times?;
+            let name = r.file_name.as_ref()?;
+            let flags = timestamp_flags(&si, &name.times, volume_created, now);
+            if flags.is_empty() {
+                return None;
+            }
+            let parent = ntfs_raw::resolve_parent_path(&records, &index, r);
+            Some(TimestampAnomaly {
+                path: cleanup_path(&format!("{}\\{}", parent, name.name)),
+                si_created: filetime_to_string(si.created),
+                si_modified: filetime_to_string(si.modified),
+                fn_created: filetime_to_string(name.times.created),
+                fn_modified: filetime_to_string(name.times.modified),
+                flags,
+            })
+        })
+        .collect();
+
+    anomalies.sort_by_key(|a| Reverse(a.flags.len()));
+    Ok(anomalies)
+}
+
+/// Decodes the data runs of every in-use, non-resident file.
+///
+/// returns the fragmented files (most extents first), the volume-wide summary and
+/// the per-folder averages.
+fn scan_fragmentation(
+    source: &str,
+) -> Result<(Vec<FragmentedFile>, FragmentationSummary, FolderAverages), Box<dyn Error>> {
+    let mut volume = RawVolume::open(source)?;
+    let cluster_size = volume.cluster_size;
+    let records = volume.scan_records()?;
+    let index = ntfs_raw::record_index(&records);
+
+    let mut summary = FragmentationSummary::default();
+    let mut fragmented: Vec<FragmentedFile> = Vec::new();
+    let mut folders: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for record in records.iter().filter(|r| r.in_use && !r.is_directory && !r.resident_data) {
+        let extents = ntfs_raw::extents(&record.data_runs);
+        let Some(name) = record.file_name.as_ref().filter(|_| !extents.is_empty()) else {
+            continue;
+        };
+
+        let parent = ntfs_raw::resolve_parent_path(&records, &index, record);
+        let folder = folders.entry(parent.clone()).or_insert((0, 0));
+        folder.0 += extents.len();
+        folder.1 += 1;
+
+        summary.files += 1;
+        summary.total_extents += extents.len();
+        summary.total_bytes += record.size;
+        if extents.len() < 2 {
+            continue;
+        }
+
+        summary.fragmented_files += 1;
+        summary.fragmented_bytes += record.size;
+        let largest_gap = extents
+            .windows(2)
+            .map(|pair| (pair[0].0 + pair[0].1).abs_diff(pair[1].0))
+            .max()
+            .unwrap_or(0);
+        fragmented.push(FragmentedFile {
+            path: cleanup_path(&format!("{}\\{}", parent, name.name)),
+            size: record.size,
+            extents: extents.len(),
+            largest_gap: largest_gap * cluster_size,
+        });
+    }
+
+    fragmented.sort_by_key(|f| Reverse(f.extents));
+
+    // folders with only a couple of files make the averages pretty useless
+    let mut folder_averages: FolderAverages = folders
+        .into_iter()
+        .filter(|(_, (_, files))| *files >= 5)
+        .map(|(folder, (extents, files))| (folder, extents as f64 / files as f64, files))
+        .collect();
+    folder_averages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    Ok((fragmented, summary, folder_averages))
+}
+
+/// Reads `$Bitmap` and works out how the allocated and free clusters are laid out,
+/// including an ASCII map that is `rows` lines of `MAP_WIDTH` cells.
+fn scan_cluster_layout(source: &str, rows: usize) -> Result<ClusterLayout, Box<dyn Error>> {
+    const MAP_WIDTH: u64 = 64;
+
+    let mut volume = RawVolume::open(source)?;
+    let bitmap = volume.read_bitmap()?;
+    let total = bitmap.total_clusters;
+
+    // the MFT zone Windows keeps free for $MFT isn't stored anywhere, where $MFT ends and
+    // what's free behind it is
+    let mft_extents = volume.mft_extents();
+    let mft_growth_clusters = mft_extents
+        .last()
+        .map_or(0, |&(lcn, length)| bitmap.free_from(lcn.saturating_add(length)));
+    let mut layout = ClusterLayout {
+        cluster_size: volume.cluster_size,
+        total_clusters: total,
+        allocated_clusters: bitmap.allocated_range(0, total),
+        mft_size: volume.mft_size,
+        mft_extents,
+        mft_growth_clusters,
+        ..Default::default()
+    };
+
+    bitmap.free_runs(|lcn, length| {
+        layout.free_extents += 1;
+        if length > layout.largest_free_clusters {
+            layout.largest_free_lcn = lcn;
+            layout.largest_free_clusters = length;
+        }
+    });
+
+    // every cell covers an equal slice of the volume, shaded by how full that slice is
+    let cells = MAP_WIDTH * rows as u64;
+    let per_cell = total.div_ceil(cells).max(1);
+    let cell_chars: Vec<char> = (0..cells)
+        .map(|cell| {
+            let start = cell * per_cell;
+            if start >= total {
+                return ' ';
+            }
+            let end = (start + per_cell).min(total);
+            let used = bitmap.allocated_range(start, end) as f64 / (end - start) as f64;
+            match used {
+                0.0 => '.',
+                u if u < 0.34 => '-',
+                u if u < 0.67 => '+',
+                u if u < 1.0 => '=',
+                _ => '#',
+            }
+        })
+        .collect();
+    layout.map = cell_chars
+        .chunks(MAP_WIDTH as usize)
+        .map(|row| row.iter().collect())
+        .collect();
+
+    Ok(layout)
+}
+
+/// Checks every in-use name on the volume for things Win32 tools can't handle, hard links
+/// and 8.3 names included. Works on the raw $FILE_NAME attributes, so nothing gets
+/// cleaned up or lossily decoded first.
+fn scan_path_issues(source: &str) -> Result<Vec<PathProblem>, Box<dyn Error>> {
+    let mut volume = RawVolume::open(source)?;
+    let records = volume.scan_records()?;
+    let index = ntfs_raw::record_index(&records);
+    let live: Vec<&RawRecord> = records
+        .iter()
+        .filter(|r| r.in_use && r.number >= FIRST_USER_RECORD && r.file_name.is_some())
+        .collect();
+
+    // names that only differ by case inside one directory
+    let mut siblings: HashMap<(u64, String), usize> = HashMap::new();
+    for name in live.iter().flat_map(|r| r.names()) {
+        *siblings.entry((name.parent, name.name.to_lowercase())).or_insert(0) += 1;
+    }
+
+    let mut problems: Vec<PathProblem> = live
+        .into_iter()
+        .flat_map(|r| r.names().map(move |name| (r, name)))
+        .filter_map(|(r, name)| {
+            let path = format!("{}\\{}", ntfs_raw::resolve_folder_of(&records, &index, Some(name)), name.name);
+
+            let mut issues = path_check::name_issues(&name.name);
+            // measured as if the volume was mounted as a drive letter
+            if path_check::path_too_long(&format!("C:{}", path)) {
+                issues.insert(0, PathIssue::TooLong);
+            }
+            if !name.valid_utf16 {
+                issues.push(PathIssue::InvalidUtf16);
+            }
+            if siblings[&(name.parent, name.name.to_lowercase())] > 1 {
+                issues.push(PathIssue::CaseCollision);
+            }
+            if issues.is_empty() {
+                return None;
+            }
+            Some(PathProblem {
+                path,
+                size: r.size,
+                is_directory: r.is_directory,
+                issues,
+            })
+        })
+        .collect();
+
+    problems.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(problems)
+}
+
+/// Displays files that were deleted but whose MFT records haven't been reused yet.
+///
+/// # Arguments
+///
+/// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+/// * `count` - The number of deleted files to display in the results
+///
+/// # Examples
+///
+/// ```
+/// // Display the 2 largest deleted files in an image:
+/// print_deleted_files("D:/evidence/disk.img", 2).unwrap();
+/// // Output:
+/// // Deleted Files on D:/evidence/disk.img (Top 2 of 1843 by size):
+/// // holiday.mp4                    1.21 GB  Modified: 2024-03-02 11:20:45  [likely recoverable]
+/// //     in: \Users\username\Videos
+/// // notes.txt                      312 bytes  Modified: 2024-03-01 08:10:02  [resident in MFT]
+/// //     in: <orphan>\Old
+/// ```
+pub fn print_deleted_files(target: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    validate_ntfs_source(target, |source| {
+        note(format!("Reading raw MFT records from {}...", source));
+        let deleted = scan_deleted_files(source)?;
+
+        println!(
+            "Deleted Files on {} (Top {} of {} by size):",
+            source, count, deleted.len()
+        );
+        for file in deleted.into_iter().take(count) {
+            println!(
+                "{:<30} {}  Modified: {}  [{}]",
+                filter_filename(&file.name, true),
+                format_size(file.size),
+                file.modified.as_deref().unwrap_or("Unknown"),
+                file.recovery
+            );
+            println!("    in: {}", file.parent_path);
+        }
+        Ok(())
+    })
+}
+
+/// Displays files whose $STANDARD_INFORMATION and $FILE_NAME timestamps disagree
+/// in ways that usually point to timestomping.
+///
+/// # Arguments
+///
+/// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+/// * `count` - The number of flagged files to display in the results
+///
+/// # Examples
+///
+/// ```
+/// // Display the top flagged file on drive C:
+/// print_timestamp_anomalies("C", 1).unwrap();
+/// // Output:
+/// // Timestamp Anomalies on \\.\C: (Top 1 of 37):
+/// // \Users\username\AppData\Local\Temp\svc.exe
+/// //     SI Created: 2015-07-10 00:00:00  Modified: 2015-07-10 00:00:00
+/// //     FN Created: 2024-02-11 03:14:07  Modified: 2024-02-11 03:14:07
+/// //     [SI created earlier than FN] [SI modified earlier than FN] [zeroed sub-second precision]
+/// //
+/// // Summary:
+/// // SI created earlier than FN          : 21
+/// // ...
+/// ```
+pub fn print_timestamp_anomalies(target: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    validate_ntfs_source(target, |source| {
+        note(format!("Comparing MFT timestamps on {}...", source));
+        let anomalies = scan_timestamp_anomalies(source)?;
+
+        println!(
+            "Timestamp Anomalies on {} (Top {} of {}):",
+            source, count, anomalies.len()
+        );
+        for anomaly in anomalies.iter().take(count) {
+            println!("{}", anomaly.path);
+            println!(
+                "    SI Created: {}  Modified: {}",
+                anomaly.si_created.as_deref().unwrap_or("Unknown"),
+                anomaly.si_modified.as_deref().unwrap_or("Unknown")
+            );
+            println!(
+                "    FN Created: {}  Modified: {}",
+                anomaly.fn_created.as_deref().unwrap_or("Unknown"),
+                anomaly.fn_modified.as_deref().unwrap_or("Unknown")
+            );
+            let flags: Vec<String> = anomaly.flags.iter().map(|f| format!("[{}]", f)).collect();
+            println!("    {}", flags.join(" "));
+        }
+
+        // count per flag so one noisy rule doesn't hide the rest
+        let mut summary: HashMap<TimestampFlag, usize> = HashMap::new();
+        for flag in anomalies.iter().flat_map(|a| a.flags.iter()) {
+            *summary.entry(*flag).or_insert(0) += 1;
+        }
+        let mut summary: Vec<_> = summary.into_iter().collect();
+        summary.sort_by_key(|(_, flagged)| Reverse(*flagged));
+
+        println!("\nSummary:");
+        for (flag, flagged) in summary {
+            println!("{:<36}: {}", flag.to_string(), flagged);
+        }
+        Ok(())
+    })
+}
+
+/// Displays how fragmented a volume is, along with its most fragmented files and folders.
+///
+/// # Arguments
+///
+/// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+/// * `count` - The number of files and folders to display in the results
+///
+/// # Examples
+///
+/// ```
+/// // Display the fragmentation of drive D:
+/// print_fragmentation("D", 2).unwrap();
+/// // Output:
+/// // Fragmentation Report for \\.\D:
+/// // Files with data on disk : 214022
+/// // Fragmented files        : 1840 (0.86%)
+/// // Average extents         : 1.21 per file
+/// // Fragmentation score     : 12.40% of the data sits in fragmented files
+/// //
+/// // Most Fragmented Files (Top 2):
+/// // \Archive\backup_2023.vhdx                         4120 extents  120.55 GB  largest gap: 310.20 GB
+/// // \Archive\logs\server.log                          988 extents  1.20 GB  largest gap: 2.41 GB
+/// //
+/// // Most Fragmented Folders (Top 2 by average extents, 5+ files):
+/// // \Archive\logs                                      182.30 avg extents  (14 files)
+/// // \Archive                                            51.02 avg extents  (87 files)
+/// ```
+pub fn print_fragmentation(target: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    validate_ntfs_source(target, |source| {
+        note(format!("Decoding data runs on {}...", source));
+        let (files, summary, folders) = scan_fragmentation(source)?;
+
+        let percent = |part: usize| if summary.files == 0 { 0.0 } else { part as f64 / summary.files as f64 * 100.0 };
+        println!("Fragmentation Report for {}", source);
+        println!("Files with data on disk : {}", summary.files);
+        println!(
+            "Fragmented files        : {} ({:.2}%)",
+            summary.fragmented_files,
+            percent(summary.fragmented_files)
+        );
+        println!(
+            "Average extents         : {:.2} per file",
+            percent(summary.total_extents) / 100.0
+        );
+        println!(
+            "Fragmentation score     : {:.2}% of the data sits in fragmented files",
+            summary.score()
+        );
+
+        println!("\nMost Fragmented Files (Top {}):", count);
+        for file in files.into_iter().take(count) {
+            println!(
+                "{:<50} {} extents  {}  largest gap: {}",
+                file.path,
+                file.extents,
+                format_size(file.size),
+                format_size(file.largest_gap)
+            );
+        }
+
+        println!("\nMost Fragmented Folders (Top {} by average extents, 5+ files):", count);
+        for (folder, average, files) in folders.into_iter().take(count) {
+            println!("{:<50} {:.2} avg extents  ({} files)", folder, average, files);
+        }
+        Ok(())
+    })
+}
+
+/// Displays an ASCII map of allocated vs free clusters plus the free space layout.
+///
+/// # Arguments
+///
+/// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+/// * `rows` - The number of map rows to draw (each row is 64 cells wide)
+///
+/// # Examples
+///
+/// ```
+/// // Draw a 4 row map of drive C:
+/// print_cluster_map("C", 4).unwrap();
+/// // Output:
+/// // Cluster Map for \\.\C: (4 KB clusters, 1.82 GB per cell)
+/// // ################################################################
+/// // ###########====+++++++------.....................................
+/// // ....................--------------------.......................
+/// // ...............................................===############
+/// // legend: . free  - mostly free  + half used  = mostly used  # full
+/// //
+/// // Allocated space      : 212.40 GB (45.60%)
+/// // Free space           : 253.36 GB in 48213 free extents
+/// // Largest free region  : 118.02 GB at cluster 63211520
+/// // MFT size             : 1.21 GB in 2 extents from cluster 786432
+/// // Free after the MFT   : 57.01 GB
+/// ```
+pub fn print_cluster_map(target: &str, rows: usize) -> Result<(), Box<dyn Error>> {
+    validate_ntfs_source(target, |source| {
+        note(format!("Reading $Bitmap from {}...", source));
+        let layout = scan_cluster_layout(source, rows)?;
+        let clusters = |count: u64| format_size(count * layout.cluster_size);
+        let free = layout.total_clusters - layout.allocated_clusters;
+
+        println!(
+            "Cluster Map for {} ({} clusters, {} per cell)",
+            source,
+            format_size(layout.cluster_size),
+            clusters(layout.total_clusters.div_ceil(64 * rows.max(1) as u64))
+        );
+        for row in &layout.map {
+            println!("{}", row);
+        }
+        println!("legend: . free  - mostly free  + half used  = mostly used  # full\n");
+
+        println!(
+            "Allocated space      : {} ({:.2}%)",
+            clusters(layout.allocated_clusters),
+            layout.allocated_clusters as f64 / layout.total_clusters.max(1) as f64 * 100.0
+        );
+        println!(
+            "Free space           : {} in {} free extents",
+            clusters(free),
+            layout.free_extents
+        );
+        println!(
+            "Largest free region  : {} at cluster {}",
+            clusters(layout.largest_free_clusters),
+            layout.largest_free_lcn
+        );
+        println!(
+            "MFT size             : {} in {} extents from cluster {}",
+            format_size(layout.mft_size),
+            layout.mft_extents.len(),
+            layout.mft_extents.first().map_or(0, |&(lcn, _)| lcn)
+        );
+        println!("Free after the MFT   : {}", clusters(layout.mft_growth_clusters));
+        Ok(())
+    })
+}
+
+/// Displays names and paths that NTFS accepts but Explorer, shells and most tools don't:
+/// over-long paths, trailing dots or spaces, reserved device names, broken UTF-16,
+/// case-only collisions and control characters.
+///
+/// # Arguments
+///
+/// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+/// * `count` - The number of flagged entries to display in the results
+///
+/// # Examples
+///
+/// ```
+/// // Display the first 2 flagged entries on drive D:
+/// print_path_issues("D", 2).unwrap();
+/// // Output:
+/// // Path Issues on \\.\D: (2 of 14):
+/// // \Projects\build\aux.c                              4.12 KB  [reserved device name]
+/// // \Projects\Readme.md                                 1.02 KB  [differs only by case from a sibling]
+/// //
+/// // Summary:
+/// // differs only by case from a sibling : 9  (1.40 MB)
+/// // ...
+/// ```
+pub fn print_path_issues(target: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    validate_ntfs_source(target, |source| {
+        note(format!("Checking names on {}...", source));
+        let problems = scan_path_issues(source)?;
+
+        println!("Path Issues on {} ({} of {}):", source, count.min(problems.len()), problems.len());
+        for problem in problems.iter().take(count) {
+            let issues: Vec<String> = problem.issues.iter().map(|i| format!("[{}]", i)).collect();
+            let size = if problem.is_directory {
+                "<dir>".to_string()
+            } else {
+                format_size(problem.size)
+            };
+            // escape control characters instead of letting them garble the terminal
+            let path: String = problem
+                .path
+                .chars()
+                .map(|c| if c.is_control() { c.escape_debug().to_string() } else { c.to_string() })
+                .collect();
+            println!("{:<50} {:>12}  {}", path, size, issues.join(" "));
+        }
+
+        let mut summary: HashMap<PathIssue, (usize, u64)> = HashMap::new();
+        for problem in &problems {
+            for issue in &problem.issues {
+                let entry = summary.entry(*issue).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += problem.size;
+            }
+        }
+        let mut summary: Vec<_> = summary.into_iter().collect();
+        summary.sort_by_key(|(_, (flagged, _))| Reverse(*flagged));
+
+        println!("\nSummary:");
+        for (issue, (flagged, bytes)) in summary {
+            println!("{:<36}: {}  ({})", issue.to_string(), flagged, format_size(bytes));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod mft_reports_tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_flags() {
+        const DAY: u64 = 864_000_000_000;
+        // somewhere in 2022, with sub-second ticks like the kernel writes them
+        const T: u64 = 133_000_000_000_000_123;
+        let (volume_created, now) = (T - 100 * DAY, T + 10 * DAY);
+        let times = |t: u64| NtfsTimes { created: t, modified: t, mft_changed: t, accessed: t };
+        let flags = |si: NtfsTimes, fn_times: NtfsTimes| timestamp_flags(&si, &fn_times, volume_created, now);
+
+        assert!(flags(times(T), times(T)).is_empty());
+        assert_eq!(flags(NtfsTimes { created: T - 5, ..times(T) }, times(T)), [TimestampFlag::SiCreatedBeforeFn]);
+        assert_eq!(flags(NtfsTimes { modified: T - 5, ..times(T) }, times(T)), [TimestampFlag::SiModifiedBeforeFn]);
+        assert_eq!(flags(NtfsTimes { mft_changed: T - 5, ..times(T) }, times(T)), [TimestampFlag::SiChangedBeforeFn]);
+        assert_eq!(flags(NtfsTimes { accessed: T - 5, ..times(T) }, times(T)), [TimestampFlag::SiAccessedBeforeFn]);
+
+        let round = NtfsTimes { modified: 133_000_000_000_000_000, ..times(T) };
+        assert_eq!(flags(round, round), [TimestampFlag::ZeroedSubseconds]);
+        let early = NtfsTimes { created: volume_created - 2 * DAY, ..times(T) };
+        assert_eq!(flags(early, early), [TimestampFlag::BeforeVolumeCreation]);
+        let future = NtfsTimes { accessed: now + 2 * DAY, ..times(T) };
+        assert_eq!(flags(future, future), [TimestampFlag::InFuture]);
+
+        // 1601-01-01, FILETIME zero
+        let epoch = NtfsTimes { created: 0, ..times(T) };
+        assert_eq!(flags(epoch, epoch), [TimestampFlag::BeforeVolumeCreation, TimestampFlag::NullEpoch]);
+        // as far in the future as FILETIME goes, and a clock just as broken
+        let far = NtfsTimes { created: u64::MAX, ..times(T) };
+        assert_eq!(flags(far, far), [TimestampFlag::InFuture]);
+        assert!(timestamp_flags(&times(T), &times(T), volume_created, u64::MAX).is_empty());
+    }
+}
@@ -1,8 +1,26 @@
 #![allow(unused_imports)]
+pub mod attributes;
+pub mod compression;
+pub mod empty_tree;
+pub mod health_check;
+pub mod ignore;
+pub mod links;
+pub mod listing;
+pub mod mft_reports;
+pub mod name_index;
+#[cfg(target_os = "windows")]
 pub mod ntfs_explorer;
+pub mod ntfs_raw;
+pub mod path_check;
+pub mod perm_audit;
+pub mod query;
+pub mod scan_errors;
+pub mod search;
 pub mod storage;
 pub mod types;
+pub mod zero_regions;
 // mod testshelf;
 
 pub use storage::StorageAnalyser;
+#[cfg(target_os = "windows")]
 pub use ntfs_explorer::NtfsExplorer;
\ No newline at end of file
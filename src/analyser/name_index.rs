@@ -0,0 +1,388 @@
+// optional trigram index over the cached scan entries, so `find` only has to run
+// its matcher on entries that contain every trigram of the pattern's literal parts.
+//
+// an index is saved per drive in outputs/name-index and picked up again by the next session,
+// where the first scan of the drive only has to bring it up to date instead of rebuilding it
+use super::types::ScanEntry;
+use crate::utility::{output::note, utils::app_file};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    mem::size_of,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+pub const NAME_INDEX_DIR: &str = "outputs/name-index";
+// bumped whenever the layout of a saved index changes
+const INDEX_MAGIC: &[u8; 8] = b"TRGMIDX1";
+
+pub struct TrigramIndex {
+    // trigram -> sorted doc ids containing it
+    postings: HashMap<u32, Vec<u32>>,
+    // path with forward slashes -> doc id, used to carry docs over on a rescan
+    docs: HashMap<String, u32>,
+    // doc id -> position in the entry list, None once the entry is gone
+    doc_entry: Vec<Option<u32>>,
+    live: usize,
+    pub build_time: Duration,
+}
+
+/// path with forward slashes, case kept so two files differing only in case stay two docs
+fn doc_key(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// the path and name as `find` compares them, lowercased. the \0 keeps trigrams from spanning the two
+fn doc_text(key: &str, entry: &ScanEntry) -> String {
+    format!("{}\0{}", key, entry.name).to_lowercase()
+}
+
+fn trigrams(text: &str) -> impl Iterator<Item = u32> + '_ {
+    text.as_bytes()
+        .windows(3)
+        .map(|w| (w[0] as u32) << 16 | (w[1] as u32) << 8 | w[2] as u32)
+}
+
+impl TrigramIndex {
+    pub fn build(entries: &[ScanEntry]) -> Self {
+        let start = Instant::now();
+        let mut index = TrigramIndex {
+            postings: HashMap::new(),
+            docs: HashMap::with_capacity(entries.len()),
+            doc_entry: Vec::with_capacity(entries.len()),
+            live: 0,
+            build_time: Duration::ZERO,
+        };
+        for (position, entry) in entries.iter().enumerate() {
+            index.add(entry, position as u32);
+        }
+        index.build_time = start.elapsed();
+        index
+    }
+
+    fn add(&mut self, entry: &ScanEntry, position: u32) {
+        let key = doc_key(&entry.path);
+        let doc = self.doc_entry.len() as u32;
+
+        // the name goes in too in case a backend leaves it out of the path
+        let mut grams: Vec<u32> = trigrams(&doc_text(&key, entry)).collect();
+        grams.sort_unstable();
+        grams.dedup();
+
+        // a path seen twice keeps only its latest doc, the old one leaves every posting list
+        if let Some(old) = self.docs.get(&key).copied() {
+            for gram in &grams {
+                if let Some(list) = self.postings.get_mut(gram) {
+                    list.retain(|&d| d != old);
+                }
+            }
+            if self.doc_entry[old as usize].take().is_some() {
+                self.live -= 1;
+            }
+        }
+
+        for gram in grams {
+            self.postings.entry(gram).or_default().push(doc);
+        }
+        self.docs.insert(key, doc);
+        self.doc_entry.push(Some(position));
+        self.live += 1;
+    }
+
+    /// Brings the index in line with a refreshed entry list. Entries that are still
+    /// there just get their position updated, new ones are added and gone ones are
+    /// dropped from the results. Rebuilds from scratch once most docs are dead.
+    ///
+    /// returns (added, removed)
+    pub fn update(&mut self, entries: &[ScanEntry]) -> (usize, usize) {
+        let start = Instant::now();
+        let mut seen = vec![false; self.doc_entry.len()];
+        let mut added = 0;
+
+        for (position, entry) in entries.iter().enumerate() {
+            match self.docs.get(&doc_key(&entry.path)) {
+                Some(&doc) if !seen[doc as usize] => {
+                    seen[doc as usize] = true;
+                    self.doc_entry[doc as usize] = Some(position as u32);
+                }
+                _ => {
+                    self.add(entry, position as u32);
+                    seen.push(true);
+                    added += 1;
+                }
+            }
+        }
+
+        let mut removed = 0;
+        for (doc, seen) in seen.into_iter().enumerate() {
+            if !seen && self.doc_entry[doc].take().is_some() {
+                removed += 1;
+            }
+        }
+        self.live = self.doc_entry.iter().filter(|position| position.is_some()).count();
+        let doc_entry = &self.doc_entry;
+        self.docs.retain(|_, doc| doc_entry[*doc as usize].is_some());
+
+        if self.doc_entry.len() > self.live * 2 {
+            *self = Self::build(entries);
+        } else {
+            self.build_time = start.elapsed();
+        }
+        (added, removed)
+    }
+
+    /// Positions of the entries that could match a pattern made of these literals,
+    /// in entry order. `None` means the literals are too short to narrow anything down.
+    pub fn candidates(&self, literals: &[String]) -> Option<Vec<usize>> {
+        let mut grams: Vec<u32> = literals
+            .iter()
+            .flat_map(|literal| trigrams(literal).collect::<Vec<_>>())
+            .collect();
+        if grams.is_empty() {
+            return None;
+        }
+        grams.sort_unstable();
+        grams.dedup();
+
+        let mut lists: Vec<&Vec<u32>> = Vec::with_capacity(grams.len());
+        for gram in &grams {
+            match self.postings.get(gram) {
+                Some(list) => lists.push(list),
+                None => return Some(Vec::new()),
+            }
+        }
+        // intersect starting from the rarest trigram
+        lists.sort_by_key(|list| list.len());
+        let mut docs: Vec<u32> = lists[0].clone();
+        for list in &lists[1..] {
+            docs.retain(|doc| list.binary_search(doc).is_ok());
+            if docs.is_empty() {
+                break;
+            }
+        }
+
+        let mut positions: Vec<usize> = docs
+            .into_iter()
+            .filter_map(|doc| self.doc_entry[doc as usize].map(|p| p as usize))
+            .collect();
+        positions.sort_unstable();
+        Some(positions)
+    }
+
+    /// number of entries currently indexed
+    pub fn entry_count(&self) -> usize {
+        self.live
+    }
+
+    pub fn trigram_count(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// rough heap usage in bytes, hash map overhead is estimated at one extra word per slot
+    pub fn memory_usage(&self) -> usize {
+        let slot = size_of::<usize>();
+        let postings: usize = self
+            .postings
+            .values()
+            .map(|list| list.capacity() * size_of::<u32>() + size_of::<Vec<u32>>() + size_of::<u32>() + slot)
+            .sum();
+        let docs: usize = self
+            .docs
+            .keys()
+            .map(|key| key.capacity() + size_of::<String>() + size_of::<u32>() + slot)
+            .sum();
+        postings + docs + self.doc_entry.capacity() * size_of::<Option<u32>>()
+    }
+
+    /// Writes the docs and postings to the drive's index file. Entry positions aren't kept,
+    /// they only mean something for the entry list of this session.
+    pub fn save(&self, drive: &str) -> io::Result<()> {
+        let path = index_file(drive);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("partial");
+        let mut out = BufWriter::new(File::create(&partial)?);
+        self.write(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(INDEX_MAGIC)?;
+        out.write_all(&(self.doc_entry.len() as u32).to_le_bytes())?;
+        out.write_all(&(self.docs.len() as u32).to_le_bytes())?;
+        for (key, doc) in &self.docs {
+            out.write_all(&doc.to_le_bytes())?;
+            out.write_all(&(key.len() as u32).to_le_bytes())?;
+            out.write_all(key.as_bytes())?;
+        }
+        out.write_all(&(self.postings.len() as u32).to_le_bytes())?;
+        for (gram, list) in &self.postings {
+            out.write_all(&gram.to_le_bytes())?;
+            out.write_all(&(list.len() as u32).to_le_bytes())?;
+            for doc in list {
+                out.write_all(&doc.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks up the saved index of a drive and brings it up to date with `entries`,
+    /// saving it again when that changed anything. `None` when there's none to pick up.
+    pub fn restore(drive: &str, entries: &[ScanEntry]) -> Option<Self> {
+        let file = File::open(index_file(drive)).ok()?;
+        let start = Instant::now();
+        let mut index = match Self::read(&mut BufReader::new(file)) {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("Ignoring the saved name index of drive {}: {}", drive, e);
+                return None;
+            }
+        };
+        // nothing is seated yet, so what `update` calls removed is always 0 here
+        let saved_docs = index.docs.len();
+        let (added, _) = index.update(entries);
+        let removed = (saved_docs + added).saturating_sub(index.docs.len());
+        index.build_time = start.elapsed();
+        note(format!("Picked up the saved name index of drive {} ({} added, {} removed) in {:?}", drive, added, removed, index.build_time));
+        if added + removed > 0
+            && let Err(e) = index.save(drive)
+        {
+            eprintln!("Couldn't save the name index of drive {}: {}", drive, e);
+        }
+        Some(index)
+    }
+
+    /// Drops the saved index of a drive, if it has one.
+    pub fn forget(drive: &str) -> io::Result<()> {
+        match fs::remove_file(index_file(drive)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn read(input: &mut impl Read) -> io::Result<Self> {
+        fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+            let mut bytes = [0u8; 4];
+            input.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+        let corrupted = || io::Error::new(io::ErrorKind::InvalidData, "the index file is corrupted");
+
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(corrupted());
+        }
+        let doc_count = read_u32(input)?;
+        let mut docs = HashMap::new();
+        for _ in 0..read_u32(input)? {
+            let doc = read_u32(input)?;
+            let mut key = vec![0u8; read_u32(input)? as usize];
+            input.read_exact(&mut key)?;
+            if doc >= doc_count {
+                return Err(corrupted());
+            }
+            docs.insert(String::from_utf8(key).map_err(|_| corrupted())?, doc);
+        }
+        let mut postings = HashMap::new();
+        for _ in 0..read_u32(input)? {
+            let gram = read_u32(input)?;
+            let len = read_u32(input)?;
+            let list = (0..len).map(|_| read_u32(input)).collect::<io::Result<Vec<u32>>>()?;
+            if list.iter().any(|&doc| doc >= doc_count) {
+                return Err(corrupted());
+            }
+            postings.insert(gram, list);
+        }
+
+        // every doc starts out gone, `update` seats the ones still on the drive
+        Ok(TrigramIndex {
+            postings,
+            docs,
+            doc_entry: vec![None; doc_count as usize],
+            live: 0,
+            build_time: Duration::ZERO,
+        })
+    }
+}
+
+/// where the index of a drive is saved, one file per drive letter
+fn index_file(drive: &str) -> PathBuf {
+    let letter: String = drive.chars().take(1).flat_map(char::to_uppercase).collect();
+    app_file(&format!("{}/{}.trigrams", NAME_INDEX_DIR, letter))
+}
+
+#[cfg(test)]
+mod name_index_tests {
+    use super::*;
+
+    fn entry(path: &str) -> ScanEntry {
+        ScanEntry {
+            path: path.to_string(),
+            name: path.rsplit(['/', '\\']).next().unwrap().to_string(),
+            size: 1,
+            is_directory: false,
+            modified: None,
+            attributes: 0,
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let entries = vec![
+            entry("C:\\Docs\\Report_2023.pdf"),
+            entry("C:\\Docs\\notes.txt"),
+            entry("C:\\Music\\report.mp3"),
+        ];
+        let index = TrigramIndex::build(&entries);
+
+        assert_eq!(index.candidates(&["report".into()]), Some(vec![0, 2]));
+        assert_eq!(index.candidates(&["docs/rep".into()]), Some(vec![0]));
+        assert_eq!(index.candidates(&["zzz".into()]), Some(vec![]));
+        assert_eq!(index.candidates(&["ab".into()]), None);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut entries = vec![entry("C:/a/old_report.pdf"), entry("C:/a/keep.txt")];
+        let mut index = TrigramIndex::build(&entries);
+
+        entries.remove(0);
+        entries.push(entry("C:/a/new_report.pdf"));
+        assert_eq!(index.update(&entries), (1, 1));
+        assert_eq!(index.entry_count(), 2);
+        assert_eq!(index.candidates(&["report".into()]), Some(vec![1]));
+        assert_eq!(index.candidates(&["keep".into()]), Some(vec![0]));
+    }
+
+    #[test]
+    fn test_same_path_twice() {
+        let entries = vec![entry("C:/a/report.pdf"), entry("C:\\a\\report.pdf"), entry("C:/a/Report.pdf")];
+        let index = TrigramIndex::build(&entries);
+
+        // the second copy replaces the first everywhere, a different case is a different file
+        assert_eq!(index.entry_count(), 2);
+        assert_eq!(index.candidates(&["report".into()]), Some(vec![1, 2]));
+        assert!(index.postings.values().all(|list| !list.contains(&0)));
+    }
+
+    #[test]
+    fn test_saved_index() {
+        let mut entries = vec![entry("C:/a/old_report.pdf"), entry("C:/a/keep.txt")];
+        let mut file = Vec::new();
+        TrigramIndex::build(&entries).write(&mut file).unwrap();
+
+        // a later session reads it back and only has to seat the entries it finds
+        let mut restored = TrigramIndex::read(&mut file.as_slice()).unwrap();
+        entries.reverse();
+        assert_eq!(restored.update(&entries), (0, 0));
+        assert_eq!(restored.candidates(&["report".into()]), Some(vec![1]));
+
+        file[0] = b'X';
+        assert!(TrigramIndex::read(&mut file.as_slice()).is_err());
+    }
+}
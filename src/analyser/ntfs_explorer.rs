@@ -1,20 +1,45 @@
 #![allow(dead_code)]
+use super::{
+    attributes::{AttributeFilter, count_attributes, print_attribute_counts},
+    compression::{estimate_compression, print_compression_estimate},
+    empty_tree,
+    ignore::{IgnoreMatcher, ScanFilter},
+    links::{self, check_link, classify_reparse_tag, is_path_link},
+    listing::{Fields, ListingOptions, SortKey},
+    name_index::TrigramIndex,
+    ntfs_raw::{self, FIRST_USER_RECORD, RawVolume},
+    query::{Query, Queryable},
+    scan_errors::{self, ScanErrorLog},
+    search::SearchQuery,
+    types::{DriveAnalysis, EmptyTree, LinkInfo, LinkStatus, ListingRow, ScanEntry, ScanErrorKind},
+    zero_regions::{print_zero_regions, scan_zero_regions},
+};
 use crate::utility::{
-    constants::GB_TO_BYTES,
-    utils::validate_drive
+    constants::{GB_TO_BYTES, MIN_FILE_TYPE_SIZE_GB, MIN_FOLDER_SIZE_GB},
+    output::{OutputFormat, note, print_json},
+    progress::{CancelGuard, INCOMPLETE_RESULTS, ScanProgress, cancelled},
+    utils::{
+        cleanup_path, filter_filename, format_size, timestamp_to_string, validate_drive,
+        validate_ntfs_source,
+    }
+};
+use ntfs_reader::{
+    api::NtfsAttributeType, file::NtfsFile, file_info::FileInfo, mft::Mft, volume::Volume,
 };
-use ntfs_reader::{file_info::FileInfo, mft::Mft, volume::Volume};
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     error::Error,
     ffi::OsStr,
     fmt::format,
     io::{Read, Seek},
+    mem,
     os::windows::ffi::OsStrExt,
     path::Path,
     ptr::null_mut,
+    time::Instant,
 };
-use time::{Duration, OffsetDateTime};
 
 
 // unsafe shit, use properly or get a panic attack
@@ -28,50 +53,101 @@ unsafe extern "system" {
     ) -> i32;
 }
 
-pub struct NtfsExplorer {}
+pub struct NtfsExplorer {
+    // every file and folder per drive letter, read from the MFT once and reused
+    entry_cache: HashMap<String, Vec<ScanEntry>>,
+    // optional trigram index per drive letter, only built when asked for
+    name_index: HashMap<String, TrigramIndex>,
+    // which entries the listings show, set per command from its flags
+    attribute_filter: AttributeFilter,
+    // include/exclude patterns from the command, the entry cache was read with these
+    scan_filter: ScanFilter,
+    // drive letters whose read got cancelled part way
+    incomplete: HashSet<String>,
+    // what each drive letter's last read couldn't make sense of
+    scan_errors: HashMap<String, ScanErrorLog>,
+    // text for the shell, json for scripts, set per command from its flags
+    output_format: OutputFormat,
+    // the entries read with the patterns used before the current ones, so going back is free
+    shelved: Option<ShelvedEntries>,
+}
+
+/// Everything that depends on the include/exclude patterns, kept aside while other
+/// patterns are in use.
+struct ShelvedEntries {
+    filter: ScanFilter,
+    entry_cache: HashMap<String, Vec<ScanEntry>>,
+    name_index: HashMap<String, TrigramIndex>,
+    incomplete: HashSet<String>,
+    scan_errors: HashMap<String, ScanErrorLog>,
+}
 
 impl NtfsExplorer {
     pub fn new() -> Self {
-        NtfsExplorer {}
+        NtfsExplorer {
+            entry_cache: HashMap::new(),
+            name_index: HashMap::new(),
+            attribute_filter: AttributeFilter::default(),
+            scan_filter: ScanFilter::default(),
+            incomplete: HashSet::new(),
+            scan_errors: HashMap::new(),
+            output_format: OutputFormat::default(),
+            shelved: None,
+        }
     }
-    
-    /// returns true if the file name appears to be a concatenation of GUIDs.
-    fn is_guid_concat(&self, name: &str) -> bool {
-        // Heuristic: if the name starts with '{', contains "}{", and ends with '}'
-        // it likely is two GUIDs concatenated.
-        name.starts_with('{') && name.contains("}{") && name.ends_with('}')
+
+    pub fn set_attribute_filter(&mut self, filter: AttributeFilter) {
+        self.attribute_filter = filter;
     }
-    
-    /// given a file name, returns a user-friendly name (filtering out GUID concatenations).
-    pub fn filter_filename(&self, name: &str, empty: bool) -> String {
-        if name.is_empty() && empty {
-            "No Name".to_string()
-        } else if self.is_guid_concat(name) {
-            "GUID name".to_string()
-        } else {
-            name.to_string()
-        }
+
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
     }
-    
-    /// given a number in bytes, returns a compressed version of it
-    ///
-    /// examples : `1505210368 --> 1.40 GB` | `815663130 --> 777.88 MB`
-    fn format_size(&self, bytes: u64) -> String {
-        const KB: u64 = 1024;
-        const MB: u64 = KB * 1024;
-        const GB: u64 = MB * 1024;
-    
-        if bytes >= GB {
-            format!("{:.2} GB", bytes as f64 / GB as f64)
-        } else if bytes >= MB {
-            format!("{:.2} MB", bytes as f64 / MB as f64)
-        } else if bytes >= KB {
-            format!("{:.2} KB", bytes as f64 / KB as f64)
-        } else {
-            format!("{} bytes", bytes)
+
+    fn json(&self) -> bool {
+        self.output_format == OutputFormat::Json
+    }
+
+    /// Different patterns mean a different listing. Changing them puts the cached entries
+    /// aside and brings back the ones read with the new patterns, when they were kept.
+    pub fn set_scan_filter(&mut self, filter: ScanFilter) {
+        if filter == self.scan_filter {
+            return;
+        }
+        let previous = ShelvedEntries {
+            filter: mem::replace(&mut self.scan_filter, filter),
+            entry_cache: mem::take(&mut self.entry_cache),
+            name_index: mem::take(&mut self.name_index),
+            incomplete: mem::take(&mut self.incomplete),
+            scan_errors: mem::take(&mut self.scan_errors),
+        };
+        match self.shelved.take() {
+            Some(shelved) if shelved.filter == self.scan_filter => {
+                self.entry_cache = shelved.entry_cache;
+                self.name_index = shelved.name_index;
+                self.incomplete = shelved.incomplete;
+                self.scan_errors = shelved.scan_errors;
+            }
+            _ if !previous.entry_cache.is_empty() => {
+                note("Include/exclude patterns changed, drives will be read again with them..");
+            }
+            _ => {}
         }
+        self.shelved = Some(previous);
     }
     
+    /// the first `count` files as rows for json output, with their full paths
+    fn listing_rows(&self, files: Vec<FileInfo>) -> Vec<ListingRow> {
+        files
+            .into_iter()
+            .map(|file| ListingRow {
+                name: file.path.to_string_lossy().to_string(),
+                size: file.size,
+                modified: file.modified.and_then(|t| timestamp_to_string(t.unix_timestamp())),
+            })
+            .collect()
+    }
+
     /// Returns a folder key (as a String) from a full file path.
     /// It takes up to 5 directory components from the root.
     /// Example:
@@ -105,7 +181,7 @@ impl NtfsExplorer {
         // Split into components and transform each one.
         let mut transformed_components: Vec<String> = without_prefix
             .split('\\')
-            .map(|comp| self.filter_filename(comp, false))
+            .map(|comp| filter_filename(comp, false))
             .collect();
     
         // Remove any leading empty component if it exists.
@@ -121,17 +197,23 @@ impl NtfsExplorer {
         Some(format!("{}:\\{}", drive_letter, result))
     }
     
-    /// Checks if a folder is hidden.
-    ///
-    /// hidden being with dots or folders that have the hidden tag (totally)
-    #[allow(dead_code)]
-    fn is_hidden_folder(&self, folder: &str) -> bool {
-        // Extract the final component and check if it starts with '.'
-        if let Some(name) = Path::new(folder).file_name().and_then(|s| s.to_str()) {
-            name.starts_with('.')
-        } else {
-            false
-        }
+    /// FILE_ATTRIBUTE_* flags from a record's $STANDARD_INFORMATION,
+    /// ntfs_reader's FileInfo doesn't carry them
+    fn standard_attributes(&self, file: &NtfsFile) -> u32 {
+        let mut attributes = 0;
+        file.attributes(|attr| {
+            if attr.header.type_id == NtfsAttributeType::StandardInformation as u32 {
+                attributes = attr.as_standard_info().file_attributes;
+            }
+        });
+        attributes
+    }
+
+    /// whether the include/exclude rules keep an entry straight from the MFT
+    fn keeps_file(&self, ignore: &IgnoreMatcher, info: &FileInfo, drive_letter: &str) -> bool {
+        info.path
+            .to_str()
+            .is_some_and(|path| ignore.keeps(&self.format_folder_path(path, drive_letter), info.is_directory))
     }
 
     /// Formats a folder path to a consistent format with drive letter.
@@ -155,24 +237,27 @@ impl NtfsExplorer {
         };
 
         // Clean up path and filter invalid or system folders
-        self.cleanup_path(&formatted_path)
+        cleanup_path(&formatted_path)
     }
 
-    /// Clean up path and filter out system/special folders that might cause issues
-    fn cleanup_path(&self, path: &str) -> String {
-        // Convert backslashes to forward slashes for consistent handling
-        let mut cleaned = path.replace('\\', "/");
-
-        // Remove duplicate slashes
-        while cleaned.contains("//") {
-            cleaned = cleaned.replace("//", "/");
-        }
-
-        // Convert back to Windows format
-        cleaned.replace('/', "\\")
+    /// Empty folders on a drive with the system folders already left out,
+    /// ready to be handed to `folder_cleanup::delete_empty_folders`.
+    pub fn empty_folder_candidates(&self, drive_letter: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut folders = Vec::new();
+        validate_drive(drive_letter, |formatted_drive| {
+            folders = self
+                .scan_empty_folders(&formatted_drive[..1])
+                .into_iter()
+                .filter(|path| !self.is_system_folder(path))
+                .collect();
+            Ok(())
+        })?;
+        Ok(folders)
     }
 
-    /// Determines if a folder is a system folder that should be excluded
+    /// Determines if a folder is a system folder that should be excluded.
+    /// Goes by path, since empty folder scans only have paths to go on; listings
+    /// use the real system attribute through `attribute_filter` instead.
     fn is_system_folder(&self, path: &str) -> bool {
         // Check for typical system folders that should be excluded
         let lower_path = path.to_lowercase();
@@ -189,6 +274,124 @@ impl NtfsExplorer {
     
     
     // -- scanning functions -- //
+
+    /// Reads every file and folder of a drive from the MFT once and caches it,
+    /// so searching doesn't have to go back to the disk every time.
+    fn collect_entries(&mut self, drive_letter: &str) -> Result<&Vec<ScanEntry>, Box<dyn Error>> {
+        if !self.entry_cache.contains_key(drive_letter) {
+            note(format!("No cache found, reading the MFT of drive {}..", drive_letter));
+            let drive_path = format!("\\\\.\\{}:", drive_letter);
+            let volume = Volume::new(&drive_path)
+                .map_err(|e| format!("Failed to open volume at {}: {:?}", drive_path, e))?;
+            let mft = Mft::new(volume).map_err(|e| format!("Failed to read the MFT: {:?}", e))?;
+
+            let ignore = self.scan_filter.matcher(&format!("{}:\\", drive_letter));
+            let expected_bytes = self.get_drive_space(drive_letter).ok().map(|(_, used, _)| used);
+            let progress = ScanProgress::begin(expected_bytes);
+            // the MFT has no permissions to trip over, but names that aren't valid
+            // Unicode have no usable path and used to vanish from every listing
+            let mut errors = ScanErrorLog::new(expected_bytes);
+            let mut entries: Vec<ScanEntry> = Vec::new();
+            mft.iterate_files(|file| {
+                // iterate_files can't be stopped, so a cancelled scan just skips the rest
+                if cancelled() {
+                    return;
+                }
+                let info = FileInfo::new(&mft, file);
+                let Some(path_str) = info.path.to_str() else {
+                    let lossy = info.path.to_string_lossy();
+                    let parent = Path::new(lossy.as_ref()).parent().map(|p| p.to_string_lossy().to_string());
+                    let folder = self.format_folder_path(&parent.unwrap_or_default(), drive_letter);
+                    errors.record(&folder, ScanErrorKind::InvalidName, false);
+                    return;
+                };
+                let path = self.format_folder_path(path_str, drive_letter);
+                if !ignore.keeps(&path, info.is_directory) {
+                    return;
+                }
+                let parent = Path::new(&path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+                progress.record(&parent, info.size);
+                entries.push(ScanEntry {
+                    path,
+                    name: info.name.clone(),
+                    size: info.size,
+                    is_directory: info.is_directory,
+                    modified: info.modified.map(|t| t.unix_timestamp()),
+                    attributes: self.standard_attributes(file),
+                });
+            });
+
+            if progress.finish() {
+                self.incomplete.remove(drive_letter);
+            } else {
+                self.incomplete.insert(drive_letter.to_string());
+            }
+            errors.scanned_bytes = entries.iter().filter(|e| !e.is_directory).map(|e| e.size).sum();
+            note(errors.summary());
+            self.scan_errors.insert(drive_letter.to_string(), errors);
+            note(format!("Cached {} entries..", entries.len()));
+            self.entry_cache.insert(drive_letter.to_string(), entries);
+        }
+        if self.incomplete.contains(drive_letter) {
+            note(format!("(incomplete: reading drive {} was cancelled, `rescan {}` to finish it)", drive_letter, drive_letter));
+        }
+        Ok(&self.entry_cache[drive_letter])
+    }
+
+    /// Drops the cached entries of a drive and reads its MFT again.
+    pub fn rescan_drive(&mut self, drive: &str) -> Result<(), Box<dyn Error>> {
+        validate_drive(drive, |formatted_drive| {
+            let letter = &formatted_drive[..1];
+            self.entry_cache.remove(letter);
+            // what other patterns read of it is just as old
+            if let Some(shelved) = &mut self.shelved {
+                shelved.entry_cache.remove(letter);
+                shelved.name_index.remove(letter);
+                shelved.incomplete.remove(letter);
+                shelved.scan_errors.remove(letter);
+            }
+            self.collect_entries(letter)?;
+            if let Some(index) = self.name_index.get_mut(letter) {
+                let (added, removed) = index.update(&self.entry_cache[letter]);
+                note(format!(
+                    "Index updated: {} added, {} removed in {:?}",
+                    added, removed, index.build_time
+                ));
+                index.save(letter)?;
+            }
+            println!("Rescan complete for drive {}", formatted_drive);
+            Ok(())
+        })
+    }
+
+    /// Builds (or drops, with `enable` false) the trigram name index of a drive,
+    /// which `find` then uses to skip entries that can't match.
+    pub fn set_name_index(&mut self, drive: &str, enable: bool) -> Result<(), Box<dyn Error>> {
+        validate_drive(drive, |formatted_drive| {
+            let letter = &formatted_drive[..1];
+            if !enable {
+                TrigramIndex::forget(letter)?;
+                match self.name_index.remove(letter) {
+                    Some(_) => println!("Dropped the name index of drive {}", formatted_drive),
+                    None => println!("Drive {} has no name index", formatted_drive),
+                }
+                return Ok(());
+            }
+
+            self.collect_entries(letter)?;
+            let index = TrigramIndex::build(&self.entry_cache[letter]);
+            index.save(letter)?;
+            println!(
+                "Indexed {} entries ({} trigrams) in {:?}, using about {}",
+                index.entry_count(),
+                index.trigram_count(),
+                index.build_time,
+                format_size(index.memory_usage() as u64)
+            );
+            self.name_index.insert(letter.to_string(), index);
+            Ok(())
+        })
+    }
     
     /// retrieves total, used, and free space (in bytes) for the given drive letter.
     ///     
@@ -220,18 +423,38 @@ impl NtfsExplorer {
         }
     }
     
-    /// scans the NTFS drive and returns a HashMap with file extensions and their total sizes.
-    fn scan_file_type_dist(&self, drive_letter: &str) -> HashMap<String, u64> {
+    /// Runs `visit` over every MFT record with Ctrl+C able to cut it short,
+    /// returns false when it did. iterate_files can't be stopped, so the rest just gets skipped
+    fn iterate_cancellable(mft: &Mft, mut visit: impl FnMut(&NtfsFile)) -> bool {
+        let _cancel = CancelGuard::begin();
+        mft.iterate_files(|file| {
+            if !cancelled() {
+                visit(file);
+            }
+        });
+        !cancelled()
+    }
+
+    /// scans the NTFS drive and returns a HashMap with file extensions and their total sizes,
+    /// plus whether the scan ran to the end.
+    fn scan_file_type_dist(&self, drive_letter: &str, options: &ListingOptions) -> (HashMap<String, u64>, bool) {
         let drive_path = format!("\\\\.\\{}:", drive_letter);
         let volume =
             Volume::new(&drive_path).expect(&format!("Failed to open volume at {}", drive_path));
         let mft = Mft::new(volume).expect("Failed to create MFT from the volume");
     
         let mut distribution: HashMap<String, u64> = HashMap::new();
+        let ignore = self.scan_filter.matcher(&format!("{}:\\", drive_letter));
     
-        mft.iterate_files(|file| {
+        let complete = Self::iterate_cancellable(&mft, |file| {
+            if !self.attribute_filter.allows(self.standard_attributes(file)) {
+                return;
+            }
             let info = FileInfo::new(&mft, file);
-            if !info.is_directory {
+            if !info.is_directory
+                && self.keeps_file(&ignore, &info, drive_letter)
+                && options.under_path(&info.path.to_string_lossy())
+            {
                 let extension = Path::new(&info.name)
                     .extension()
                     .and_then(|s| s.to_str())
@@ -241,126 +464,167 @@ impl NtfsExplorer {
             }
         });
     
-        distribution
+        (distribution, complete)
     }
     
-    fn scan_largest_files(&self, drive_letter: &str) -> Vec<FileInfo> {
+    fn scan_largest_files(&self, drive_letter: &str) -> (Vec<FileInfo>, bool) {
         let drive_path = format!("\\\\.\\{}:", drive_letter);
         let volume =
             Volume::new(&drive_path).expect(&format!("Failed to open volume at {}", drive_path));
         let mft = Mft::new(volume).expect("Failed to create MFT from the volume");
     
         let mut files: Vec<FileInfo> = Vec::new();
-        mft.iterate_files(|file| {
+        let ignore = self.scan_filter.matcher(&format!("{}:\\", drive_letter));
+        let complete = Self::iterate_cancellable(&mft, |file| {
+            if !self.attribute_filter.allows(self.standard_attributes(file)) {
+                return;
+            }
             #[allow(unused_mut)]
             let mut info = FileInfo::new(&mft, file);
-            if !info.is_directory {
+            if !info.is_directory && self.keeps_file(&ignore, &info, drive_letter) {
                 // Convert from clusters to bytes if needed:
                 files.push(info);
             }
         });
         files.sort_by(|a, b| b.size.cmp(&a.size));
-        files
+        (files, complete)
     }
     
     /// Scans the NTFS drive and returns a HashMap of folder paths (up to 5 levels deep)
-    /// and their total file sizes, excluding hidden folders.
-    fn scan_largest_folders(&self, drive_letter: &str) -> HashMap<String, u64> {
+    /// and their total file sizes, leaving out whatever the attribute filter hides.
+    /// The flag is false when Ctrl+C cut the scan short.
+    fn scan_largest_folders(&self, drive_letter: &str) -> (HashMap<String, u64>, bool) {
         let drive_path = format!("\\\\.\\{}:", drive_letter);
         let volume =
             Volume::new(&drive_path).expect(&format!("Failed to open volume at {}", drive_path));
         let mft = Mft::new(volume).expect("Failed to create MFT from the volume");
     
         let mut folder_sizes: HashMap<String, u64> = HashMap::new();
+        let ignore = self.scan_filter.matcher(&format!("{}:\\", drive_letter));
     
-        mft.iterate_files(|file| {
+        let complete = Self::iterate_cancellable(&mft, |file| {
+            if !self.attribute_filter.allows(self.standard_attributes(file)) {
+                return;
+            }
             let info = FileInfo::new(&mft, file);
-            if !info.is_directory {
+            if !info.is_directory && self.keeps_file(&ignore, &info, drive_letter) {
                 // Convert the file's PathBuf to &str.
                 if let Some(path_str) = info.path.to_str() {
                     if let Some(folder) = self.folder_key_from_path(path_str, drive_letter, 5) {
-                        // Skip hidden folders if needed, e.g., folders starting with a dot.
                         *folder_sizes.entry(folder).or_insert(0) += info.size;
                     }
                 }
             }
         });
     
-        folder_sizes
+        (folder_sizes, complete)
     }
 
-    /// Scans the NTFS drive and returns a vector of empty folder paths.
-    /// A folder is considered empty if it contains no files and no subfolders.
-    fn scan_empty_folders(&self, drive_letter: &str) -> Vec<String> {
+    /// Scans the NTFS drive for folders with no files anywhere below them,
+    /// each subtree reported once at its topmost empty folder.
+    /// With `junk_as_empty`, zero-byte files and Thumbs.db / desktop.ini / .DS_Store don't count.
+    /// Not cancellable: with part of the files missing, folders would look empty when they aren't.
+    fn scan_empty_trees(&self, drive_letter: &str, junk_as_empty: bool) -> Vec<EmptyTree> {
         let drive_path = format!("\\\\.\\{}:", drive_letter);
         let volume =
             Volume::new(&drive_path).expect(&format!("Failed to open volume at {}", drive_path));
         let mft = Mft::new(volume).expect("Failed to create MFT from the volume");
 
-        // Use a more efficient data structure: HashMap where key is folder path,
-        // and value is a boolean indicating if it's empty (true) or not (false)
-        let mut folder_status: HashMap<String, bool> = HashMap::new();
-
-        // First pass: collect all directories with their initial "empty" status
+        let mut dirs: Vec<String> = Vec::new();
+        let mut files: Vec<(String, u64)> = Vec::new();
+        // ignored files still count as content, only ignored folders are left out
+        let ignore = self.scan_filter.matcher(&format!("{}:\\", drive_letter));
         mft.iterate_files(|file| {
             let info = FileInfo::new(&mft, file);
-
             if let Some(path_str) = info.path.to_str() {
+                let path = self.format_folder_path(path_str, drive_letter);
                 if info.is_directory {
-                    // Insert this directory as potentially empty (true) if not already present
-                    let folder_path = self.format_folder_path(path_str, drive_letter);
-                    folder_status.entry(folder_path).or_insert(true);
-
-                    // Mark parent as non-empty since it contains this directory
-                    if let Some(parent) = Path::new(path_str).parent() {
-                        if let Some(parent_str) = parent.to_str() {
-                            let parent_path = self.format_folder_path(parent_str, drive_letter);
-                            folder_status.insert(parent_path, false);
-                        }
+                    if !ignore.is_excluded_deep(&path, true) {
+                        dirs.push(path);
                     }
                 } else {
-                    // For files: mark their parent directory as non-empty
-                    if let Some(parent) = Path::new(path_str).parent() {
-                        if let Some(parent_str) = parent.to_str() {
-                            let parent_path = self.format_folder_path(parent_str, drive_letter);
-                            folder_status.insert(parent_path, false);
-                        }
-                    }
+                    files.push((path, info.size));
                 }
             }
         });
 
-        // Filter folders that are still marked as empty
-        folder_status.into_iter()
-            .filter(|(_, is_empty)| *is_empty)
-            .map(|(path, _)| path)
+        empty_tree::find_empty_trees(
+            dirs.iter().map(String::as_str),
+            files.iter().map(|(path, size)| (path.as_str(), *size)),
+            junk_as_empty,
+        )
+    }
+
+    /// Scans the NTFS drive and returns every empty folder path, nested ones included.
+    /// A folder is considered empty if it contains no files and only empty subfolders.
+    fn scan_empty_folders(&self, drive_letter: &str) -> Vec<String> {
+        self.scan_empty_trees(drive_letter, false)
+            .into_iter()
+            .flat_map(|tree| std::iter::once(tree.root).chain(tree.nested))
             .collect()
     }
     
-    /// Scans the NTFS drive and returns a vector of FileInfo for non‑directory files
-    /// that have a modification time (OffsetDateTime) satisfying the filter closure.
-    fn scan_files_by_modified<F>(&self, drive_letter: &str, filter: F) -> Vec<FileInfo>
-    where
-        F: Fn(OffsetDateTime) -> bool,
-    {
+    /// Scans the NTFS drive and returns the FileInfo of every entry matching the query,
+    /// sorted and limited the way the query asks. The flag is false when Ctrl+C cut the scan short.
+    fn scan_files_matching(&self, drive_letter: &str, query: &Query) -> (Vec<FileInfo>, bool) {
         let drive_path = format!("\\\\.\\{}:", drive_letter);
         let volume =
             Volume::new(&drive_path).expect(&format!("Failed to open volume at {}", drive_path));
         let mft = Mft::new(volume).expect("Failed to create MFT from the volume");
     
         let mut files: Vec<FileInfo> = Vec::new();
-        mft.iterate_files(|file| {
+        let ignore = self.scan_filter.matcher(&format!("{}:\\", drive_letter));
+        let complete = Self::iterate_cancellable(&mft, |file| {
+            if !self.attribute_filter.allows(self.standard_attributes(file)) {
+                return;
+            }
             let info = FileInfo::new(&mft, file);
-            if !info.is_directory {
-                if let Some(modified) = info.modified {
-                    if filter(modified) {
-                        files.push(info);
-                    }
-                }
+            if query.matches(&info) && self.keeps_file(&ignore, &info, drive_letter) {
+                files.push(info);
             }
         });
-        files.sort_by(|a, b| b.size.cmp(&a.size));
-        files
+        (query.apply(files), complete)
+    }
+
+    /// Lists every reparse point on the volume from the MFT.
+    /// Targets are only checked (and followed) on live volumes, an image's links point
+    /// into a file system that isn't mounted here.
+    fn scan_links(&self, source: &str, follow: bool) -> Result<Vec<LinkInfo>, Box<dyn Error>> {
+        let mut volume = RawVolume::open(source)?;
+        let records = volume.scan_records()?;
+        let index = ntfs_raw::record_index(&records);
+        // `\\.\C:` -> `C:`, None for images
+        let mount = source.strip_prefix("\\\\.\\").map(|drive| drive.to_string());
+        let mut visited = HashSet::new();
+
+        let mut links: Vec<LinkInfo> = records
+            .iter()
+            .filter(|r| r.in_use && r.number >= FIRST_USER_RECORD)
+            .filter_map(|r| {
+                let reparse = r.reparse.as_ref()?;
+                let name = r.file_name.as_ref()?;
+                let path = format!("{}\\{}", ntfs_raw::resolve_parent_path(&records, &index, r), name.name);
+                let kind = classify_reparse_tag(reparse.tag, reparse.target.as_deref());
+
+                let (status, followed_bytes) = match &mount {
+                    Some(drive) if is_path_link(kind) && reparse.target.is_some() => {
+                        let root = format!("{}\\", drive);
+                        check_link(Path::new(&format!("{}{}", drive, path)), Path::new(&root), follow, &mut visited)
+                    }
+                    _ => (LinkStatus::Unknown, None),
+                };
+                Some(LinkInfo {
+                    path,
+                    kind,
+                    target: reparse.target.clone(),
+                    status,
+                    followed_bytes,
+                })
+            })
+            .collect();
+
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(links)
     }
     
     
@@ -390,11 +654,19 @@ impl NtfsExplorer {
     pub fn print_drive_space(&self, drive_letter: &str) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
             let (total, used, free) = self.get_drive_space(formatted_drive)?;
+            if self.json() {
+                return print_json(&DriveAnalysis {
+                    total_size: total as f64 / GB_TO_BYTES,
+                    used_space: used as f64 / GB_TO_BYTES,
+                    free_space: free as f64 / GB_TO_BYTES,
+                    free_space_percent: free as f64 / total.max(1) as f64 * 100.0,
+                });
+            }
 
             println!("Drive {}:", formatted_drive);
-            println!("Total space: {} GB", self.format_size(total));
-            println!("Used space : {} GB", self.format_size(used));
-            println!("Free space : {} GB", self.format_size(free));
+            println!("Total space: {} GB", format_size(total));
+            println!("Used space : {} GB", format_size(used));
+            println!("Free space : {} GB", format_size(free));
             Ok(())
         })
     }
@@ -404,13 +676,15 @@ impl NtfsExplorer {
     /// # Arguments
     ///
     /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
-    /// * `count` - The number of file types to display in the results
+    /// * `options` - The listing flags: how many file types, their size range, order and
+    ///   which folder's files are counted (`--path`)
     ///
     /// # Examples
     ///
     /// ```
     /// // Display top 5 file types by size on drive D:
-    /// print_file_type_dist("D", 5).unwrap();
+    /// let options = ListingOptions { top: Some(5), ..Default::default() };
+    /// print_file_type_dist("D", &options).unwrap();
     /// // Output:
     /// // File Type Distribution for Drive D: (Top 5 by space usage):
     /// // .mp4            150 GB
@@ -419,68 +693,90 @@ impl NtfsExplorer {
     /// // .docx           45 GB
     /// // No Extension    32 GB
     /// ```
-    pub fn print_file_type_dist(&self, drive_letter: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    pub fn print_file_type_dist(&self, drive_letter: &str, options: &ListingOptions) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
-            let distribution = self.scan_file_type_dist(formatted_drive);
-            let mut items: Vec<(&String, &u64)> = distribution.iter().collect();
-            items.sort_by(|a, b| b.1.cmp(a.1));
+            let (distribution, complete) = self.scan_file_type_dist(formatted_drive, options);
+            if !complete {
+                note(INCOMPLETE_RESULTS);
+            }
+            let options = options.min_size_or((MIN_FILE_TYPE_SIZE_GB * GB_TO_BYTES) as u64);
+            let items = options.finish(distribution.into_iter().collect(), SortKey::Size, |(ext, size)| {
+                Fields::named(ext.as_str(), *size)
+            });
+            if self.json() {
+                let rows: Vec<ListingRow> = items
+                    .into_iter()
+                    .map(|(ext, size)| ListingRow { name: ext, size, modified: None })
+                    .collect();
+                return print_json(&rows);
+            }
 
             println!(
                 "File Type Distribution for Drive {} (Top {} by space usage):",
-                formatted_drive, count
+                formatted_drive,
+                options.top()
             );
-            for (ext, size) in items.into_iter().take(count) {
-                let display_ext = if ext.is_empty() { "No Extension" } else { ext };
-                println!("{:<15}: {}", display_ext, self.format_size(*size));
+            for (ext, size) in items {
+                let display_ext = if ext.is_empty() { "No Extension" } else { &ext };
+                println!("{:<15}: {}", display_ext, format_size(size));
             }
             Ok(())
         })
     }
     
-    /// Displays the largest files on a drive, sorted by size.
+    /// Displays the largest files on a drive, sorted by size unless `--sort` says otherwise.
     ///
     /// # Arguments
     ///
     /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
-    /// * `count` - The number of files to display in the results
+    /// * `options` - The listing flags: how many files, their size range, order and folder
     ///
     /// # Examples
     ///
     /// ```
     /// // Display top 3 largest files on drive E:
-    /// print_largest_files("E", 3).unwrap();
+    /// let options = ListingOptions { top: Some(3), ..Default::default() };
+    /// print_largest_files("E", &options).unwrap();
     /// // Output:
     /// // Largest Files on Drive E (Top 3):
     /// // movie.mkv                       8.5 GB
     /// // backup.iso                      4.2 GB
     /// // dataset.csv                     1.8 GB
     /// ```
-    pub fn print_largest_files(&self, drive_letter: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    pub fn print_largest_files(&self, drive_letter: &str, options: &ListingOptions) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
-            let files = self.scan_largest_files(formatted_drive);
+            let (files, complete) = self.scan_largest_files(formatted_drive);
+            if !complete {
+                note(INCOMPLETE_RESULTS);
+            }
+            let files = options.finish(files, SortKey::Size, |file| Fields::entry(file));
+            if self.json() {
+                return print_json(&self.listing_rows(files));
+            }
 
-            println!("Largest Files on Drive {} (Top {}):", formatted_drive, count);
-            for file in files.into_iter().take(count) {
+            println!("Largest Files on Drive {} (Top {}):", formatted_drive, options.top());
+            for file in files {
                 // Filter the file name if it's a GUID concatenation.
-                let display_name = self.filter_filename(&file.name, true);
-                println!("{:<30} {}", display_name, self.format_size(file.size));
+                let display_name = filter_filename(&file.name, true);
+                println!("{:<30} {}", display_name, format_size(file.size));
             }
             Ok(())
         })
     }
     
-    /// Displays the largest folders on a drive, sorted by total size.
+    /// Displays the largest folders on a drive, sorted by total size unless `--sort` says otherwise.
     ///
     /// # Arguments
     ///
     /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
-    /// * `count` - The number of folders to display in the results
+    /// * `options` - The listing flags: how many folders, their size range, order and folder
     ///
     /// # Examples
     ///
     /// ```
     /// // Display top 5 largest folders on drive C:
-    /// print_largest_folders("C", 5).unwrap();
+    /// let options = ListingOptions { top: Some(5), ..Default::default() };
+    /// print_largest_folders("C", &options).unwrap();
     /// // Output:
     /// // Largest Folders on Drive C: (Top 5):
     /// // C:\Users\username\Videos                              350 GB
@@ -489,108 +785,131 @@ impl NtfsExplorer {
     /// // C:\Windows                                            65 GB
     /// // C:\Program Files (x86)                                45 GB
     /// ```
-    pub fn print_largest_folders(&self, drive_letter: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    pub fn print_largest_folders(&self, drive_letter: &str, options: &ListingOptions) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
-            let folder_sizes = self.scan_largest_folders(formatted_drive);
+            let (folder_sizes, complete) = self.scan_largest_folders(formatted_drive);
+            if !complete {
+                note(INCOMPLETE_RESULTS);
+            }
 
-            // Convert to vector and sort descending by size.
-            let mut folders: Vec<(&String, &u64)> = folder_sizes.iter().collect();
-            folders.sort_by(|a, b| b.1.cmp(a.1));
+            // Leave out the small ones unless given --min-size, and sort descending by size.
+            let options = options.min_size_or((MIN_FOLDER_SIZE_GB * GB_TO_BYTES) as u64);
+            let folders = options.finish(folder_sizes.into_iter().collect(), SortKey::Size, |(folder, size)| {
+                Fields::path(folder.as_str(), *size)
+            });
+            if self.json() {
+                let rows: Vec<ListingRow> = folders
+                    .into_iter()
+                    .map(|(folder, size)| ListingRow { name: folder, size, modified: None })
+                    .collect();
+                return print_json(&rows);
+            }
 
-            println!("Largest Folders on Drive {} (Top {}):", formatted_drive, count);
-            for (folder, size) in folders.into_iter().take(count) {
-                println!("{:<50} {}", folder, self.format_size(*size));
+            println!("Largest Folders on Drive {} (Top {}):", formatted_drive, options.top());
+            for (folder, size) in folders {
+                println!("{:<50} {}", folder, format_size(size));
             }
             Ok(())
         })
     }
     
-    /// Prints the largest files modified within the last 30 days.
+    /// Prints the largest files modified within the last 30 days, or `--age`.
     ///
     /// it is gonna be a little too accurate, for now
     ///
     /// # Arguments
     ///
     /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
-    /// * `count` - The number of files to display in the results
+    /// * `options` - The listing flags: how many files, the window, their size range, order and folder
     ///
     /// # Examples
     ///
     /// ```
     /// // Display top 4 recent large files on drive D:
-    /// print_recent_large_files("D", 4).unwrap();
+    /// let options = ListingOptions { top: Some(4), ..Default::default() };
+    /// print_recent_large_files("D", &options).unwrap();
     /// // Output:
-    /// // Recent Large Files on Drive D: (Modified within last 30 days):
+    /// // Recent Large Files on Drive D: (Modified within the last 30d):
     /// // project_backup.zip             2.5 GB  Modified: 2023-05-10T14:32:15Z
     /// // meeting_recording.mp4          1.8 GB  Modified: 2023-05-15T09:45:30Z
     /// // system_logs.tar                1.2 GB  Modified: 2023-05-18T22:10:05Z
     /// // virtual_machine.vhdx           0.9 GB  Modified: 2023-05-20T16:25:40Z
     /// ```
-    pub fn print_recent_large_files(&self, drive_letter: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    pub fn print_recent_large_files(&self, drive_letter: &str, options: &ListingOptions) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
-            let now = OffsetDateTime::now_utc();
-            let threshold = Duration::days(30);
-
-            let files = self.scan_files_by_modified(formatted_drive, |mod_time| now - mod_time <= threshold);
+            let (files, complete) = self.scan_files_matching(formatted_drive, &options.age_query(true));
+            if !complete {
+                note(INCOMPLETE_RESULTS);
+            }
+            let files = options.finish(files, SortKey::Size, |file| Fields::entry(file));
+            if self.json() {
+                return print_json(&self.listing_rows(files));
+            }
 
             println!(
-                "Recent Large Files on Drive {} (Modified within last 30 days):",
-                formatted_drive
+                "Recent Large Files on Drive {} (Modified within the last {}):",
+                formatted_drive,
+                options.age(true)
             );
-            for file in files.into_iter().take(count) {
-                println!(
-                    "{:<30} {}  Modified: {}",
-                    self.filter_filename(&*file.name, true),
-                    self.format_size(file.size),
-                    file.modified.unwrap()
-                );
-            }
+            self.print_dated_files(files);
             Ok(())
         })
     }
     
-    /// Prints the largest files modified more than 6 months ago.
+    /// Prints the largest files modified more than 6 months ago, or `--age`.
     ///
     /// it is gonna be a little too accurate, for now
     ///
     /// # Arguments
     ///
     /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
-    /// * `count` - The number of files to display in the results
+    /// * `options` - The listing flags: how many files, the window, their size range, order and folder
     ///
     /// # Examples
     ///
     /// ```
     /// // Display top 3 old large files on drive C:
-    /// print_old_large_files("C", 3).unwrap();
+    /// let options = ListingOptions { top: Some(3), ..Default::default() };
+    /// print_old_large_files("C", &options).unwrap();
     /// // Output:
-    /// // Old Large Files on Drive C: (Modified more than 6 months ago):
+    /// // Old Large Files on Drive C: (Modified more than 6mo ago):
     /// // old_backup_2022.zip            4.5 GB  Modified: 2022-08-12T18:20:45Z
     /// // archive_data.tar               3.2 GB  Modified: 2022-05-30T11:15:22Z
     /// // legacy_application.iso         2.8 GB  Modified: 2021-11-05T14:40:15Z
     /// ```
-    pub fn print_old_large_files(&self, drive_letter: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    pub fn print_old_large_files(&self, drive_letter: &str, options: &ListingOptions) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
-            let now = OffsetDateTime::now_utc();
-            let threshold = Duration::days(6 * 30); // approximate 6 months
-
-            let files = self.scan_files_by_modified(formatted_drive, |mod_time| now - mod_time >= threshold);
+            let (files, complete) = self.scan_files_matching(formatted_drive, &options.age_query(false));
+            if !complete {
+                note(INCOMPLETE_RESULTS);
+            }
+            let files = options.finish(files, SortKey::Size, |file| Fields::entry(file));
+            if self.json() {
+                return print_json(&self.listing_rows(files));
+            }
 
             println!(
-                "Old Large Files on Drive {} (Modified more than 6 months ago):",
-                formatted_drive
+                "Old Large Files on Drive {} (Modified more than {} ago):",
+                formatted_drive,
+                options.age(false)
             );
-            for file in files.into_iter().take(count) {
-                println!(
-                    "{:<30} {}  Modified: {}",
-                    self.filter_filename(&*file.name, true),
-                    self.format_size(file.size),
-                    file.modified.unwrap()
-                );
-            }
+            self.print_dated_files(files);
             Ok(())
         })
     }
+
+    // the age listings' rows; the queries only pick files with a date, but a missing one isn't worth a panic
+    fn print_dated_files(&self, files: Vec<FileInfo>) {
+        for file in files {
+            let modified = file.modified.map_or_else(|| "unknown".to_string(), |t| t.to_string());
+            println!(
+                "{:<30} {}  Modified: {}",
+                filter_filename(&file.name, true),
+                format_size(file.size),
+                modified
+            );
+        }
+    }
     
     /// Displays empty folders on a drive.
     ///
@@ -598,28 +917,29 @@ impl NtfsExplorer {
     ///
     /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
     /// * `count` - The number of empty folders to display in the results
+    /// * `junk_as_empty` - Treat folders holding only zero-byte or junk files as empty
     ///
     /// # Examples
     ///
     /// ```
     /// // Display top 10 empty folders on drive C:
-    /// print_empty_folders("C", 10).unwrap();
+    /// print_empty_folders("C", 10, false).unwrap();
     /// // Output:
     /// // Empty Folders on Drive C: (Top 10):
-    /// // C:\Users\username\Documents\Projects\Archived
+    /// // C:\Users\username\Documents\Projects\Archived  (+4 nested empty folders)
     /// // C:\Program Files\Temp
-    /// // C:\Users\username\Downloads\Extracted
+    /// // C:\Users\username\Downloads\Extracted  (+12 nested empty folders)
     /// // C:\Windows\Logs\Old
     /// // C:\Backups\System\2023
     /// ```
-    pub fn print_empty_folders(&self, drive_letter: &str, count: usize) -> Result<(), Box<dyn Error>> {
+    pub fn print_empty_folders(&self, drive_letter: &str, count: usize, junk_as_empty: bool) -> Result<(), Box<dyn Error>> {
         validate_drive(drive_letter, |formatted_drive| {
-            println!("Scanning for empty folders on Drive {}...", formatted_drive);
-            let empty_folders = self.scan_empty_folders(formatted_drive);
+            note(format!("Scanning for empty folders on Drive {}...", formatted_drive));
+            let empty_trees = self.scan_empty_trees(formatted_drive, junk_as_empty);
 
             // Filter out system folders which are often reported as empty due to permissions
-            let filtered_folders: Vec<String> = empty_folders.into_iter()
-                .filter(|path| !self.is_system_folder(path))
+            let filtered_folders: Vec<EmptyTree> = empty_trees.into_iter()
+                .filter(|tree| !self.is_system_folder(&tree.root))
                 .collect();
 
             println!("Empty Folders on Drive {} (Top {}):", drive_letter, count);
@@ -628,26 +948,13 @@ impl NtfsExplorer {
             // Verify each folder is truly empty using filesystem operations
             let mut verified_empty_folders = Vec::new();
 
-            for folder in &filtered_folders {
-                // Skip any path that doesn't actually exist (might be due to access rights issues)
-                let path = Path::new(folder);
-                if !path.exists() {
+            for tree in &filtered_folders {
+                // the whole subtree, nested folders and junk included, has to still be
+                // empty on disk. missing or unreadable folders are skipped
+                if !empty_tree::still_empty(Path::new(&tree.root), junk_as_empty) {
                     continue;
                 }
-
-                // Try to read directory entries
-                match std::fs::read_dir(path) {
-                    Ok(entries) => {
-                        let is_empty = entries.count() == 0;
-                        if is_empty {
-                            verified_empty_folders.push(folder.clone());
-                        }
-                    },
-                    Err(_) => {
-                        // Skip folders we can't read (likely permission issues)
-                        continue;
-                    }
-                }
+                verified_empty_folders.push(empty_tree::tree_summary(tree));
 
                 // Don't process more than needed for the display
                 if verified_empty_folders.len() >= count {
@@ -667,6 +974,383 @@ impl NtfsExplorer {
             Ok(())
         })
     }
+
+    /// Searches the cached entries for names (or paths) matching a `find` query,
+    /// largest matches first. Without a drive it searches every drive scanned so far.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive letter to search (e.g., "C"), or `None` for all cached drives
+    /// * `query` - The parsed search pattern and filters
+    /// * `count` - The number of matches to display in the results
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Find the 2 largest pdfs starting with "report" on drive C:
+    /// print_search(Some("C"), &query, 2).unwrap();
+    /// // Output:
+    /// // Found 153 matches in 41.2ms
+    /// // Search Results for "report*.pdf" (Top 2):
+    /// // C:\Users\username\Documents\report_final.pdf         12.40 MB  Modified: 2024-05-02 10:11:12
+    /// // C:\Users\username\Downloads\report_2023.pdf          2.10 MB  Modified: 2023-12-20 16:40:03
+    /// ```
+    pub fn print_search(&mut self, drive: Option<&str>, query: &SearchQuery, count: usize) -> Result<(), Box<dyn Error>> {
+        let mut letters: Vec<String> = match drive {
+            Some(drive) => {
+                let mut letter = None;
+                validate_drive(drive, |formatted_drive| {
+                    letter = Some(formatted_drive[..1].to_string());
+                    Ok(())
+                })?;
+                // an invalid drive was already reported by validate_drive
+                match letter {
+                    Some(letter) => vec![letter],
+                    None => return Ok(()),
+                }
+            }
+            None => self.entry_cache.keys().cloned().collect(),
+        };
+        if letters.is_empty() {
+            return Err("No drives scanned yet, give find a drive letter to scan first".into());
+        }
+        letters.sort();
+
+        for letter in &letters {
+            self.collect_entries(letter)?;
+            if !self.name_index.contains_key(letter)
+                && let Some(index) = TrigramIndex::restore(letter, &self.entry_cache[letter])
+            {
+                self.name_index.insert(letter.clone(), index);
+            }
+        }
+
+        let start = Instant::now();
+        let literals = query.literals();
+        let mut matches: Vec<&ScanEntry> = Vec::new();
+        for letter in &letters {
+            let entries = &self.entry_cache[letter];
+            match self.name_index.get(letter).and_then(|index| index.candidates(&literals)) {
+                Some(candidates) => matches.extend(
+                    candidates.into_iter().map(|i| &entries[i]).filter(|entry| query.matches(entry)),
+                ),
+                None => matches.extend(entries.iter().filter(|entry| query.matches(entry))),
+            }
+        }
+        matches.retain(|entry| self.attribute_filter.allows(entry.attributes));
+        matches.sort_by_key(|entry| Reverse(entry.size));
+        note(format!("Found {} matches in {:?}", matches.len(), start.elapsed()));
+        if self.json() {
+            return print_json(&matches.into_iter().take(count).collect::<Vec<_>>());
+        }
+
+        println!("Search Results for \"{}\" (Top {}):", query.pattern, count);
+        for entry in matches.into_iter().take(count) {
+            println!(
+                "{:<50} {}  Modified: {}",
+                entry.path,
+                format_size(entry.size),
+                entry
+                    .modified
+                    .and_then(timestamp_to_string)
+                    .unwrap_or_else(|| "Unknown".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    /// Runs a query over the cached entries of a drive and prints what matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive_letter` - The drive letter to query (e.g., "C", "D")
+    /// * `query` - The parsed query, its `limit` overrides `count`
+    /// * `count` - The number of matches to display when the query has no limit
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Old videos in the downloads folder on drive C:
+    /// let query = Query::parse("ext in (mkv, mp4) and modified < -6mo and path ~ 'downloads' order by size desc")?;
+    /// print_query("C", &query, 2).unwrap();
+    /// // Output:
+    /// // Found 12 matches in 18.5ms
+    /// // Query Results on Drive C: (Top 2):
+    /// // C:\Users\username\Downloads\lecture.mkv             3.20 GB  Modified: 2023-09-14 20:01:44
+    /// // C:\Users\username\Downloads\trip.mp4                1.10 GB  Modified: 2023-07-02 12:30:09
+    /// ```
+    pub fn print_query(&mut self, drive_letter: &str, query: &Query, count: usize) -> Result<(), Box<dyn Error>> {
+        let mut letter = None;
+        validate_drive(drive_letter, |formatted_drive| {
+            letter = Some(formatted_drive[..1].to_string());
+            Ok(())
+        })?;
+        // an invalid drive was already reported by validate_drive
+        let Some(letter) = letter else {
+            return Ok(());
+        };
+
+        self.collect_entries(&letter)?;
+        let entries: Vec<&ScanEntry> = self.entry_cache[&letter]
+            .iter()
+            .filter(|entry| self.attribute_filter.allows(entry.attributes))
+            .collect();
+        let start = Instant::now();
+        let total = entries.iter().filter(|entry| query.matches(entry)).count();
+        let matches = query.apply(entries);
+        note(format!("Found {} matches in {:?}", total, start.elapsed()));
+
+        let count = query.limit.unwrap_or(count);
+        if self.json() {
+            return print_json(&matches.into_iter().take(count).collect::<Vec<_>>());
+        }
+        println!("Query Results on Drive {}: (Top {}):", letter, count);
+        for entry in matches.into_iter().take(count) {
+            println!(
+                "{:<50} {}  Modified: {}",
+                entry.path,
+                format_size(entry.size),
+                entry
+                    .modified
+                    .and_then(timestamp_to_string)
+                    .unwrap_or_else(|| "Unknown".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    /// Full paths of the files a query picks, for commands that act on a result set.
+    /// Empty when the drive letter is invalid (validate_drive reports that).
+    /// Paths of the entries a query picks, left to the attribute filter plus `also`.
+    pub fn query_file_paths(&mut self, drive_letter: &str, query: &Query, also: AttributeFilter) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut letter = None;
+        validate_drive(drive_letter, |formatted_drive| {
+            letter = Some(formatted_drive[..1].to_string());
+            Ok(())
+        })?;
+        let Some(letter) = letter else {
+            return Ok(Vec::new());
+        };
+
+        let filter = self.attribute_filter.and(also);
+        self.collect_entries(&letter)?;
+        let entries = &self.entry_cache[&letter];
+        Ok(query
+            .apply(entries.iter().filter(|entry| filter.allows(entry.attributes)).collect())
+            .into_iter()
+            .map(|entry| entry.path.clone())
+            .collect())
+    }
+
+    /// Estimates how much space compression would save, per codec, per top level folder
+    /// and per file type, by compressing blocks sampled across the drive's files.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
+    /// * `blocks` - How many 64 KB blocks to sample, more means tighter bounds
+    /// * `count` - The number of folders and file types to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// print_compression_estimate("C", 500, 20).unwrap();
+    /// // Output:
+    /// // Sampled 500 blocks of 64 KB out of 212.40 GB (3 unreadable), 95% bounds in brackets
+    /// // lz4        ratio 71.3% (68.9-73.7%)  saves 60.96 GB (55.86-66.05 GB)
+    /// // ...
+    /// // By Folder (lz4 ~ filesystem compression, zstd -19 ~ archiving):
+    /// // C:\Users    120.31 GB  lz4 saves 30.12 GB (25.40-34.84)  zstd -19 saves 45.02 GB (39.77-50.27)  [283 blocks]
+    /// ```
+    pub fn print_compression_estimate(&mut self, drive_letter: &str, blocks: usize, count: usize) -> Result<(), Box<dyn Error>> {
+        let mut letter = None;
+        validate_drive(drive_letter, |formatted_drive| {
+            letter = Some(formatted_drive[..1].to_string());
+            Ok(())
+        })?;
+        let Some(letter) = letter else {
+            return Ok(());
+        };
+
+        self.collect_entries(&letter)?;
+        let files: Vec<&ScanEntry> = self.entry_cache[&letter].iter().filter(|e| !e.is_directory).collect();
+        let start = Instant::now();
+        let estimate = estimate_compression(&files, blocks);
+        println!("Compression Estimate for Drive {}: (in {:?})", letter, start.elapsed());
+        print_compression_estimate(&estimate, count);
+        Ok(())
+    }
+
+    /// Reads the large files of a drive looking for all-zero 64 KB blocks, and lists what
+    /// making them sparse would free up next to the files that are sparse already.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive_letter` - The drive letter to analyze (e.g., "C", "D")
+    /// * `min_size` - Only files at least this big (in bytes) get read
+    /// * `throttle` - Optional read limit in bytes per second
+    /// * `count` - The number of files to display per list
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// print_zero_regions("D", 100 * 1_048_576, Some(50 * 1_048_576), 20).unwrap();
+    /// // Output:
+    /// // Sparse candidates (zero bytes that could be freed):
+    /// // D:\VMs\build.vhdx    80.00 GB  zeros 51.25 GB (64%)  reclaimable 51.25 GB
+    /// // Already sparse:
+    /// // D:\Downloads\game.part    40.00 GB  allocated 12.40 GB
+    /// ```
+    pub fn print_zero_regions(
+        &mut self,
+        drive_letter: &str,
+        min_size: u64,
+        throttle: Option<u64>,
+        count: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut letter = None;
+        validate_drive(drive_letter, |formatted_drive| {
+            letter = Some(formatted_drive[..1].to_string());
+            Ok(())
+        })?;
+        let Some(letter) = letter else {
+            return Ok(());
+        };
+
+        self.collect_entries(&letter)?;
+        let files: Vec<&ScanEntry> = self.entry_cache[&letter].iter().filter(|e| !e.is_directory).collect();
+        let start = Instant::now();
+        let (results, unreadable) = scan_zero_regions(&files, min_size, throttle);
+        println!("Zero Regions on Drive {}: (read in {:?})", letter, start.elapsed());
+        print_zero_regions(&results, unreadable, count);
+        Ok(())
+    }
+
+    /// Displays the symlinks, junctions, mount points and other reparse points on a volume.
+    ///
+    /// The scans never descend into these, so nothing behind them is counted twice.
+    /// With `follow`, targets outside the volume's own tree are walked and their size shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+    /// * `follow` - Whether to add up what the links point at
+    /// * `count` - The number of links to display in the results
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Display the first 2 links on drive C:
+    /// print_links("C", false, 2).unwrap();
+    /// // Output:
+    /// // Links on \\.\C::
+    /// // junction           \Documents and Settings -> C:\Users
+    /// // symlink            \Users\me\old -> D:\gone  [broken]
+    /// //
+    /// // Summary (312 total):
+    /// // cloud placeholder : 240
+    /// // ...
+    /// ```
+    pub fn print_links(&self, target: &str, follow: bool, count: usize) -> Result<(), Box<dyn Error>> {
+        validate_ntfs_source(target, |source| {
+            note(format!("Reading reparse points on {}...", source));
+            let found = self.scan_links(source, follow)?;
+            println!("Links on {}:", source);
+            links::print_links(&found, count);
+            Ok(())
+        })
+    }
+
+    /// Displays how many entries carry each attribute flag and how many bytes they hold.
+    ///
+    /// Reads the flags straight from $STANDARD_INFORMATION, so it works on images too.
+    /// An entry with several flags counts towards each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The drive letter (e.g., "C") or the path of a raw NTFS image
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Display the attribute counts of drive C:
+    /// print_attributes("C").unwrap();
+    /// // Output:
+    /// // Attributes on \\.\C::
+    /// // Attribute                 Entries         Size
+    /// // hidden                      41210      12.38 GB
+    /// // system                      10544       9.02 GB
+    /// // ...
+    /// ```
+    pub fn print_attributes(&self, target: &str) -> Result<(), Box<dyn Error>> {
+        validate_ntfs_source(target, |source| {
+            note(format!("Reading attributes on {}...", source));
+            let mut volume = RawVolume::open(source)?;
+            let records = volume.scan_records()?;
+            let entries = records
+                .iter()
+                .filter(|r| r.in_use && r.number >= FIRST_USER_RECORD && r.file_name.is_some())
+                .map(|r| (r.file_attributes, r.size));
+            let (counts, total) = count_attributes(entries);
+
+            println!("Attributes on {}:", source);
+            print_attribute_counts(&counts, total);
+            Ok(())
+        })
+    }
+
+    /// Displays what the last read of a drive couldn't list, reading it first if needed,
+    /// and how much of the used space the cached entries don't account for.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive letter to report on (e.g., "C")
+    /// * `count` - The number of error lines to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Display the scan errors of drive C:
+    /// print_scan_errors("C", 20).unwrap();
+    /// // Output:
+    /// //    Count  Error              Path
+    /// //        2  invalid name       C:\Users\username\Downloads
+    /// //
+    /// // 2 entries couldn't be read (2 invalid name), 0 folders skipped entirely. `scan-errors` lists them.
+    /// // Scanned 182.40 GB, 6.12 GB of used space unaccounted for (file system metadata, excluded paths and anything unreadable)
+    /// ```
+    pub fn print_scan_errors(&mut self, drive: &str, count: usize) -> Result<(), Box<dyn Error>> {
+        validate_drive(drive, |formatted_drive| {
+            let letter = &formatted_drive[..1];
+            self.collect_entries(letter)?;
+            if let Some(log) = self.scan_errors.get(letter) {
+                if self.json() {
+                    return print_json(&log.errors());
+                }
+                scan_errors::print_scan_errors(log, count);
+            }
+            Ok(())
+        })
+    }
+}
+
+// lets the age listings run queries straight over the MFT entries
+impl Queryable for FileInfo {
+    fn size(&self) -> u64 {
+        self.size
+    }
+    fn name(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.name)
+    }
+    fn path(&self) -> Cow<'_, str> {
+        self.path.to_string_lossy()
+    }
+    fn modified(&self) -> Option<i64> {
+        self.modified.map(|t| t.unix_timestamp())
+    }
+    fn is_directory(&self) -> bool {
+        self.is_directory
+    }
 }
 
 #[cfg(test)]
@@ -688,6 +1372,6 @@ mod ntfs_tests {
         let explorer = NtfsExplorer::new();
         println!("\n\n");
         // Display the top 10 empty folders
-        explorer.print_empty_folders("C", 100).unwrap();
+        explorer.print_empty_folders("C", 100, false).unwrap();
     }
-}
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,971 @@
+// raw NTFS structures, read straight off a volume or a disk image
+// ntfs_reader only hands out in-use records, so anything that needs deleted records,
+// the cluster bitmap or data runs goes through here instead
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+// attribute type codes
+pub const ATTR_STANDARD_INFORMATION: u32 = 0x10;
+pub const ATTR_FILE_NAME: u32 = 0x30;
+pub const ATTR_DATA: u32 = 0x80;
+pub const ATTR_REPARSE_POINT: u32 = 0xC0;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+
+// MFT record header flags
+const RECORD_IN_USE: u16 = 0x0001;
+const RECORD_IS_DIRECTORY: u16 = 0x0002;
+
+// well known records
+pub const ROOT_RECORD: u64 = 5;
+pub const BITMAP_RECORD: u64 = 6;
+pub const FIRST_USER_RECORD: u64 = 16;
+
+// fixups always use 512 byte strides, no matter what the sector size is
+const FIXUP_STRIDE: usize = 512;
+// how many records get read from disk in one go
+const READ_BATCH_RECORDS: u64 = 1024;
+// guards against parent reference loops in corrupted MFTs
+const MAX_PATH_DEPTH: usize = 256;
+// NTFS never formats clusters above 2M or records outside 256..=64K
+const MAX_CLUSTER_SIZE: u64 = 2 * 1024 * 1024;
+const MIN_RECORD_SIZE: u64 = 256;
+const MAX_RECORD_SIZE: u64 = 64 * 1024;
+// NTFS volumes have at most 2^32 clusters, so their $Bitmap is never bigger than this
+const MAX_RUNS_READ: u64 = 512 * 1024 * 1024;
+
+/// one contiguous run of clusters of a non-resident attribute.
+/// `lcn` is `None` for sparse runs, which have no clusters on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataRun {
+    pub lcn: Option<u64>,
+    pub length: u64,
+}
+
+/// the four MACB timestamps as raw FILETIME values (100ns ticks since 1601)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NtfsTimes {
+    pub created: u64,
+    pub modified: u64,
+    pub mft_changed: u64,
+    pub accessed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RawFileName {
+    pub parent: u64,
+    pub parent_seq: u16,
+    pub name: String,
+    /// false when the on-disk name has unpaired surrogates (`name` then holds U+FFFD)
+    pub valid_utf16: bool,
+    pub namespace: u8,
+    pub times: NtfsTimes,
+}
+
+/// the `$REPARSE_POINT` of a record. `target` is only filled in for symlinks and
+/// junctions/mount points, the only tags with a documented path layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReparsePoint {
+    pub tag: u32,
+    pub target: Option<String>,
+}
+
+/// everything we pull out of a single MFT record, in use or not
+#[derive(Debug, Clone, Default)]
+pub struct RawRecord {
+    pub number: u64,
+    pub sequence: u16,
+    pub in_use: bool,
+    pub is_directory: bool,
+    pub base_record: u64,
+    pub si_times: Option<NtfsTimes>,
+    pub file_attributes: u32,
+    /// the best name to show (win32, then posix, then dos)
+    pub file_name: Option<RawFileName>,
+    /// every other $FILE_NAME: hard links and the 8.3 dos name next to a long one
+    pub other_names: Vec<RawFileName>,
+    pub has_data: bool,
+    pub resident_data: bool,
+    pub size: u64,
+    pub data_runs: Vec<DataRun>,
+    pub reparse: Option<ReparsePoint>,
+    data_vcn: u64,
+}
+
+/// the volume's `$Bitmap`, one bit per cluster, set when the cluster is allocated
+pub struct ClusterBitmap {
+    bits: Vec<u8>,
+    pub total_clusters: u64,
+}
+
+impl ClusterBitmap {
+    pub fn is_allocated(&self, lcn: u64) -> bool {
+        self.bits
+            .get((lcn / 8) as usize)
+            .map(|byte| byte & (1 << (lcn % 8)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// counts how many clusters of a run are currently allocated (to anything)
+    pub fn allocated_in(&self, run: &DataRun) -> u64 {
+        match run.lcn {
+            Some(lcn) => self.allocated_range(lcn, lcn.saturating_add(run.length)),
+            None => 0,
+        }
+    }
+
+    /// counts the allocated clusters in `start..end`, a whole byte at a time where possible
+    pub fn allocated_range(&self, start: u64, end: u64) -> u64 {
+        let end = end.min(self.total_clusters);
+        let mut count = 0;
+        let mut lcn = start;
+        while lcn < end {
+            if lcn.is_multiple_of(8) && end - lcn >= 8 {
+                count += self.bits.get((lcn / 8) as usize).map_or(0, |b| b.count_ones() as u64);
+                lcn += 8;
+            } else {
+                count += self.is_allocated(lcn) as u64;
+                lcn += 1;
+            }
+        }
+        count
+    }
+
+    /// how many free clusters there are from `lcn` up to the next allocated one
+    pub fn free_from(&self, lcn: u64) -> u64 {
+        let mut end = lcn;
+        while end < self.total_clusters {
+            // whole free bytes at a time where possible
+            let free_byte = self.bits.get((end / 8) as usize) == Some(&0);
+            if end.is_multiple_of(8) && self.total_clusters - end >= 8 && free_byte {
+                end += 8;
+            } else if self.is_allocated(end) {
+                break;
+            } else {
+                end += 1;
+            }
+        }
+        end - lcn
+    }
+
+    /// calls `f(lcn, length)` for every contiguous free region of the volume, in order
+    pub fn free_runs<F>(&self, mut f: F)
+    where
+        F: FnMut(u64, u64),
+    {
+        let mut run_start: Option<u64> = None;
+        let mut lcn = 0;
+        while lcn < self.total_clusters {
+            // fast path, skip whole bytes that are all free or all used
+            if lcn.is_multiple_of(8) && self.total_clusters - lcn >= 8 {
+                match self.bits.get((lcn / 8) as usize).copied().unwrap_or(0) {
+                    0x00 => {
+                        run_start.get_or_insert(lcn);
+                        lcn += 8;
+                        continue;
+                    }
+                    0xFF => {
+                        if let Some(start) = run_start.take() {
+                            f(start, lcn - start);
+                        }
+                        lcn += 8;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            if self.is_allocated(lcn) {
+                if let Some(start) = run_start.take() {
+                    f(start, lcn - start);
+                }
+            } else {
+                run_start.get_or_insert(lcn);
+            }
+            lcn += 1;
+        }
+        if let Some(start) = run_start {
+            f(start, self.total_clusters - start);
+        }
+    }
+}
+
+pub struct RawVolume {
+    file: File,
+    pub source: String,
+    pub bytes_per_sector: u64,
+    pub cluster_size: u64,
+    pub total_clusters: u64,
+    pub mft_lcn: u64,
+    pub record_size: u64,
+    pub mft_size: u64,
+    mft_runs: Vec<DataRun>,
+}
+
+impl RawVolume {
+    /// opens a raw NTFS source, either a device path like `\\.\C:` or an image file
+    pub fn open(source: &str) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(source)
+            .map_err(|e| format!("Failed to open {} (are you running as admin?): {}", source, e))?;
+        let mut volume = RawVolume {
+            file,
+            source: source.to_string(),
+            // 4096 keeps the first read aligned on both 512e and 4Kn disks
+            bytes_per_sector: 4096,
+            cluster_size: 0,
+            total_clusters: 0,
+            mft_lcn: 0,
+            record_size: 0,
+            mft_size: 0,
+            mft_runs: Vec::new(),
+        };
+
+        let boot = volume.read_at(0, 512)?;
+        if &boot[3..11] != b"NTFS    " {
+            return Err(format!("{} is not an NTFS volume", source).into());
+        }
+
+        let corrupted = || format!("{} has a corrupted boot sector", source);
+        let bytes_per_sector = le_u16(&boot, 0x0B) as u64;
+        let sectors_per_cluster = match boot[0x0D] {
+            // values above 0x80 are a negative power of two (used for clusters > 64K)
+            raw if raw > 0x80 => 1u64.checked_shl(256 - raw as u32).unwrap_or(0),
+            raw => raw as u64,
+        };
+        let cluster_size = bytes_per_sector.checked_mul(sectors_per_cluster).unwrap_or(0);
+        if !(512..=4096).contains(&bytes_per_sector)
+            || !bytes_per_sector.is_power_of_two()
+            || !cluster_size.is_power_of_two()
+            || cluster_size > MAX_CLUSTER_SIZE
+        {
+            return Err(corrupted().into());
+        }
+
+        // same encoding as the cluster size, negative means 2^-n bytes, positive means clusters
+        let record_size = match boot[0x40] as i8 {
+            size if size < 0 => 1u64.checked_shl(size.unsigned_abs() as u32).unwrap_or(0),
+            size => size as u64 * cluster_size,
+        };
+        if !record_size.is_power_of_two() || !(MIN_RECORD_SIZE..=MAX_RECORD_SIZE).contains(&record_size) {
+            return Err(corrupted().into());
+        }
+
+        volume.bytes_per_sector = bytes_per_sector;
+        volume.cluster_size = cluster_size;
+        volume.total_clusters = le_u64(&boot, 0x28) / sectors_per_cluster;
+        volume.mft_lcn = le_u64(&boot, 0x30);
+        volume.record_size = record_size;
+
+        // record 0 is $MFT itself, its data runs tell us where the rest of the records live
+        let mft_offset = volume.cluster_offset(volume.mft_lcn)?;
+        let mut mft_record = volume.read_at(mft_offset, volume.record_size as usize)?;
+        let mft = parse_record(&mut mft_record, 0).ok_or("Failed to parse the $MFT record")?;
+        if mft.data_runs.is_empty() {
+            return Err("$MFT has no data runs".into());
+        }
+        volume.mft_size = mft.size;
+        volume.mft_runs = mft.data_runs;
+
+        Ok(volume)
+    }
+
+    /// where $MFT actually sits, from its own data runs
+    pub fn mft_extents(&self) -> Vec<(u64, u64)> {
+        extents(&self.mft_runs)
+    }
+
+    pub fn record_count(&self) -> u64 {
+        self.mft_size / self.record_size
+    }
+
+    /// byte offset of a cluster, failing on run lists that point past any real volume
+    fn cluster_offset(&self, lcn: u64) -> io::Result<u64> {
+        lcn.checked_mul(self.cluster_size)
+            .ok_or_else(|| corrupted_runs(&self.source))
+    }
+
+    /// reads `len` bytes at `offset`, widening the read to sector boundaries
+    /// since raw devices refuse unaligned reads
+    fn read_at(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let sector = self.bytes_per_sector;
+        let start = offset - offset % sector;
+        let end = offset
+            .checked_add(len as u64)
+            .and_then(|end| end.div_ceil(sector).checked_mul(sector))
+            .ok_or_else(|| corrupted_runs(&self.source))?;
+
+        let mut buf = vec![0u8; (end - start) as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.file.read(&mut buf[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+
+        let skip = (offset - start) as usize;
+        if filled < skip + len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("read past the end of {}", self.source),
+            ));
+        }
+        buf.truncate(skip + len);
+        buf.drain(..skip);
+        Ok(buf)
+    }
+
+    /// reads the clusters of a run list back to back, sparse runs read as zeros. `limit` comes
+    /// from the disk too, so asking for more than the volume (or `MAX_RUNS_READ`) holds fails
+    pub fn read_runs(&mut self, runs: &[DataRun], limit: u64) -> io::Result<Vec<u8>> {
+        let volume_bytes = self.total_clusters.saturating_mul(self.cluster_size);
+        if limit > volume_bytes.min(MAX_RUNS_READ) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} has an attribute of {} bytes, more than can be read into memory", self.source, limit),
+            ));
+        }
+        let mut data = Vec::new();
+        for run in runs {
+            if data.len() as u64 >= limit {
+                break;
+            }
+            let bytes = self.cluster_offset(run.length)?.min(limit - data.len() as u64);
+            match run.lcn {
+                Some(lcn) => data.extend(self.read_at(self.cluster_offset(lcn)?, bytes as usize)?),
+                None => data.resize(data.len() + bytes as usize, 0),
+            }
+        }
+        Ok(data)
+    }
+
+    /// finds the byte offset of a record by walking the $MFT data runs
+    fn record_offset(&self, number: u64) -> Option<u64> {
+        let mut remaining = number.checked_mul(self.record_size)?;
+        for run in &self.mft_runs {
+            let run_bytes = run.length.checked_mul(self.cluster_size)?;
+            if remaining < run_bytes {
+                return run.lcn?.checked_mul(self.cluster_size)?.checked_add(remaining);
+            }
+            remaining -= run_bytes;
+        }
+        None
+    }
+
+    pub fn read_record(&mut self, number: u64) -> Result<RawRecord, Box<dyn Error>> {
+        let offset = self
+            .record_offset(number)
+            .ok_or_else(|| format!("Record {} is outside the MFT", number))?;
+        let mut buf = self.read_at(offset, self.record_size as usize)?;
+        parse_record(&mut buf, number).ok_or_else(|| format!("Record {} is corrupted", number).into())
+    }
+
+    /// calls `f` for every readable record in the MFT, including the ones not in use
+    pub fn for_each_record<F>(&mut self, mut f: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(RawRecord),
+    {
+        let total = self.record_count();
+        let record_size = self.record_size as usize;
+        let mut number = 0u64;
+
+        for run in self.mft_runs.clone() {
+            let run_bytes = self.cluster_offset(run.length)?;
+            let run_records = run_bytes / self.record_size;
+            let Some(lcn) = run.lcn else {
+                number = number.saturating_add(run_records);
+                continue;
+            };
+
+            // checking the end of the run up front keeps the offsets inside it from overflowing
+            let start = self.cluster_offset(lcn)?;
+            start.checked_add(run_bytes).ok_or_else(|| corrupted_runs(&self.source))?;
+            let mut done = 0u64;
+            while done < run_records && number < total {
+                let batch = (run_records - done).min(READ_BATCH_RECORDS).min(total - number);
+                let mut buf = self.read_at(start + done * self.record_size, batch as usize * record_size)?;
+                for chunk in buf.chunks_exact_mut(record_size) {
+                    if let Some(record) = parse_record(chunk, number) {
+                        f(record);
+                    }
+                    number += 1;
+                }
+                done += batch;
+            }
+        }
+        Ok(())
+    }
+
+    /// reads every record and folds extension records into their base record,
+    /// so files with an attribute list still end up with their full run list
+    pub fn scan_records(&mut self) -> Result<Vec<RawRecord>, Box<dyn Error>> {
+        let mut records = Vec::new();
+        let mut extensions = Vec::new();
+        self.for_each_record(|record| {
+            if record.base_record != 0 {
+                extensions.push(record);
+            } else {
+                records.push(record);
+            }
+        })?;
+
+        let index = record_index(&records);
+        extensions.sort_by_key(|ext| (ext.base_record, ext.data_vcn));
+        for ext in extensions {
+            let Some(&i) = index.get(&ext.base_record) else {
+                continue;
+            };
+            let base = &mut records[i];
+            if ext.has_data {
+                if ext.data_vcn == 0 {
+                    base.size = ext.size;
+                }
+                base.has_data = true;
+                base.data_runs.extend(ext.data_runs);
+            }
+            match (&base.file_name, ext.file_name) {
+                (None, name) => base.file_name = name,
+                (Some(_), Some(name)) => base.other_names.push(name),
+                (Some(_), None) => {}
+            }
+            base.other_names.extend(ext.other_names);
+        }
+        Ok(records)
+    }
+
+    pub fn read_bitmap(&mut self) -> Result<ClusterBitmap, Box<dyn Error>> {
+        let record = self.read_record(BITMAP_RECORD)?;
+        let bits = self.read_runs(&record.data_runs, record.size)?;
+        Ok(ClusterBitmap {
+            bits,
+            total_clusters: self.total_clusters,
+        })
+    }
+}
+
+/// merges physically adjacent runs and drops sparse ones, leaving the extents
+/// that actually exist on disk as `(lcn, length)` pairs
+pub fn extents(runs: &[DataRun]) -> Vec<(u64, u64)> {
+    let mut extents: Vec<(u64, u64)> = Vec::new();
+    for run in runs {
+        let Some(lcn) = run.lcn else {
+            continue;
+        };
+        match extents.last_mut() {
+            Some((start, length)) if start.checked_add(*length) == Some(lcn) => {
+                *length = length.saturating_add(run.length)
+            }
+            _ => extents.push((lcn, run.length)),
+        }
+    }
+    extents
+}
+
+/// maps record numbers to their position in a record list
+pub fn record_index(records: &[RawRecord]) -> HashMap<u64, usize> {
+    records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| (record.number, i))
+        .collect()
+}
+
+/// rebuilds the folder a record lived in by walking its parent references up to the root.
+///
+/// a parent that has been reused since (sequence number mismatch) ends the walk,
+/// and the path gets an `<orphan>` prefix instead of a drive root
+pub fn resolve_parent_path(
+    records: &[RawRecord],
+    index: &HashMap<u64, usize>,
+    record: &RawRecord,
+) -> String {
+    resolve_folder_of(records, index, record.file_name.as_ref())
+}
+
+/// like `resolve_parent_path`, for any one of a record's names, since hard links can
+/// live in other folders than the name that gets shown
+pub fn resolve_folder_of(
+    records: &[RawRecord],
+    index: &HashMap<u64, usize>,
+    name: Option<&RawFileName>,
+) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut current = name.map(|n| (n.parent, n.parent_seq));
+
+    while let Some((parent, seq)) = current {
+        if parent == ROOT_RECORD {
+            parts.reverse();
+            return format!("\\{}", parts.join("\\"));
+        }
+        if parts.len() >= MAX_PATH_DEPTH {
+            break;
+        }
+        let Some(parent_record) = index.get(&parent).map(|&i| &records[i]) else {
+            break;
+        };
+        // freeing a record bumps its sequence number, so a deleted parent is one ahead
+        let same_parent = parent_record.sequence == seq
+            || (!parent_record.in_use && parent_record.sequence == seq.wrapping_add(1));
+        let Some(name) = parent_record.file_name.as_ref().filter(|_| same_parent) else {
+            break;
+        };
+        parts.push(&name.name);
+        current = Some((name.parent, name.parent_seq));
+    }
+
+    parts.reverse();
+    format!("<orphan>\\{}", parts.join("\\"))
+}
+
+impl RawRecord {
+    /// the shown name first, then every other one
+    pub fn names(&self) -> impl Iterator<Item = &RawFileName> {
+        self.file_name.iter().chain(&self.other_names)
+    }
+}
+
+/// parses a single MFT record in place (fixups get applied to `buf`).
+/// returns `None` for empty, torn or otherwise unreadable records
+pub fn parse_record(buf: &mut [u8], number: u64) -> Option<RawRecord> {
+    if buf.len() < 0x30 || &buf[0..4] != b"FILE" || !apply_fixups(buf) {
+        return None;
+    }
+
+    let flags = le_u16(buf, 0x16);
+    let mut record = RawRecord {
+        number,
+        sequence: le_u16(buf, 0x10),
+        in_use: flags & RECORD_IN_USE != 0,
+        is_directory: flags & RECORD_IS_DIRECTORY != 0,
+        base_record: le_u64(buf, 0x20) & 0x0000_FFFF_FFFF_FFFF,
+        ..Default::default()
+    };
+
+    let used = (le_u32(buf, 0x18) as usize).min(buf.len());
+    let mut pos = le_u16(buf, 0x14) as usize;
+    let mut name_rank = 0;
+
+    while pos + 16 <= used {
+        let attr_type = le_u32(buf, pos);
+        let attr_len = le_u32(buf, pos + 4) as usize;
+        if attr_type == ATTR_END || attr_len < 16 || pos + attr_len > used {
+            break;
+        }
+        let attr = &buf[pos..pos + attr_len];
+        let non_resident = attr[8] != 0;
+        let unnamed = attr[9] == 0;
+
+        match attr_type {
+            ATTR_STANDARD_INFORMATION if !non_resident => {
+                if let Some(value) = resident_value(attr).filter(|v| v.len() >= 0x24) {
+                    record.si_times = Some(read_times(value, 0x00));
+                    record.file_attributes = le_u32(value, 0x20);
+                }
+            }
+            ATTR_FILE_NAME if !non_resident => {
+                if let Some(name) = resident_value(attr).and_then(parse_file_name) {
+                    // prefer win32 names, then posix, and only fall back to 8.3 dos names
+                    let rank = match name.namespace {
+                        1 | 3 => 3,
+                        0 => 2,
+                        _ => 1,
+                    };
+                    if rank > name_rank {
+                        name_rank = rank;
+                        record.other_names.extend(record.file_name.replace(name));
+                    } else {
+                        record.other_names.push(name);
+                    }
+                }
+            }
+            // only the unnamed stream, alternate data streams are not the file's size
+            ATTR_DATA if unnamed => {
+                record.has_data = true;
+                if non_resident {
+                    let vcn = le_u64(attr, 0x10);
+                    let runs_offset = le_u16(attr, 0x20) as usize;
+                    if vcn == 0 {
+                        record.size = le_u64(attr, 0x30);
+                    }
+                    record.data_vcn = vcn;
+                    record.data_runs = attr
+                        .get(runs_offset..)
+                        .map(decode_data_runs)
+                        .unwrap_or_default();
+                } else {
+                    record.resident_data = true;
+                    record.size = le_u32(attr, 0x10) as u64;
+                }
+            }
+            // reparse data is practically always resident, the tag stays 0 when it isn't
+            ATTR_REPARSE_POINT => {
+                record.reparse = Some(match resident_value(attr).filter(|_| !non_resident) {
+                    Some(value) => parse_reparse_point(value),
+                    None => ReparsePoint { tag: 0, target: None },
+                });
+            }
+            _ => {}
+        }
+        pos += attr_len;
+    }
+
+    Some(record)
+}
+
+fn parse_file_name(value: &[u8]) -> Option<RawFileName> {
+    if value.len() < 0x42 {
+        return None;
+    }
+    let name_len = value[0x40] as usize;
+    let units: Vec<u16> = value
+        .get(0x42..0x42 + name_len * 2)?
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    let reference = le_u64(value, 0x00);
+
+    Some(RawFileName {
+        parent: reference & 0x0000_FFFF_FFFF_FFFF,
+        parent_seq: (reference >> 48) as u16,
+        name: String::from_utf16_lossy(&units),
+        valid_utf16: String::from_utf16(&units).is_ok(),
+        namespace: value[0x41],
+        times: read_times(value, 0x08),
+    })
+}
+
+pub const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+pub const IO_REPARSE_TAG_SYMLINK: u32 = 0xA000_000C;
+
+/// Reads the tag of a reparse buffer and, for symlinks and mount points, the path it
+/// points to. The print name is preferred, the substitute name (`\??\C:\...`) is the fallback.
+pub fn parse_reparse_point(value: &[u8]) -> ReparsePoint {
+    if value.len() < 8 {
+        return ReparsePoint { tag: 0, target: None };
+    }
+    let tag = le_u32(value, 0);
+    // symlinks carry an extra flags field before their path buffer
+    let buffer_start = match tag {
+        IO_REPARSE_TAG_MOUNT_POINT => 16,
+        IO_REPARSE_TAG_SYMLINK => 20,
+        _ => return ReparsePoint { tag, target: None },
+    };
+    let read_name = |offset_at: usize| {
+        let offset = buffer_start + le_u16(value, offset_at) as usize;
+        let len = le_u16(value, offset_at + 2) as usize;
+        let units: Vec<u16> = value
+            .get(offset..offset + len)?
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        Some(String::from_utf16_lossy(&units)).filter(|name| !name.is_empty())
+    };
+    let target = read_name(12)
+        .or_else(|| read_name(8))
+        .map(|name| name.strip_prefix("\\??\\").map(str::to_string).unwrap_or(name));
+    ReparsePoint { tag, target }
+}
+
+fn resident_value(attr: &[u8]) -> Option<&[u8]> {
+    let len = le_u32(attr, 0x10) as usize;
+    let offset = le_u16(attr, 0x14) as usize;
+    attr.get(offset..offset + len)
+}
+
+fn read_times(value: &[u8], at: usize) -> NtfsTimes {
+    NtfsTimes {
+        created: le_u64(value, at),
+        modified: le_u64(value, at + 0x08),
+        mft_changed: le_u64(value, at + 0x10),
+        accessed: le_u64(value, at + 0x18),
+    }
+}
+
+/// swaps the update sequence numbers at the end of every 512 byte stride back to the
+/// original bytes. returns false if a stride doesn't carry the expected number (torn write)
+fn apply_fixups(record: &mut [u8]) -> bool {
+    let usa_offset = le_u16(record, 0x04) as usize;
+    let usa_count = le_u16(record, 0x06) as usize;
+    if usa_count == 0 || usa_offset + usa_count * 2 > record.len() {
+        return false;
+    }
+
+    let usn = [record[usa_offset], record[usa_offset + 1]];
+    for i in 1..usa_count {
+        let end = i * FIXUP_STRIDE;
+        if end > record.len() || record[end - 2..end] != usn {
+            return false;
+        }
+        let fix = usa_offset + i * 2;
+        record[end - 2] = record[fix];
+        record[end - 1] = record[fix + 1];
+    }
+    true
+}
+
+/// decodes a data run list. every run starts with a header byte, low nibble is the size
+/// of the length field, high nibble the size of the (signed, relative) offset field
+pub fn decode_data_runs(buf: &[u8]) -> Vec<DataRun> {
+    let mut runs = Vec::new();
+    let mut pos = 0;
+    let mut lcn: i64 = 0;
+
+    while let Some(&header) = buf.get(pos) {
+        if header == 0 {
+            break;
+        }
+        let len_size = (header & 0x0F) as usize;
+        let off_size = (header >> 4) as usize;
+        pos += 1;
+        if len_size == 0 || len_size > 8 || off_size > 8 || pos + len_size + off_size > buf.len() {
+            break;
+        }
+
+        let length = read_uint(&buf[pos..pos + len_size]);
+        pos += len_size;
+        if off_size == 0 {
+            runs.push(DataRun { lcn: None, length });
+            continue;
+        }
+
+        match lcn.checked_add(read_int(&buf[pos..pos + off_size])) {
+            Some(next) if next >= 0 => lcn = next,
+            _ => break,
+        }
+        pos += off_size;
+        runs.push(DataRun {
+            lcn: Some(lcn as u64),
+            length,
+        });
+    }
+    runs
+}
+
+fn corrupted_runs(source: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} has data runs outside of any volume", source),
+    )
+}
+
+fn read_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+fn read_int(bytes: &[u8]) -> i64 {
+    let value = read_uint(bytes) as i64;
+    let bits = bytes.len() * 8;
+    // sign extend from the top bit of the last byte
+    if bits < 64 && bytes[bytes.len() - 1] & 0x80 != 0 {
+        value - (1i64 << bits)
+    } else {
+        value
+    }
+}
+
+fn le_u16(buf: &[u8], at: usize) -> u16 {
+    buf.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .unwrap_or(0)
+}
+
+fn le_u32(buf: &[u8], at: usize) -> u32 {
+    buf.get(at..at + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+fn le_u64(buf: &[u8], at: usize) -> u64 {
+    buf.get(at..at + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod ntfs_raw_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+
+    #[test]
+    fn test_reparse_point() {
+        let wide = |text: &str| text.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+        let substitute = wide("\\??\\D:\\Data");
+        let mut value = Vec::new();
+        value.extend(IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+        value.extend([0u8; 4]);
+        // substitute name at 0, print name empty right after it
+        value.extend(0u16.to_le_bytes());
+        value.extend((substitute.len() as u16).to_le_bytes());
+        value.extend((substitute.len() as u16).to_le_bytes());
+        value.extend(0u16.to_le_bytes());
+        value.extend(&substitute);
+
+        let point = parse_reparse_point(&value);
+        assert_eq!(point.tag, IO_REPARSE_TAG_MOUNT_POINT);
+        assert_eq!(point.target.as_deref(), Some("D:\\Data"));
+        assert_eq!(parse_reparse_point(&0x8000_0013u32.to_le_bytes().repeat(2)).target, None);
+    }
+
+    #[test]
+    fn test_data_runs() {
+        // 0x18 clusters at 0x5634, then 0x10 clusters 0x100 back, then a sparse run of 8
+        let runs = decode_data_runs(&[0x21, 0x18, 0x34, 0x56, 0x21, 0x10, 0x00, 0xFF, 0x01, 0x08, 0x00]);
+        assert_eq!(
+            runs,
+            vec![
+                DataRun { lcn: Some(0x5634), length: 0x18 },
+                DataRun { lcn: Some(0x5534), length: 0x10 },
+                DataRun { lcn: None, length: 0x08 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_runs_overflow() {
+        // a run at the very top of the lcn range, then one that would step past it
+        let runs = decode_data_runs(&[0x81, 0x01, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0x11, 0x01, 0x7F]);
+        assert_eq!(runs, vec![DataRun { lcn: Some(0x7FFF_FFFF_FFFF_FFF0), length: 1 }]);
+    }
+
+    #[test]
+    fn test_corrupted_boot_sector() {
+        let dir = ScratchDir::new("ntfs-boot");
+        let path = dir.join("boot.bin");
+        let boot = |sectors_per_cluster: u8, record_size: u8| {
+            let mut boot = vec![0u8; 512];
+            boot[3..11].copy_from_slice(b"NTFS    ");
+            boot[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+            boot[0x0D] = sectors_per_cluster;
+            boot[0x40] = record_size;
+            std::fs::write(&path, boot).unwrap();
+            RawVolume::open(path.to_str().unwrap()).err().map(|e| e.to_string())
+        };
+        let corrupted = Some(format!("{} has a corrupted boot sector", path.display()));
+
+        // zero sized records, a shift past 64 bits, and clusters beyond 2M
+        assert_eq!(boot(8, 0), corrupted);
+        assert_eq!(boot(8, 0x80), corrupted);
+        assert_eq!(boot(0x81, 0xF6), corrupted);
+        assert_eq!(boot(0, 0xF6), corrupted);
+        // valid geometry gets past the checks and fails on the missing $MFT instead
+        assert_ne!(boot(8, 0xF6), corrupted);
+    }
+
+    #[test]
+    fn test_read_runs_limit() {
+        let dir = ScratchDir::new("ntfs-runs");
+        let path = dir.join("volume.bin");
+        std::fs::write(&path, vec![0xAAu8; 4 * 4096]).unwrap();
+        let mut volume = RawVolume {
+            file: File::open(&path).unwrap(),
+            source: path.display().to_string(),
+            bytes_per_sector: 512,
+            cluster_size: 4096,
+            total_clusters: 4,
+            mft_lcn: 0,
+            record_size: 1024,
+            mft_size: 0,
+            mft_runs: Vec::new(),
+        };
+        let sparse = [DataRun { lcn: None, length: 1 << 40 }];
+        let used = [DataRun { lcn: Some(1), length: 1 }];
+
+        assert_eq!(volume.read_runs(&sparse, 100).unwrap(), vec![0; 100]);
+        assert_eq!(volume.read_runs(&used, 4096).unwrap(), vec![0xAA; 4096]);
+        // a size only a corrupted record has, bigger than the whole volume
+        let error = volume.read_runs(&sparse, 5 * 4096).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(volume.read_runs(&sparse, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_extents() {
+        let runs = [
+            DataRun { lcn: Some(100), length: 10 },
+            DataRun { lcn: None, length: 4 },
+            DataRun { lcn: Some(110), length: 5 },
+            DataRun { lcn: Some(50), length: 2 },
+        ];
+        assert_eq!(extents(&runs), vec![(100, 15), (50, 2)]);
+    }
+
+    #[test]
+    fn test_free_runs() {
+        // clusters 0-3 used, 4-15 free, 16-19 used, 20-23 free
+        let bitmap = ClusterBitmap {
+            bits: vec![0b0000_1111, 0b0000_0000, 0b0000_1111],
+            total_clusters: 24,
+        };
+        let mut runs = Vec::new();
+        bitmap.free_runs(|lcn, length| runs.push((lcn, length)));
+        assert_eq!(runs, vec![(4, 12), (20, 4)]);
+        assert_eq!(bitmap.allocated_range(0, 24), 8);
+        assert_eq!(bitmap.allocated_range(2, 18), 4);
+        assert_eq!((bitmap.free_from(0), bitmap.free_from(4), bitmap.free_from(6)), (0, 12, 10));
+        assert_eq!((bitmap.free_from(20), bitmap.free_from(24)), (4, 0));
+    }
+
+    #[test]
+    fn test_fixups() {
+        let mut record = vec![0u8; 1024];
+        record[0..4].copy_from_slice(b"FILE");
+        record[0x04..0x06].copy_from_slice(&0x30u16.to_le_bytes());
+        record[0x06..0x08].copy_from_slice(&3u16.to_le_bytes());
+        // usn, then the real bytes that belong at the end of each stride
+        record[0x30..0x36].copy_from_slice(&[0xAB, 0xCD, 0x11, 0x22, 0x33, 0x44]);
+        record[510..512].copy_from_slice(&[0xAB, 0xCD]);
+        record[1022..1024].copy_from_slice(&[0xAB, 0xCD]);
+
+        assert!(apply_fixups(&mut record));
+        assert_eq!(&record[510..512], &[0x11, 0x22]);
+        assert_eq!(&record[1022..1024], &[0x33, 0x44]);
+
+        // the second pass sees the real bytes instead of the usn, so it counts as torn
+        assert!(!apply_fixups(&mut record));
+    }
+
+    #[test]
+    fn test_every_file_name() {
+        let mut record = vec![0u8; 1024];
+        record[0..4].copy_from_slice(b"FILE");
+        record[0x04..0x06].copy_from_slice(&0x30u16.to_le_bytes());
+        record[0x06..0x08].copy_from_slice(&3u16.to_le_bytes());
+        record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+        record[0x16..0x18].copy_from_slice(&RECORD_IN_USE.to_le_bytes());
+        record[0x18..0x1C].copy_from_slice(&1024u32.to_le_bytes());
+        for at in [0x30, 510, 1022] {
+            record[at] = 0x01;
+        }
+
+        // a dos name, the long name it belongs to, then a hard link in another folder
+        let mut pos = 0x38;
+        for (parent, namespace, name) in [(5u64, 2u8, "REPORT~1.TXT"), (5, 1, "report final.txt"), (40, 1, "nul.txt")] {
+            let units: Vec<u16> = name.encode_utf16().collect();
+            let value_len = 0x42 + units.len() * 2;
+            let attr_len = (0x18 + value_len + 7) & !7;
+            let attr = &mut record[pos..pos + attr_len];
+            attr[0..4].copy_from_slice(&ATTR_FILE_NAME.to_le_bytes());
+            attr[4..8].copy_from_slice(&(attr_len as u32).to_le_bytes());
+            attr[0x10..0x14].copy_from_slice(&(value_len as u32).to_le_bytes());
+            attr[0x14..0x16].copy_from_slice(&0x18u16.to_le_bytes());
+            let value = &mut attr[0x18..0x18 + value_len];
+            value[0..8].copy_from_slice(&parent.to_le_bytes());
+            value[0x40] = units.len() as u8;
+            value[0x41] = namespace;
+            for (i, unit) in units.iter().enumerate() {
+                value[0x42 + i * 2..0x44 + i * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            pos += attr_len;
+        }
+        record[pos..pos + 4].copy_from_slice(&ATTR_END.to_le_bytes());
+
+        let parsed = parse_record(&mut record, 64).unwrap();
+        assert_eq!(parsed.file_name.as_ref().unwrap().name, "report final.txt");
+        let names: Vec<(u64, &str)> = parsed.names().map(|n| (n.parent, n.name.as_str())).collect();
+        assert_eq!(names, [(5, "report final.txt"), (5, "REPORT~1.TXT"), (40, "nul.txt")]);
+    }
+}
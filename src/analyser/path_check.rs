@@ -0,0 +1,67 @@
+// checks for names and paths that NTFS stores fine but Win32 tools choke on
+use super::types::PathIssue;
+
+/// MAX_PATH, including the terminating null
+pub const MAX_PATH: usize = 260;
+
+// the superscript digits are reserved too, Win32 treats them like 1-3
+const RESERVED_NAMES: [&str; 32] = [
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$", "COM0", "COM1", "COM2", "COM3", "COM4",
+    "COM5", "COM6", "COM7", "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3",
+    "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// Issues with a single name. Reserved device names count with any extension too,
+/// since `nul.txt` still opens the NUL device.
+pub fn name_issues(name: &str) -> Vec<PathIssue> {
+    let mut issues = Vec::new();
+
+    if name.ends_with(['.', ' ']) {
+        issues.push(PathIssue::TrailingDotOrSpace);
+    }
+
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        issues.push(PathIssue::ReservedName);
+    }
+
+    if name.chars().any(|c| (c as u32) < 0x20 || c == '\u{7f}') {
+        issues.push(PathIssue::ControlCharacter);
+    }
+    if name.contains(['<', '>', ':', '"', '|', '?', '*']) {
+        issues.push(PathIssue::ForbiddenCharacter);
+    }
+    issues
+}
+
+/// true when a full path (drive letter included) doesn't fit in MAX_PATH
+pub fn path_too_long(path: &str) -> bool {
+    path.encode_utf16().count() >= MAX_PATH
+}
+
+#[cfg(test)]
+mod path_check_tests {
+    use super::*;
+
+    #[test]
+    fn test_name_issues() {
+        assert!(name_issues("report.pdf").is_empty());
+        assert!(name_issues("console.log").is_empty());
+        assert_eq!(name_issues("NUL.txt"), vec![PathIssue::ReservedName]);
+        assert_eq!(name_issues("conin$"), vec![PathIssue::ReservedName]);
+        assert_eq!(name_issues("lpt0.log"), vec![PathIssue::ReservedName]);
+        assert_eq!(name_issues("com².dat"), vec![PathIssue::ReservedName]);
+        assert!(name_issues("com10").is_empty());
+        assert_eq!(name_issues("com1 "), vec![PathIssue::TrailingDotOrSpace, PathIssue::ReservedName]);
+        assert_eq!(name_issues("notes."), vec![PathIssue::TrailingDotOrSpace]);
+        assert_eq!(name_issues("bell\u{7}"), vec![PathIssue::ControlCharacter]);
+        assert_eq!(name_issues("what?.txt"), vec![PathIssue::ForbiddenCharacter]);
+    }
+
+    #[test]
+    fn test_path_length() {
+        let short = format!("C:\\{}", "a".repeat(256));
+        assert!(!path_too_long(&short));
+        assert!(path_too_long(&format!("{}b", short)));
+    }
+}
@@ -0,0 +1,98 @@
+// permission checks for Unix file systems: world-writable paths, setuid/setgid binaries,
+// orphaned owners and home directory files that other users can get at
+use super::types::{PermIssue, UnixPermissions};
+use std::{collections::HashSet, fs};
+
+const STICKY: u32 = 0o1000;
+const SETUID: u32 = 0o4000;
+const SETGID: u32 = 0o2000;
+const OTHER_WRITE: u32 = 0o002;
+const GROUP_OTHER_WRITE: u32 = 0o022;
+const GROUP_OTHER_ALL: u32 = 0o077;
+
+/// folders and file names in a home directory that should only be readable by the owner
+const PRIVATE_NAMES: [&str; 8] = [
+    ".ssh", ".gnupg", ".aws", ".kube", ".netrc", ".pgpass", "id_rsa", "id_ed25519",
+];
+
+/// uids listed in a passwd file
+pub fn parse_passwd_uids(passwd: &str) -> HashSet<u32> {
+    passwd
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split(':').nth(2)?.parse().ok())
+        .collect()
+}
+
+/// Local accounts from /etc/passwd. `None` when it can't be read, in which case
+/// owners aren't checked at all (accounts from LDAP and the like aren't in there either).
+pub fn known_uids() -> Option<HashSet<u32>> {
+    fs::read_to_string("/etc/passwd").ok().map(|passwd| parse_passwd_uids(&passwd))
+}
+
+fn in_home(path: &str) -> bool {
+    path.starts_with("/home/") || path.starts_with("/root/")
+}
+
+fn is_private(path: &str) -> bool {
+    path.split('/').any(|part| PRIVATE_NAMES.contains(&part))
+}
+
+/// Everything wrong with one file or directory.
+/// Setgid on directories is how shared folders keep their group, so that one's left alone.
+pub fn permission_issues(
+    path: &str,
+    permissions: UnixPermissions,
+    is_directory: bool,
+    known_uids: Option<&HashSet<u32>>,
+) -> Vec<PermIssue> {
+    let mode = permissions.mode;
+    let mut issues = Vec::new();
+
+    let world_writable = mode & OTHER_WRITE != 0 && !(is_directory && mode & STICKY != 0);
+    if world_writable {
+        issues.push(PermIssue::WorldWritable);
+    }
+    if !is_directory && mode & SETUID != 0 {
+        issues.push(PermIssue::Setuid);
+    }
+    if !is_directory && mode & SETGID != 0 {
+        issues.push(PermIssue::Setgid);
+    }
+    if known_uids.is_some_and(|uids| !uids.contains(&permissions.uid)) {
+        issues.push(PermIssue::UnknownOwner);
+    }
+    if in_home(path) && !world_writable {
+        let loose = if is_private(path) { GROUP_OTHER_ALL } else { GROUP_OTHER_WRITE };
+        if mode & loose != 0 {
+            issues.push(PermIssue::PermissiveInHome);
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod perm_audit_tests {
+    use super::*;
+
+    fn perms(mode: u32, uid: u32) -> UnixPermissions {
+        UnixPermissions { mode, uid, gid: 0 }
+    }
+
+    #[test]
+    fn test_permission_issues() {
+        let uids = parse_passwd_uids("root:x:0:0:root:/root:/bin/bash\n# comment\nme:x:1000:1000::/home/me:/bin/sh\n");
+        assert_eq!(uids, HashSet::from([0, 1000]));
+        let check = |path, mode, uid, dir| permission_issues(path, perms(mode, uid), dir, Some(&uids));
+
+        assert!(check("/tmp", 0o1777, 0, true).is_empty());
+        assert_eq!(check("/srv/drop", 0o777, 0, true), vec![PermIssue::WorldWritable]);
+        assert_eq!(check("/usr/bin/sudo", 0o4755, 0, false), vec![PermIssue::Setuid]);
+        assert!(check("/srv/shared", 0o2775, 0, true).is_empty());
+        assert_eq!(check("/opt/app/run.sh", 0o755, 1234, false), vec![PermIssue::UnknownOwner]);
+        assert_eq!(check("/home/me/notes.txt", 0o664, 1000, false), vec![PermIssue::PermissiveInHome]);
+        assert!(check("/home/me/notes.txt", 0o644, 1000, false).is_empty());
+        assert_eq!(check("/home/me/.ssh/id_rsa", 0o640, 1000, false), vec![PermIssue::PermissiveInHome]);
+        assert!(permission_issues("/x", perms(0o755, 4242), false, None).is_empty());
+    }
+}
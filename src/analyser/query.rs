@@ -0,0 +1,528 @@
+// a small expression language for picking files out of a scan, e.g.
+// size > 1GiB and ext in (mkv, mp4) and modified < -6mo and path ~ 'Downloads' order by size desc limit 50
+use super::types::{FileInfo, ScanEntry};
+use crate::utility::constants::{
+    DATE_FORMAT, LARGEST_FILES_QUERY, OLD_LARGE_FILES_QUERY, RECENT_LARGE_FILES_QUERY,
+};
+use crate::utility::utils::{parse_date, parse_duration, parse_size};
+use chrono::{NaiveDateTime, Utc};
+use std::{borrow::Cow, cmp::Ordering, error::Error, path::Path};
+
+pub const QUERY_USAGE: &str = "Usage: query [drive] <expression> [order by <field> [asc|desc]] [limit N]\n\
+    fields: size, name, path, ext, modified, type (file | dir)\n\
+    e.g. query C \"size > 1GiB and ext in (mkv, mp4) and modified < -6mo and path ~ 'Downloads'\" order by size desc limit 50";
+
+/// result sets other commands (like `archive`) can act on, by name
+pub const RESULT_SETS: [&str; 3] = ["old-large-files", "recent-large-files", "largest-files"];
+
+/// the canned query behind a named result set
+pub fn result_set_query(name: &str) -> Option<&'static str> {
+    match name {
+        "old-large-files" => Some(OLD_LARGE_FILES_QUERY),
+        "recent-large-files" => Some(RECENT_LARGE_FILES_QUERY),
+        "largest-files" => Some(LARGEST_FILES_QUERY),
+        _ => None,
+    }
+}
+
+/// anything a query can be run against, implemented for the entries of both backends
+pub trait Queryable {
+    fn size(&self) -> u64;
+    fn name(&self) -> Cow<'_, str>;
+    fn path(&self) -> Cow<'_, str>;
+    /// unix timestamp (seconds)
+    fn modified(&self) -> Option<i64>;
+    fn is_directory(&self) -> bool;
+}
+
+impl Queryable for ScanEntry {
+    fn size(&self) -> u64 {
+        self.size
+    }
+    fn name(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.name)
+    }
+    fn path(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.path)
+    }
+    fn modified(&self) -> Option<i64> {
+        self.modified
+    }
+    fn is_directory(&self) -> bool {
+        self.is_directory
+    }
+}
+
+impl Queryable for FileInfo {
+    fn size(&self) -> u64 {
+        self.size
+    }
+    fn name(&self) -> Cow<'_, str> {
+        Path::new(&self.full_path)
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default()
+    }
+    fn path(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.full_path)
+    }
+    fn modified(&self) -> Option<i64> {
+        self.last_modified
+            .as_deref()
+            .and_then(|m| NaiveDateTime::parse_from_str(m, DATE_FORMAT).ok())
+            .map(|dt| dt.and_utc().timestamp())
+    }
+    fn is_directory(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Queryable> Queryable for &T {
+    fn size(&self) -> u64 {
+        (*self).size()
+    }
+    fn name(&self) -> Cow<'_, str> {
+        (*self).name()
+    }
+    fn path(&self) -> Cow<'_, str> {
+        (*self).path()
+    }
+    fn modified(&self) -> Option<i64> {
+        (*self).modified()
+    }
+    fn is_directory(&self) -> bool {
+        (*self).is_directory()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Size,
+    Name,
+    Path,
+    Ext,
+    Modified,
+    Type,
+}
+
+impl Field {
+    fn parse(word: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(match word.to_lowercase().as_str() {
+            "size" => Field::Size,
+            "name" => Field::Name,
+            "path" => Field::Path,
+            "ext" | "extension" => Field::Ext,
+            "modified" | "mtime" => Field::Modified,
+            "type" => Field::Type,
+            _ => return Err(format!("unknown field: {}", word).into()),
+        })
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Field::Size | Field::Modified)
+    }
+
+    fn value_of<T: Queryable>(self, item: &T) -> Option<Value> {
+        Some(match self {
+            Field::Size => Value::Int(item.size() as i64),
+            Field::Modified => Value::Int(item.modified()?),
+            Field::Name => Value::Text(item.name().to_lowercase()),
+            Field::Path => Value::Text(item.path().to_lowercase()),
+            Field::Ext => Value::Text(
+                Path::new(item.name().as_ref())
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default(),
+            ),
+            Field::Type => Value::Text(if item.is_directory() { "dir" } else { "file" }.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Contains,
+    NotContains,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum Value {
+    Int(i64),
+    Text(String),
+}
+
+#[derive(Debug)]
+enum Expr {
+    All,
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+    In(Field, Vec<Value>),
+}
+
+impl Expr {
+    fn eval<T: Queryable>(&self, item: &T) -> bool {
+        match self {
+            Expr::All => true,
+            Expr::And(a, b) => a.eval(item) && b.eval(item),
+            Expr::Or(a, b) => a.eval(item) || b.eval(item),
+            Expr::Not(e) => !e.eval(item),
+            Expr::In(field, values) => field.value_of(item).is_some_and(|v| values.contains(&v)),
+            Expr::Compare(field, op, value) => {
+                let Some(actual) = field.value_of(item) else {
+                    return false;
+                };
+                match (op, &actual, value) {
+                    (Op::Contains, Value::Text(a), Value::Text(b)) => a.contains(b.as_str()),
+                    (Op::NotContains, Value::Text(a), Value::Text(b)) => !a.contains(b.as_str()),
+                    (Op::Eq, a, b) => a == b,
+                    (Op::Ne, a, b) => a != b,
+                    (Op::Lt, a, b) => a < b,
+                    (Op::Le, a, b) => a <= b,
+                    (Op::Gt, a, b) => a > b,
+                    (Op::Ge, a, b) => a >= b,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Op(Op),
+    In,
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// splits a query into tokens. strings go in single quotes, double quotes are ignored
+/// so the whole expression can be wrapped in them like a shell argument
+fn tokenize(text: &str) -> Result<Vec<Token>, Box<dyn Error>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() || c == '"' => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                });
+            }
+            '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated string in query".into()),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '<' | '>' | '=' | '!' | '~' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let (op, double) = match (c, next) {
+                    ('<', Some('=')) => (Op::Le, true),
+                    ('>', Some('=')) => (Op::Ge, true),
+                    ('=', Some('=')) => (Op::Eq, true),
+                    ('!', Some('=')) => (Op::Ne, true),
+                    ('!', Some('~')) => (Op::NotContains, true),
+                    ('<', _) => (Op::Lt, false),
+                    ('>', _) => (Op::Gt, false),
+                    ('=', _) => (Op::Eq, false),
+                    ('~', _) => (Op::Contains, false),
+                    _ => return Err("'!' needs to be followed by '=' or '~'".into()),
+                };
+                if double {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()<>=!~,'\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.to_lowercase().as_str() {
+                    "in" => Token::In,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// turns a relative age like `-6mo`, `-2w`, `-30d` or `-12h` into a unix timestamp
+fn parse_relative(text: &str) -> Option<i64> {
+    Some(Utc::now().timestamp() - parse_duration(text.strip_prefix('-')?)?)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), Box<dyn Error>> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(format!("expected {} in query", what).into()),
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let mut expr = self.and_expr()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let mut expr = self.unary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Box<dyn Error>> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.or_expr()?;
+            self.expect(Token::RParen, "')'")?;
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let field = match self.next() {
+            Some(Token::Word(word)) => Field::parse(&word)?,
+            _ => return Err("expected a field name in query".into()),
+        };
+
+        match self.next() {
+            Some(Token::In) => {
+                self.expect(Token::LParen, "'(' after 'in'")?;
+                let mut values = vec![self.value(field)?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    values.push(self.value(field)?);
+                }
+                self.expect(Token::RParen, "')' to close the 'in' list")?;
+                Ok(Expr::In(field, values))
+            }
+            Some(Token::Op(op)) => {
+                let text_op = matches!(op, Op::Contains | Op::NotContains);
+                let ordering_op = matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge);
+                if (field.is_numeric() && text_op) || (!field.is_numeric() && ordering_op) {
+                    return Err(format!("{:?} can't be compared with {:?}", field, op).into());
+                }
+                Ok(Expr::Compare(field, op, self.value(field)?))
+            }
+            _ => Err(format!("expected an operator after {:?}", field).into()),
+        }
+    }
+
+    fn value(&mut self, field: Field) -> Result<Value, Box<dyn Error>> {
+        let text = match self.next() {
+            Some(Token::Word(text)) | Some(Token::Text(text)) => text,
+            _ => return Err(format!("expected a value for {:?}", field).into()),
+        };
+        match field {
+            Field::Size => parse_size(&text)
+                .map(|size| Value::Int(size as i64))
+                .ok_or_else(|| format!("invalid size: {}", text).into()),
+            Field::Modified => parse_relative(&text)
+                .or_else(|| parse_date(&text))
+                .map(Value::Int)
+                .ok_or_else(|| format!("invalid date: {} (use YYYY-MM-DD or -30d, -6mo, ...)", text).into()),
+            Field::Ext => Ok(Value::Text(text.trim_start_matches('.').to_lowercase())),
+            _ => Ok(Value::Text(text.to_lowercase())),
+        }
+    }
+}
+
+/// a parsed query: a filter, an optional sort order and an optional limit
+#[derive(Debug)]
+pub struct Query {
+    expr: Expr,
+    // field and whether it's descending
+    order: Option<(Field, bool)>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+
+        let expr = if parser.peek().is_none() || parser.peek_keyword("order") || parser.peek_keyword("limit") {
+            Expr::All
+        } else {
+            parser.or_expr()?
+        };
+
+        let mut order = None;
+        if parser.peek_keyword("order") {
+            parser.pos += 1;
+            if !parser.peek_keyword("by") {
+                return Err("expected 'by' after 'order'".into());
+            }
+            parser.pos += 1;
+            let field = match parser.next() {
+                Some(Token::Word(word)) => Field::parse(&word)?,
+                _ => return Err("expected a field after 'order by'".into()),
+            };
+            let mut descending = false;
+            if parser.peek_keyword("desc") || parser.peek_keyword("asc") {
+                descending = parser.peek_keyword("desc");
+                parser.pos += 1;
+            }
+            order = Some((field, descending));
+        }
+
+        let mut limit = None;
+        if parser.peek_keyword("limit") {
+            parser.pos += 1;
+            limit = match parser.next() {
+                Some(Token::Word(n)) => Some(n.parse().map_err(|_| format!("invalid limit: {}", n))?),
+                _ => return Err("expected a number after 'limit'".into()),
+            };
+        }
+
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {:?} in query", token).into());
+        }
+        Ok(Query { expr, order, limit })
+    }
+
+    pub fn matches<T: Queryable>(&self, item: &T) -> bool {
+        self.expr.eval(item)
+    }
+
+    /// filters, sorts and limits `items` in one go
+    pub fn apply<T: Queryable>(&self, items: Vec<T>) -> Vec<T> {
+        let mut matched: Vec<T> = items.into_iter().filter(|item| self.matches(item)).collect();
+
+        if let Some((field, descending)) = self.order {
+            // entries without a value (e.g. no modified time) always go last
+            matched.sort_by(|a, b| {
+                match (field.value_of(a), field.value_of(b)) {
+                    (Some(x), Some(y)) if descending => y.partial_cmp(&x),
+                    (Some(x), Some(y)) => x.partial_cmp(&y),
+                    (Some(_), None) => Some(Ordering::Less),
+                    (None, Some(_)) => Some(Ordering::Greater),
+                    (None, None) => Some(Ordering::Equal),
+                }
+                .unwrap_or(Ordering::Equal)
+            });
+        }
+        if let Some(limit) = self.limit {
+            matched.truncate(limit);
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64, days_old: i64) -> ScanEntry {
+        ScanEntry {
+            path: path.to_string(),
+            name: Path::new(path).file_name().unwrap().to_string_lossy().to_string(),
+            size,
+            is_directory: false,
+            modified: Some(Utc::now().timestamp() - days_old * 86_400),
+            attributes: 0,
+        }
+    }
+
+    #[test]
+    fn test_example_query() {
+        let query = Query::parse(
+            "\"size > 1GiB and ext in (mkv, mp4) and modified < -6mo and path ~ 'Downloads'\" order by size desc limit 50",
+        )
+        .unwrap();
+        let gib = 1024 * 1024 * 1024;
+        let entries = vec![
+            entry("C:/Users/me/Downloads/old.mkv", 2 * gib, 400),
+            entry("C:/Users/me/Downloads/bigger.MP4", 5 * gib, 200),
+            entry("C:/Users/me/Downloads/new.mkv", 2 * gib, 10),
+            entry("C:/Users/me/Videos/old.mkv", 2 * gib, 400),
+            entry("C:/Users/me/Downloads/small.mp4", 1024, 400),
+        ];
+
+        let names: Vec<String> = query.apply(entries).into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["bigger.MP4", "old.mkv"]);
+        assert_eq!(query.limit, Some(50));
+    }
+
+    #[test]
+    fn test_logic_and_errors() {
+        let file = entry("D:/a/notes.txt", 10, 1);
+        assert!(Query::parse("").unwrap().matches(&file));
+        assert!(Query::parse("not (ext = md or name ~ 'draft')").unwrap().matches(&file));
+        assert!(Query::parse("type = dir or size <= 10").unwrap().matches(&file));
+        assert!(!Query::parse("type = dir or size != 10").unwrap().matches(&file));
+
+        assert!(Query::parse("name > 'a'").is_err());
+        assert!(Query::parse("size ~ 10").is_err());
+        assert!(Query::parse("colour = red").is_err());
+        assert!(Query::parse("size > 1gb limit").is_err());
+        assert!(Query::parse(OLD_LARGE_FILES_QUERY).is_ok());
+        assert!(Query::parse(RECENT_LARGE_FILES_QUERY).is_ok());
+        for name in RESULT_SETS {
+            assert!(result_set_query(name).is_some_and(|q| Query::parse(q).is_ok()));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+// what scans couldn't read. folders that can't be listed and files whose metadata can't be
+// read used to be dropped without a word, leaving totals low with no hint why.
+//
+// errors are grouped per folder and kind, so a folder of a thousand locked files is one line
+use super::types::{ScanError, ScanErrorKind};
+use crate::utility::constants::GB_TO_BYTES;
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+};
+
+impl ScanErrorKind {
+    pub fn of_io(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => ScanErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound => ScanErrorKind::NotFound,
+            _ => ScanErrorKind::Other,
+        }
+    }
+
+    pub fn of_walk(error: &walkdir::Error) -> Self {
+        if error.loop_ancestor().is_some() {
+            return ScanErrorKind::LinkLoop;
+        }
+        error.io_error().map_or(ScanErrorKind::Other, Self::of_io)
+    }
+}
+
+/// Everything one scan couldn't read, and how much it did.
+#[derive(Debug, Clone, Default)]
+pub struct ScanErrorLog {
+    errors: HashMap<(String, ScanErrorKind), ScanError>,
+    /// bytes the scan counted
+    pub scanned_bytes: u64,
+    /// used space of the drive, what the scan should have come close to
+    pub expected_bytes: Option<u64>,
+}
+
+impl ScanErrorLog {
+    pub fn new(expected_bytes: Option<u64>) -> Self {
+        ScanErrorLog { expected_bytes, ..Default::default() }
+    }
+
+    pub fn record(&mut self, path: &str, kind: ScanErrorKind, subtree: bool) {
+        let error = self
+            .errors
+            .entry((path.to_string(), kind))
+            .or_insert_with(|| ScanError { path: path.to_string(), kind, count: 0, subtree });
+        error.count += 1;
+        error.subtree |= subtree;
+    }
+
+    /// An error from walking. Failing to list a folder loses everything below it,
+    /// anything else counts against the folder it's in.
+    pub fn record_walk(&mut self, error: &walkdir::Error) {
+        let path = error.path().unwrap_or(Path::new("?"));
+        let kind = ScanErrorKind::of_walk(error);
+        if kind != ScanErrorKind::LinkLoop && path.is_dir() {
+            self.record(&path.to_string_lossy(), kind, true);
+        } else {
+            let folder = path.parent().unwrap_or(path);
+            self.record(&folder.to_string_lossy(), kind, false);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// number of entries that couldn't be read
+    pub fn total(&self) -> usize {
+        self.errors.values().map(|e| e.count).sum()
+    }
+
+    /// every error, the most frequent first
+    pub fn errors(&self) -> Vec<&ScanError> {
+        let mut errors: Vec<&ScanError> = self.errors.values().collect();
+        errors.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.path.cmp(&b.path)));
+        errors
+    }
+
+    /// Folders that couldn't be listed at all, leaving out any below another one.
+    pub fn inaccessible_subtrees(&self) -> Vec<&str> {
+        let mut folders: Vec<&str> = self
+            .errors
+            .values()
+            .filter(|e| e.subtree)
+            .map(|e| e.path.as_str())
+            .collect();
+        folders.sort_unstable();
+        folders.dedup();
+        let mut top: Vec<&str> = Vec::new();
+        for folder in folders {
+            if !top.iter().any(|parent| Path::new(folder).starts_with(parent)) {
+                top.push(folder);
+            }
+        }
+        top
+    }
+
+    /// Used space the scan didn't find, `None` without a drive to compare against.
+    /// Also covers file system metadata and anything the include/exclude patterns
+    /// left out, so it's an upper bound for what the errors hid.
+    pub fn unaccounted_bytes(&self) -> Option<u64> {
+        self.expected_bytes.map(|expected| expected.saturating_sub(self.scanned_bytes))
+    }
+
+    /// counts per kind, the most frequent first
+    fn kinds(&self) -> Vec<(ScanErrorKind, usize)> {
+        let mut kinds: HashMap<ScanErrorKind, usize> = HashMap::new();
+        for error in self.errors.values() {
+            *kinds.entry(error.kind).or_default() += error.count;
+        }
+        let mut kinds: Vec<_> = kinds.into_iter().collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        kinds
+    }
+
+    /// the line printed after every scan
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "No scan errors.".to_string();
+        }
+        let kinds: Vec<String> = self
+            .kinds()
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        format!(
+            "{} entries couldn't be read ({}), {} folders skipped entirely. `scan-errors` lists them.",
+            self.total(),
+            kinds.join(", "),
+            self.inaccessible_subtrees().len()
+        )
+    }
+}
+
+/// Prints the inaccessible folders, then up to `count` error lines and the unaccounted space.
+pub fn print_scan_errors(log: &ScanErrorLog, count: usize) {
+    if log.is_empty() {
+        println!("No scan errors.");
+    } else {
+        let subtrees = log.inaccessible_subtrees();
+        if !subtrees.is_empty() {
+            println!("Folders that couldn't be read, nothing below them was scanned:");
+            for folder in subtrees.iter().take(count) {
+                println!("  {}", folder);
+            }
+            if subtrees.len() > count {
+                println!("  .. and {} more", subtrees.len() - count);
+            }
+            println!();
+        }
+
+        println!("{:>8}  {:<18} Path", "Count", "Error");
+        for error in log.errors().into_iter().take(count) {
+            println!("{:>8}  {:<18} {}", error.count, error.kind.to_string(), error.path);
+        }
+        println!("\n{}", log.summary());
+    }
+
+    if let Some(unaccounted) = log.unaccounted_bytes() {
+        println!(
+            "Scanned {:.2} GB, {:.2} GB of used space unaccounted for (file system metadata, excluded paths and anything unreadable)",
+            log.scanned_bytes as f64 / GB_TO_BYTES,
+            unaccounted as f64 / GB_TO_BYTES
+        );
+    }
+}
+
+#[cfg(test)]
+mod scan_errors_tests {
+    use super::*;
+
+    #[test]
+    fn test_grouping_and_subtrees() {
+        let mut log = ScanErrorLog::new(Some(1000));
+        log.scanned_bytes = 600;
+        log.record("/data/private", ScanErrorKind::PermissionDenied, true);
+        log.record("/data/private/deeper", ScanErrorKind::PermissionDenied, true);
+        log.record("/data/locked", ScanErrorKind::PermissionDenied, false);
+        log.record("/data/locked", ScanErrorKind::PermissionDenied, false);
+        log.record("/data/locked", ScanErrorKind::NotFound, false);
+
+        assert_eq!(log.total(), 5);
+        assert_eq!(log.errors()[0].path, "/data/locked");
+        assert_eq!(log.errors()[0].count, 2);
+        assert_eq!(log.inaccessible_subtrees(), ["/data/private"]);
+        assert_eq!(log.unaccounted_bytes(), Some(400));
+        assert!(log.summary().starts_with("5 entries couldn't be read (4 permission denied, 1 not found)"));
+        assert_eq!(ScanErrorLog::default().summary(), "No scan errors.");
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(ScanErrorKind::of_io(&denied), ScanErrorKind::PermissionDenied);
+        assert_eq!(ScanErrorKind::of_io(&io::Error::other("disk")), ScanErrorKind::Other);
+    }
+}
@@ -0,0 +1,293 @@
+// everything-style name search over the cached scan entries of either backend
+use super::types::ScanEntry;
+use crate::utility::utils::{parse_date, parse_size};
+use regex::Regex;
+use std::{error::Error, path::Path};
+
+pub const FIND_USAGE: &str = "Usage: find <pattern> [drive] [-regex] [-size min..max] \
+    [-ext mp4,mkv] [-after YYYY-MM-DD] [-before YYYY-MM-DD] [-dirs]";
+
+/// a parsed `find` command.
+///
+/// the pattern is a substring by default, a glob if it has `*` or `?` in it,
+/// or a regex with `-regex`. patterns containing a slash match the full path
+/// instead of just the name. matching is always case-insensitive
+pub struct SearchQuery {
+    pub pattern: String,
+    matcher: Regex,
+    regex_mode: bool,
+    match_path: bool,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    extensions: Vec<String>,
+    after: Option<i64>,
+    before: Option<i64>,
+    dirs_only: bool,
+}
+
+impl SearchQuery {
+    /// parses the arguments after `find` as typed, so a regex keeps escapes like `\D` and `\S`.
+    /// returns the query and the drive (if one was given)
+    pub fn from_args(args: &[String]) -> Result<(Self, Option<String>), Box<dyn Error>> {
+        let mut args = args.iter();
+        let pattern = args
+            .next()
+            .filter(|p| !p.starts_with('-'))
+            .ok_or_else(|| format!("search pattern required. {}", FIND_USAGE))?
+            .clone();
+
+        let mut drive = None;
+        let mut query = SearchQuery {
+            pattern,
+            regex_mode: false,
+            match_path: false,
+            matcher: Regex::new("")?,
+            min_size: None,
+            max_size: None,
+            extensions: Vec::new(),
+            after: None,
+            before: None,
+            dirs_only: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("{} needs a value. {}", arg, FIND_USAGE))
+            };
+            let flag = arg.to_lowercase();
+            match flag.as_str() {
+                "-regex" => query.regex_mode = true,
+                "-dirs" => query.dirs_only = true,
+                "-size" => {
+                    let range = value()?;
+                    let (min, max) = range.split_once("..").unwrap_or((range, ""));
+                    let bound = |text: &str| match text {
+                        "" => Ok(None),
+                        _ => parse_size(text)
+                            .map(Some)
+                            .ok_or_else(|| format!("invalid size: {}", text)),
+                    };
+                    query.min_size = bound(min)?;
+                    query.max_size = bound(max)?;
+                }
+                "-ext" => {
+                    query.extensions = value()?
+                        .split(',')
+                        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                        .filter(|e| !e.is_empty())
+                        .collect();
+                }
+                "-after" | "-before" => {
+                    let text = value()?;
+                    let date = parse_date(text)
+                        .ok_or_else(|| format!("invalid date: {} (expected YYYY-MM-DD)", text))?;
+                    if flag == "-after" {
+                        query.after = Some(date);
+                    } else {
+                        query.before = Some(date);
+                    }
+                }
+                other if drive.is_none() && !other.starts_with('-') => {
+                    drive = Some(other.to_string());
+                }
+                other => return Err(format!("unknown find option: {}. {}", other, FIND_USAGE).into()),
+            }
+        }
+
+        // in a regex a lone backslash is an escape, only an escaped one means a path separator
+        query.match_path = if query.regex_mode {
+            query.pattern.contains('/') || query.pattern.contains("\\\\")
+        } else {
+            query.pattern.contains(['/', '\\'])
+        };
+
+        // paths get compared with forward slashes, so both kinds of separator work
+        let pattern = match (query.match_path, query.regex_mode) {
+            (true, true) => query.pattern.replace("\\\\", "/"),
+            (true, false) => query.pattern.replace('\\', "/"),
+            (false, _) => query.pattern.clone(),
+        };
+        let expression = if query.regex_mode {
+            pattern
+        } else if pattern.contains(['*', '?']) {
+            glob_to_regex(&pattern)
+        } else {
+            regex::escape(&pattern)
+        };
+        query.matcher = Regex::new(&format!("(?i){}", expression))?;
+
+        Ok((query, drive))
+    }
+
+    pub fn matches(&self, entry: &ScanEntry) -> bool {
+        if self.dirs_only && !entry.is_directory {
+            return false;
+        }
+        if self.min_size.is_some_and(|min| entry.size < min)
+            || self.max_size.is_some_and(|max| entry.size > max)
+        {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            let extension = Path::new(&entry.name)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !self.extensions.contains(&extension) {
+                return false;
+            }
+        }
+        if self.after.is_some() || self.before.is_some() {
+            let Some(modified) = entry.modified else {
+                return false;
+            };
+            if self.after.is_some_and(|after| modified < after)
+                || self.before.is_some_and(|before| modified >= before)
+            {
+                return false;
+            }
+        }
+
+        if self.match_path {
+            self.matcher.is_match(&entry.path.replace('\\', "/"))
+        } else {
+            self.matcher.is_match(&entry.name)
+        }
+    }
+
+    /// pieces of text every match has to contain (lowercased, with forward slashes),
+    /// used by the trigram index to narrow the search down before matching
+    pub fn literals(&self) -> Vec<String> {
+        let pattern = self.pattern.to_lowercase();
+        if self.regex_mode {
+            return regex_literals(&pattern);
+        }
+        pattern
+            .replace('\\', "/")
+            .split(['*', '?'])
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Pulls the plain runs of text out of a regex. Gives up (returns nothing) on
+/// alternations and groups, since their literals aren't necessarily required, and on
+/// escapes other than punctuation (`\d`, `\x41`, `\p{L}`..) rather than guess what they match.
+fn regex_literals(pattern: &str) -> Vec<String> {
+    if pattern.contains(['|', '(']) {
+        return Vec::new();
+    }
+
+    let mut literals = Vec::new();
+    let mut current = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let literal = match c {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped.is_ascii_punctuation() => Some(escaped),
+                _ => return Vec::new(),
+            },
+            // skip over classes and repetition counts
+            '[' | '{' => {
+                let close = if c == '[' { ']' } else { '}' };
+                for c in chars.by_ref() {
+                    if c == close {
+                        break;
+                    }
+                }
+                None
+            }
+            '.' | '^' | '$' | '+' | '*' | '?' | '}' | ']' | ')' => None,
+            c => Some(c),
+        };
+        match literal {
+            // a character followed by ?, * or {..} might not be there at all
+            Some(c) if !matches!(chars.peek(), Some('?' | '*' | '{')) => {
+                current.push(if c == '\\' { '/' } else { c })
+            }
+            _ => {
+                if !current.is_empty() {
+                    literals.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        literals.push(current);
+    }
+    literals
+}
+
+/// turns a glob into an anchored regex, `*` is any run of characters and `?` a single one
+fn glob_to_regex(glob: &str) -> String {
+    let mut expression = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            c => expression.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expression.push('$');
+    expression
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> ScanEntry {
+        ScanEntry {
+            path: path.to_string(),
+            name: Path::new(path).file_name().unwrap().to_string_lossy().to_string(),
+            size,
+            is_directory: false,
+            modified: Some(1_700_000_000),
+            attributes: 0,
+        }
+    }
+
+    fn query(args: &[&str]) -> SearchQuery {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        SearchQuery::from_args(&args).unwrap().0
+    }
+
+    #[test]
+    fn test_patterns() {
+        let report = entry("C:/Docs/Report_2023.pdf", 2048);
+        assert!(query(&["report"]).matches(&report));
+        assert!(query(&["report*.pdf"]).matches(&report));
+        assert!(!query(&["report*.doc"]).matches(&report));
+        assert!(query(&["^report_\\d{4}", "-regex"]).matches(&report));
+        assert!(query(&["docs/report"]).matches(&report));
+        assert!(query(&["docs\\report"]).matches(&entry("C:\\Docs\\Report_2023.pdf", 2048)));
+        // an escaped backslash in a regex is a path separator too
+        assert!(query(&["docs\\\\report_\\d+", "-regex"]).matches(&entry("C:\\Docs\\Report_2023.pdf", 2048)));
+        // upper case escapes keep their meaning, \D is not \d
+        assert!(query(&["^\\D+_", "-Regex"]).matches(&report));
+        assert!(!query(&["^\\d+_", "-regex"]).matches(&report));
+    }
+
+    #[test]
+    fn test_filters() {
+        let movie = entry("D:/Videos/movie.mkv", 3 * 1024 * 1024 * 1024);
+        assert!(query(&["movie", "d", "-size", "1gb..", "-ext", "mkv,mp4"]).matches(&movie));
+        assert!(!query(&["movie", "-size", "..1gb"]).matches(&movie));
+        assert!(!query(&["movie", "-ext", ".mp4"]).matches(&movie));
+        assert!(query(&["movie", "-after", "2023-01-01", "-before", "2024-01-01"]).matches(&movie));
+        assert!(!query(&["movie", "-dirs"]).matches(&movie));
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(query(&["Report"]).literals(), vec!["report"]);
+        assert_eq!(query(&["docs\\rep*.pdf"]).literals(), vec!["docs/rep", ".pdf"]);
+        assert_eq!(query(&["^report_[0-9]{4}\\.pdf$", "-regex"]).literals(), vec!["report_", ".pdf"]);
+        assert!(query(&["report_\\d{4}", "-regex"]).literals().is_empty());
+        assert!(query(&["\\x41bc", "-regex"]).literals().is_empty());
+        assert_eq!(query(&["colou?r", "-regex"]).literals(), vec!["colo", "r"]);
+        assert!(query(&["(jpg|png)$", "-regex"]).literals().is_empty());
+    }
+}
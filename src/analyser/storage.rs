@@ -1,15 +1,34 @@
+use super::attributes::{AttributeFilter, count_attributes, print_attribute_counts};
+use super::compression::{estimate_compression, print_compression_estimate};
+use super::empty_tree::{find_empty_trees, tree_summary};
+use super::health_check::{CHECK_STATE_FILE, CheckConfig, CheckReport, CheckState, DriveMeasure, Measurements, evaluate};
+use super::ignore::ScanFilter;
+use super::links::{print_links, walk_links};
+use super::listing::{Fields, ListingOptions, SortKey};
+use super::name_index::TrigramIndex;
+use super::perm_audit::{known_uids, permission_issues};
+use super::query::Query;
+use super::scan_errors::{ScanErrorLog, print_scan_errors};
+use super::search::SearchQuery;
 use super::types::*;
+use super::zero_regions::{print_zero_regions, scan_zero_regions};
 use crate::utility::constants::*;
+use crate::utility::output::{OutputFormat, fail, note, print_json};
 use crate::utility::utils::*;
-use chrono::{Duration, NaiveDateTime, Utc};
+use chrono::{NaiveDateTime, Utc};
 use rayon::prelude::*;
+use serde::Serialize;
 #[cfg(target_os = "windows")]
 use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::{
-    collections::HashMap,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     ffi::{OsStr, OsString},
     io::{self, Error},
+    mem,
     path::Path,
+    sync::Mutex,
+    time::Instant,
 };
 use walkdir::WalkDir;
 #[cfg(target_os = "windows")]
@@ -18,10 +37,70 @@ use winapi::um::{
     winbase::DRIVE_FIXED,
 };
 
+/// The listings that also run over every fixed drive at once with `all`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DriveReport {
+    DriveSpace,
+    FileTypes,
+    LargestFiles,
+    LargestFolders,
+    RecentLargeFiles,
+    OldLargeFiles,
+    FullAnalysis,
+}
+
 pub struct StorageAnalyser {
     pub drives: Vec<String>,
     file_cache: HashMap<String, Vec<FileInfo>>,
     folder_cache: HashMap<String, Vec<FolderSize>>,
+    // every folder path, only walked when empty folders are asked for
+    dir_cache: HashMap<String, Vec<String>>,
+    // whatever the ignore rules pruned from that walk or it couldn't read, so folders
+    // holding only those don't show up as empty
+    ignored_cache: HashMap<String, Vec<String>>,
+    // the file cache flattened for find and query, built once per scan since the
+    // name index points into it by position
+    entry_cache: HashMap<String, Vec<ScanEntry>>,
+    name_index: HashMap<String, TrigramIndex>,
+    // which files the listings show, set per command from its flags
+    attribute_filter: AttributeFilter,
+    // include/exclude patterns from the command, the caches were scanned with these
+    scan_filter: ScanFilter,
+    // drives whose scan got cancelled part way, their caches only hold part of the drive
+    incomplete: HashSet<String>,
+    // what each drive's last scan couldn't read
+    scan_errors: HashMap<String, ScanErrorLog>,
+    // the caches of the patterns used before the current ones, so going back is free
+    shelved: Option<ShelvedScans>,
+    // text or json, set per command from --format
+    output_format: OutputFormat,
+}
+
+/// Every cache that depends on the include/exclude patterns, kept aside while other
+/// patterns are in use.
+struct ShelvedScans {
+    filter: ScanFilter,
+    file_cache: HashMap<String, Vec<FileInfo>>,
+    folder_cache: HashMap<String, Vec<FolderSize>>,
+    dir_cache: HashMap<String, Vec<String>>,
+    ignored_cache: HashMap<String, Vec<String>>,
+    entry_cache: HashMap<String, Vec<ScanEntry>>,
+    name_index: HashMap<String, TrigramIndex>,
+    incomplete: HashSet<String>,
+    scan_errors: HashMap<String, ScanErrorLog>,
+}
+
+impl ShelvedScans {
+    fn forget(&mut self, drive: &str) {
+        self.file_cache.remove(drive);
+        self.folder_cache.remove(drive);
+        self.dir_cache.remove(drive);
+        self.ignored_cache.remove(drive);
+        self.entry_cache.remove(drive);
+        self.name_index.remove(drive);
+        self.incomplete.remove(drive);
+        self.scan_errors.remove(drive);
+    }
 }
 
 impl StorageAnalyser {
@@ -31,15 +110,86 @@ impl StorageAnalyser {
             drives,
             file_cache: HashMap::new(),
             folder_cache: HashMap::new(),
+            dir_cache: HashMap::new(),
+            ignored_cache: HashMap::new(),
+            entry_cache: HashMap::new(),
+            name_index: HashMap::new(),
+            attribute_filter: AttributeFilter::default(),
+            scan_filter: ScanFilter::default(),
+            incomplete: HashSet::new(),
+            scan_errors: HashMap::new(),
+            shelved: None,
+            output_format: OutputFormat::default(),
         }
     }
 
+    /// Different patterns mean different scans. Changing them puts the current caches
+    /// aside and brings back the ones scanned with the new patterns, when they were kept.
+    pub fn set_scan_filter(&mut self, filter: ScanFilter) {
+        if filter == self.scan_filter {
+            return;
+        }
+        let previous = ShelvedScans {
+            filter: mem::replace(&mut self.scan_filter, filter),
+            file_cache: mem::take(&mut self.file_cache),
+            folder_cache: mem::take(&mut self.folder_cache),
+            dir_cache: mem::take(&mut self.dir_cache),
+            ignored_cache: mem::take(&mut self.ignored_cache),
+            entry_cache: mem::take(&mut self.entry_cache),
+            name_index: mem::take(&mut self.name_index),
+            incomplete: mem::take(&mut self.incomplete),
+            scan_errors: mem::take(&mut self.scan_errors),
+        };
+        match self.shelved.take() {
+            Some(shelved) if shelved.filter == self.scan_filter => {
+                self.file_cache = shelved.file_cache;
+                self.folder_cache = shelved.folder_cache;
+                self.dir_cache = shelved.dir_cache;
+                self.ignored_cache = shelved.ignored_cache;
+                self.entry_cache = shelved.entry_cache;
+                self.name_index = shelved.name_index;
+                self.incomplete = shelved.incomplete;
+                self.scan_errors = shelved.scan_errors;
+            }
+            _ if !previous.file_cache.is_empty() => {
+                note("Include/exclude patterns changed, drives will be scanned again with them..");
+            }
+            _ => {}
+        }
+        self.shelved = Some(previous);
+    }
+
+    pub fn set_attribute_filter(&mut self, filter: AttributeFilter) {
+        self.attribute_filter = filter;
+    }
+
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    pub fn json(&self) -> bool {
+        self.output_format == OutputFormat::Json
+    }
+
+    // the json of a listing, in place of its text
+    fn print_json(value: &(impl Serialize + ?Sized)) -> io::Result<()> {
+        print_json(value).map_err(|e| Error::other(e.to_string()))
+    }
+
+    /// files as rows for json output, with their full paths
+    fn file_rows(files: Vec<FileInfo>) -> Vec<ListingRow> {
+        files
+            .into_iter()
+            .map(|file| ListingRow { name: file.full_path, size: file.size, modified: file.last_modified })
+            .collect()
+    }
+
     fn print_file_info(file: &FileInfo) {
         println!("\n[*] Path: {}", file.full_path);
         println!(
             "    Size: {:.2} MB / {:.2} GB",
-            file.size_mb,
-            file.size_mb / 1000.0
+            file.size as f64 / MB_TO_BYTES,
+            file.size as f64 / GB_TO_BYTES
         );
         println!(
             "    Last Modified: {}",
@@ -50,6 +200,22 @@ impl StorageAnalyser {
         }
     }
 
+    fn print_entry_info(entry: &ScanEntry) {
+        println!("\n[*] Path: {}", entry.path);
+        println!(
+            "    Size: {:.2} MB / {:.2} GB",
+            entry.size as f64 / MB_TO_BYTES,
+            entry.size as f64 / GB_TO_BYTES
+        );
+        println!(
+            "    Last Modified: {}",
+            entry
+                .modified
+                .and_then(timestamp_to_string)
+                .unwrap_or_else(|| "Unknown".to_string())
+        );
+    }
+
     // Windows-specific implementation to list fixed drives
     // filters for physical drives only, skips USB/network drives
     #[cfg(target_os = "windows")]
@@ -81,9 +247,18 @@ impl StorageAnalyser {
         Vec::new()
     }
 
+    /// every fixed drive, written the way the caches key them ("C:/")
+    fn fixed_drives(&self) -> Vec<String> {
+        self.drives
+            .iter()
+            .filter_map(|drive| drive.chars().next())
+            .map(|letter| format!("{}:/", letter.to_ascii_uppercase()))
+            .collect()
+    }
+
     // a full scan fn that calls all other ones
     pub fn analyze_drive(&mut self, drive: &str) -> io::Result<()> {
-        if !self.drives.contains(&drive.to_string()) {
+        if !self.fixed_drives().contains(&drive.to_string()) {
             println!(
                 "Drive {} is not a valid fixed drive. Valid drives are: {:?}",
                 drive, self.drives
@@ -95,13 +270,14 @@ impl StorageAnalyser {
         println!("Date: {}", Utc::now().format(DATE_FORMAT));
         println!("Drive: {}", drive);
 
+        let options = ListingOptions::default();
         self.print_drive_space_overview(drive)?;
-        self.print_largest_folders(drive)?;
+        self.print_largest_folders(drive, &options)?;
         self.print_empty_folders(drive)?;
-        self.print_file_type_distribution(drive)?;
-        self.print_largest_files(drive)?;
-        self.print_recent_large_files(drive)?;
-        self.print_old_large_files(drive)?;
+        self.print_file_type_distribution(drive, &options)?;
+        self.print_largest_files(drive, &options)?;
+        self.print_recent_large_files(drive, &options)?;
+        self.print_old_large_files(drive, &options)?;
 
         Ok(())
     }
@@ -110,6 +286,7 @@ impl StorageAnalyser {
     // -- private calculation functions -- //
 
     /// uses Windows API to get drive space information
+    #[cfg(target_os = "windows")]
     fn get_drive_space(&self, drive: &str) -> io::Result<DriveAnalysis> {
         use winapi::um::winnt::ULARGE_INTEGER;
         let mut free_bytes_available: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
@@ -144,13 +321,103 @@ impl StorageAnalyser {
         })
     }
 
-    fn get_file_type_distribution(&mut self, drive: &str) -> io::Result<Vec<(String, f64, usize)>> {
-        collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)?;
+    /// the same from `statvfs`, for the file system the path is on
+    #[cfg(unix)]
+    fn get_drive_space(&self, drive: &str) -> io::Result<DriveAnalysis> {
+        use std::os::unix::ffi::OsStrExt;
+        let path = std::ffi::CString::new(OsStr::new(drive).as_bytes())?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let block = stats.f_frsize as f64;
+        let total_size = stats.f_blocks as f64 * block / GB_TO_BYTES;
+        let free_space = stats.f_bavail as f64 * block / GB_TO_BYTES;
+        Ok(DriveAnalysis {
+            total_size,
+            used_space: total_size - free_space,
+            free_space,
+            free_space_percent: (free_space / total_size) * 100.0,
+        })
+    }
+
+    /// scans a drive unless it's cached already, and says so when the cached scan was cut short
+    fn ensure_scanned(&mut self, drive: &str) -> io::Result<()> {
+        let expected_bytes = if self.file_cache.contains_key(drive) {
+            None
+        } else {
+            self.get_drive_space(drive).ok().map(|space| (space.used_space * GB_TO_BYTES) as u64)
+        };
+        let complete = collect_and_cache_files(
+            drive,
+            &mut self.file_cache,
+            &mut self.folder_cache,
+            &mut self.scan_errors,
+            &self.scan_filter,
+            expected_bytes,
+        )?;
+        if !complete {
+            self.incomplete.insert(drive.to_string());
+        }
+        if self.incomplete.contains(drive) {
+            note(format!("(incomplete: the scan of {} was cancelled, `rescan {}` to finish it)", drive, drive));
+        }
+        Ok(())
+    }
+
+    /// Scans the drives that aren't cached yet, all of them at the same time.
+    /// Each scan fills its own caches, they're merged in once everything is done.
+    fn scan_drives(&mut self, drives: &[String]) {
+        let pending: Vec<(String, Option<u64>)> = drives
+            .iter()
+            .filter(|drive| !self.file_cache.contains_key(*drive))
+            .cloned()
+            .map(|drive| {
+                let expected_bytes = self.get_drive_space(&drive).ok().map(|space| (space.used_space * GB_TO_BYTES) as u64);
+                (drive, expected_bytes)
+            })
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        note(format!("Scanning {} drives in parallel..", pending.len()));
+
+        let filter = &self.scan_filter;
+        let scans: Vec<_> = pending
+            .par_iter()
+            .map(|(drive, expected_bytes)| {
+                let (mut files, mut folders, mut errors) = (HashMap::new(), HashMap::new(), HashMap::new());
+                let complete = collect_and_cache_files(drive, &mut files, &mut folders, &mut errors, filter, *expected_bytes);
+                (drive, complete, files, folders, errors)
+            })
+            .collect();
+
+        for (drive, complete, files, folders, errors) in scans {
+            match complete {
+                Ok(complete) => {
+                    if !complete {
+                        self.incomplete.insert(drive.clone());
+                    }
+                    self.file_cache.extend(files);
+                    self.folder_cache.extend(folders);
+                    self.scan_errors.extend(errors);
+                }
+                Err(e) => fail(format!("Couldn't scan {}: {}", drive, e)),
+            }
+        }
+    }
+
+    // per extension, counting only the files under --path
+    fn get_file_type_distribution(&mut self, drive: &str, options: &ListingOptions) -> io::Result<Vec<(String, f64, usize)>> {
+        self.ensure_scanned(drive)?;
 
         let file_types: HashMap<String, FileTypeStats> =
             if let Some(files) = self.file_cache.get(drive) {
                 files
                     .par_iter()
+                    .filter(|file| self.attribute_filter.allows(file.attributes))
+                    .filter(|file| options.under_path(&file.full_path))
                     .fold(
                         || HashMap::new(),
                         |mut acc, file_info| {
@@ -159,10 +426,8 @@ impl StorageAnalyser {
                                 .map(|e| e.to_string_lossy().to_lowercase())
                                 .unwrap_or_else(|| "(No Extension)".to_string());
 
-                            let size = (file_info.size_mb * MB_TO_BYTES) as u64;
-
                             let stats: &mut FileTypeStats = acc.entry(ext).or_default();
-                            stats.total_size += size;
+                            stats.total_size += file_info.size;
                             stats.count += 1;
                             acc
                         },
@@ -182,23 +447,21 @@ impl StorageAnalyser {
                 HashMap::new()
             };
 
-        let mut distribution: Vec<_> = file_types
+        Ok(file_types
             .into_iter()
             .map(|(ext, stats)| (ext, stats.total_size as f64 / GB_TO_BYTES, stats.count))
-            .filter(|&(_, size, _)| size > MIN_FILE_TYPE_SIZE_GB)
-            .collect();
-
-        distribution.par_sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        Ok(distribution)
+            .collect())
     }
 
     fn get_largest_files(&mut self, drive: &str) -> io::Result<Vec<FileInfo>> {
-        collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)?;
+        self.ensure_scanned(drive)?;
 
         if let Some(files) = self.file_cache.get(drive) {
-            let mut result = files.clone();
-            result.par_sort_unstable_by(|a, b| b.size_mb.partial_cmp(&a.size_mb).unwrap());
-            Ok(result)
+            Ok(files
+                .iter()
+                .filter(|file| self.attribute_filter.allows(file.attributes))
+                .cloned()
+                .collect())
         } else {
             Ok(Vec::new())
         }
@@ -206,109 +469,277 @@ impl StorageAnalyser {
 
     fn get_largest_folders(&self, drive: &str) -> io::Result<Vec<FolderSize>> {
         if let Some(cached_folders) = self.folder_cache.get(drive) {
-            // Use the cached folder sizes, filtering out folders that are too small.
-            let mut folders: Vec<FolderSize> = cached_folders
-                .iter()
-                .cloned()
-                .filter(|folder| folder.size_gb > MIN_FOLDER_SIZE_GB)
-                .collect();
-            // Sort descending by size.
-            folders.sort_unstable_by(|a, b| b.size_gb.partial_cmp(&a.size_gb).unwrap());
-            return Ok(folders);
+            // Use the cached folder sizes, the listing filters and sorts them.
+            return Ok(cached_folders.clone());
         }
         // Fallback in the unlikely event the cache is missing.
-        let mut folders = WalkDir::new(drive)
+        let ignore = self.scan_filter.matcher(drive);
+        let errors = Mutex::new(ScanErrorLog::new(None));
+        let folders = WalkDir::new(drive)
             .min_depth(1)
             .max_depth(3)
             .into_iter()
+            .filter_entry(|e| !ignore.is_excluded(&e.path().to_string_lossy(), e.file_type().is_dir()))
             .par_bridge()
-            .filter_map(Result::ok)
+            .filter_map(|entry| entry.map_err(|e| errors.lock().unwrap().record_walk(&e)).ok())
             .filter(|e| e.file_type().is_dir())
-            .filter(|e| {
-                !e.file_name()
-                    .to_str()
-                    .map(|s| s.starts_with('.'))
-                    .unwrap_or(false)
-            })
-            .filter_map(|entry| {
-                calculate_folder_size(entry.path())
-                    .ok()
-                    .filter(|size| size.size_gb > MIN_FOLDER_SIZE_GB)
-            })
+            .filter_map(|entry| calculate_folder_size(entry.path(), &ignore, &errors).ok())
             .collect::<Vec<_>>();
-        folders.par_sort_unstable_by(|a, b| b.size_gb.partial_cmp(&a.size_gb).unwrap());
+        note(errors.into_inner().unwrap().summary());
         Ok(folders)
     }
 
-    // gets anything older than 6 months
-    fn get_old_large_files(&mut self, drive: &str) -> io::Result<Vec<FileInfo>> {
-        collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)?;
+    // gets anything older than 6 months (or --age)
+    fn get_old_large_files(&mut self, drive: &str, options: &ListingOptions) -> io::Result<Vec<FileInfo>> {
+        self.query_files(drive, &options.age_query(false))
+    }
 
-        let mut files = if let Some(files) = self.file_cache.get(drive) {
-            files.clone()
-        } else {
-            return Ok(Vec::new());
-        };
+    // gets recently modified large files (within last 30 days, or --age)
+    fn get_recent_large_files(&mut self, drive: &str, options: &ListingOptions) -> io::Result<Vec<FileInfo>> {
+        self.query_files(drive, &options.age_query(true))
+    }
 
-        let six_months_ago = Utc::now().naive_utc() - Duration::days(180);
+    // runs a query over the cached files of a drive
+    fn query_files(&mut self, drive: &str, query: &Query) -> io::Result<Vec<FileInfo>> {
+        self.query_files_with(drive, query, self.attribute_filter)
+    }
 
-        files.retain(|file| {
-            NaiveDateTime::parse_from_str(
-                &file.last_modified.as_deref().unwrap_or("Unknown"),
-                DATE_FORMAT,
-            )
-            .map(|dt| dt < six_months_ago)
-            .unwrap_or(false)
-        });
+    fn query_files_with(&mut self, drive: &str, query: &Query, filter: AttributeFilter) -> io::Result<Vec<FileInfo>> {
+        self.ensure_scanned(drive)?;
 
-        files.sort_by(|a, b| b.size_mb.partial_cmp(&a.size_mb).unwrap());
-        Ok(files)
+        match self.file_cache.get(drive) {
+            Some(files) => Ok(query.apply(
+                files
+                    .iter()
+                    .filter(|file| filter.allows(file.attributes))
+                    .cloned()
+                    .collect(),
+            )),
+            None => Ok(Vec::new()),
+        }
     }
 
-    // gets recently modified large files (within last 30 days)
-    fn get_recent_large_files(&mut self, drive: &str) -> io::Result<Vec<FileInfo>> {
-        collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)?;
+    // full paths of the files a query picks, for commands that act on a result set.
+    // `also` leaves files out on top of the command's attribute filter
+    pub fn query_file_paths(&mut self, drive: &str, query: &Query, also: AttributeFilter) -> io::Result<Vec<String>> {
+        Ok(self
+            .query_files_with(drive, query, self.attribute_filter.and(also))?
+            .into_iter()
+            .map(|file| file.full_path)
+            .collect())
+    }
 
-        let mut files = if let Some(files) = self.file_cache.get(drive) {
-            files.clone()
-        } else {
-            return Ok(Vec::new());
-        };
-        let thirty_days_ago = Utc::now().naive_utc() - Duration::days(30);
-        files.retain(|file| {
-            NaiveDateTime::parse_from_str(
-                &file.last_modified.as_deref().unwrap_or("Unknown"),
-                DATE_FORMAT,
-            )
-            .map(|dt| dt > thirty_days_ago)
-            .unwrap_or(false)
+    // walks every folder of a drive once, the file scan doesn't keep them
+    fn cache_dirs(&mut self, drive: &str) {
+        if !self.dir_cache.contains_key(drive) {
+            let ignore = self.scan_filter.matcher(drive);
+            let mut dirs = Vec::new();
+            let mut ignored = Vec::new();
+            // unreadable things are still there, so neither they nor their parents are empty
+            let mut unreadable = HashSet::new();
+            let mut walk = WalkDir::new(drive).min_depth(1).into_iter();
+            while let Some(entry) = walk.next() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        unreadable.extend(e.path().map(|p| p.to_string_lossy().to_string()));
+                        continue;
+                    }
+                };
+                let path = entry.path().to_string_lossy().to_string();
+                let is_dir = entry.file_type().is_dir();
+                if ignore.is_excluded(&path, is_dir) || !(is_dir || ignore.is_included(&path)) {
+                    if is_dir {
+                        walk.skip_current_dir();
+                    }
+                    ignored.push(path);
+                } else if is_dir {
+                    dirs.push(path);
+                } else if entry.metadata().is_err() {
+                    // the file scan skipped it, but it still fills its folder
+                    unreadable.insert(path);
+                }
+            }
+            dirs.retain(|dir| !unreadable.contains(dir));
+            ignored.extend(unreadable);
+            self.dir_cache.insert(drive.to_string(), dirs);
+            self.ignored_cache.insert(drive.to_string(), ignored);
+        }
+    }
+
+    // permission problems under a path, files straight from the cached scan
+    // and folders from the folder list (stat'ed here, there are far fewer of them)
+    pub fn get_permission_findings(&mut self, target: &str) -> io::Result<Vec<PermFinding>> {
+        // a root that isn't there or can't be listed would otherwise scan as "0 paths"
+        let unreadable = |e: Error| Error::new(e.kind(), format!("Can't read {}: {}", target, e));
+        if std::fs::metadata(target).map_err(unreadable)?.is_dir() {
+            std::fs::read_dir(target).map_err(unreadable)?;
+        }
+        self.ensure_scanned(target)?;
+        self.cache_dirs(target);
+        let uids = known_uids();
+        if uids.is_none() {
+            note("Couldn't read /etc/passwd, skipping the owner check");
+        }
+
+        let files = self.file_cache[target].iter().filter_map(|file| {
+            let permissions = file.permissions?;
+            Some((file.full_path.clone(), file.size, false, permissions))
         });
-        files.sort_by(|a, b| b.size_mb.partial_cmp(&a.size_mb).unwrap());
-        Ok(files)
+        let dirs = self.dir_cache[target].iter().filter_map(|dir| {
+            let metadata = std::fs::symlink_metadata(dir).ok()?;
+            Some((dir.clone(), 0, true, unix_permissions(&metadata)?))
+        });
+
+        let mut findings: Vec<PermFinding> = files
+            .chain(dirs)
+            .filter_map(|(path, size, is_directory, permissions)| {
+                let issues = permission_issues(&path, permissions, is_directory, uids.as_ref());
+                (!issues.is_empty()).then_some(PermFinding { path, size, is_directory, permissions, issues })
+            })
+            .collect();
+        findings.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(findings)
     }
 
+    // folders with no files anywhere below them, reported at their topmost point.
+    // with `junk_as_empty`, zero-byte files and Thumbs.db and friends don't count as files
+    pub fn get_empty_folder_trees(&mut self, drive: &str, junk_as_empty: bool) -> io::Result<Vec<EmptyTree>> {
+        self.ensure_scanned(drive)?;
+
+        self.cache_dirs(drive);
+
+        let dirs = self.dir_cache[drive].iter().map(String::as_str);
+        let files = self
+            .file_cache
+            .get(drive)
+            .into_iter()
+            .flatten()
+            .map(|file| (file.full_path.as_str(), file.size));
+        // ignored entries aren't in the scan but are still on disk, so they count as content
+        let ignored = self.ignored_cache[drive].iter().map(|path| (path.as_str(), 1));
+        Ok(find_empty_trees(dirs, files.chain(ignored), junk_as_empty))
+    }
+
+    // every truly empty folder, nested ones included
     pub fn get_empty_folders(&mut self, drive: &str) -> io::Result<Vec<String>> {
-        collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)?;
+        let trees = self.get_empty_folder_trees(drive, false)?;
+        Ok(trees
+            .into_iter()
+            .flat_map(|tree| std::iter::once(tree.root).chain(tree.nested))
+            .collect())
+    }
 
-        // filtering for folders with 0 files.
-        if let Some(cached_folders) = self.folder_cache.get(drive) {
-            let empty_folders: Vec<String> = cached_folders
-                .iter()
-                .filter(|folder| folder.file_count == 0)
-                .map(|folder| folder.folder.clone())
-                .collect();
-            Ok(empty_folders)
-        } else {
-            Ok(vec![])
+    /// drives that already have a cached scan
+    pub fn cached_drives(&self) -> Vec<String> {
+        let mut drives: Vec<String> = self.file_cache.keys().cloned().collect();
+        drives.sort();
+        drives
+    }
+
+    /// the cached scan as `ScanEntry`s, so searches work the same on both backends
+    pub fn scan_entries(&mut self, drive: &str) -> io::Result<&[ScanEntry]> {
+        self.cache_entries(drive)?;
+        Ok(&self.entry_cache[drive])
+    }
+
+    // flattens the cached scan into the entry cache, unless that's done already.
+    // folders are added up from the files at every depth, the folder cache stops at 3 levels
+    fn cache_entries(&mut self, drive: &str) -> io::Result<()> {
+        self.ensure_scanned(drive)?;
+        if self.entry_cache.contains_key(drive) {
+            return Ok(());
         }
+
+        let cached = self.file_cache.get(drive).map(Vec::as_slice).unwrap_or_default();
+        let files = cached.iter().map(|file| {
+            let path = Path::new(&file.full_path);
+            ScanEntry {
+                path: file.full_path.clone(),
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                size: file.size,
+                is_directory: false,
+                modified: file
+                    .last_modified
+                    .as_deref()
+                    .and_then(|m| NaiveDateTime::parse_from_str(m, DATE_FORMAT).ok())
+                    .map(|dt| dt.and_utc().timestamp()),
+                attributes: file.attributes,
+            }
+        });
+        let folders = folder_sizes_from_files(drive, cached, usize::MAX).into_iter().map(|folder| {
+            ScanEntry {
+                name: Path::new(&folder.folder)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                path: folder.folder,
+                size: folder.size,
+                is_directory: true,
+                modified: None,
+                attributes: 0,
+            }
+        });
+
+        let entries = files.chain(folders).collect();
+        self.entry_cache.insert(drive.to_string(), entries);
+        Ok(())
     }
 
     pub fn rescan_drive(&mut self, drive: &str) -> io::Result<()> {
         // Clear any previously cached data for this drive.
         self.file_cache.remove(drive);
         self.folder_cache.remove(drive);
-        // Force a re-scan by calling collect_and_cache_files.
-        collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)
+        self.dir_cache.remove(drive);
+        self.ignored_cache.remove(drive);
+        self.entry_cache.remove(drive);
+        self.incomplete.remove(drive);
+        self.scan_errors.remove(drive);
+        // what other patterns cached of it is just as old
+        if let Some(shelved) = &mut self.shelved {
+            shelved.forget(drive);
+        }
+        // Force a re-scan, collect_and_cache_files runs again now the caches are gone.
+        self.ensure_scanned(drive)?;
+
+        if self.name_index.contains_key(drive) {
+            self.cache_entries(drive)?;
+            let index = self.name_index.get_mut(drive).unwrap();
+            let (added, removed) = index.update(&self.entry_cache[drive]);
+            note(format!(
+                "Index updated: {} added, {} removed in {:?}",
+                added, removed, index.build_time
+            ));
+            index.save(drive)?;
+        }
+        Ok(())
+    }
+
+    // builds or drops the trigram name index `find` uses to narrow its search
+    pub fn set_name_index(&mut self, drive: &str, enable: bool) -> io::Result<()> {
+        if !enable {
+            TrigramIndex::forget(drive)?;
+            match self.name_index.remove(drive) {
+                Some(_) => println!("Dropped the name index of drive {}", drive),
+                None => println!("Drive {} has no name index", drive),
+            }
+            return Ok(());
+        }
+
+        let index = TrigramIndex::build(self.scan_entries(drive)?);
+        index.save(drive)?;
+        println!(
+            "Indexed {} entries ({} trigrams) in {:?}, using about {:.2} MB",
+            index.entry_count(),
+            index.trigram_count(),
+            index.build_time,
+            index.memory_usage() as f64 / MB_TO_BYTES
+        );
+        self.name_index.insert(drive.to_string(), index);
+        Ok(())
     }
 
     
@@ -316,8 +747,9 @@ impl StorageAnalyser {
 
     pub fn print_drive_space_overview(&self, drive: &str) -> io::Result<()> {
         match self.get_drive_space(drive) {
+            Ok(analysis) if self.json() => Self::print_json(&analysis),
             Ok(analysis) => {
-                println!("\n--- Drive Space Overview ---");
+                note("\n--- Drive Space Overview ---");
                 println!("Total Size: {:.2} GB", analysis.total_size);
                 println!("Used Space: {:.2} GB", analysis.used_space);
                 println!(
@@ -326,17 +758,41 @@ impl StorageAnalyser {
                 );
                 Ok(())
             }
-            Err(e) => {
-                eprintln!("Failed to analyze drive '{}': {}", drive, e);
-                Err(e)
-            }
+            Err(e) => Err(Error::new(e.kind(), format!("Failed to analyze drive '{}': {}", drive, e))),
         }
     }
 
-    pub fn print_file_type_distribution(&mut self, drive: &str) -> io::Result<()> {
-        println!("\n--- File Type Distribution (Top 10) ---");
-        let distribution = self.get_file_type_distribution(drive)?;
-        for (ext, size, count) in distribution.iter().take(10) {
+    // a listing's rows as the flags want them, largest first unless --sort says otherwise
+    fn finish_files(files: Vec<FileInfo>, options: &ListingOptions) -> Vec<FileInfo> {
+        options.finish(files, SortKey::Size, |file| Fields::entry(file))
+    }
+
+    // folders and file types below the usual minimum are left out unless given --min-size
+    fn finish_folders(folders: Vec<FolderSize>, options: &ListingOptions) -> Vec<FolderSize> {
+        let options = options.min_size_or((MIN_FOLDER_SIZE_GB * GB_TO_BYTES) as u64);
+        options.finish(folders, SortKey::Size, |folder| {
+            Fields::path(folder.folder.as_str(), folder.size).count(folder.file_count)
+        })
+    }
+
+    fn finish_file_types(types: Vec<(String, f64, usize)>, options: &ListingOptions) -> Vec<(String, f64, usize)> {
+        let options = options.min_size_or((MIN_FILE_TYPE_SIZE_GB * GB_TO_BYTES) as u64);
+        options.finish(types, SortKey::Size, |(ext, size, count)| {
+            Fields::named(ext.as_str(), (size * GB_TO_BYTES) as u64).count(*count)
+        })
+    }
+
+    pub fn print_file_type_distribution(&mut self, drive: &str, options: &ListingOptions) -> io::Result<()> {
+        let distribution = Self::finish_file_types(self.get_file_type_distribution(drive, options)?, options);
+        if self.json() {
+            let rows: Vec<ListingRow> = distribution
+                .into_iter()
+                .map(|(ext, size, _)| ListingRow { name: ext, size: (size * GB_TO_BYTES) as u64, modified: None })
+                .collect();
+            return Self::print_json(&rows);
+        }
+        note(format!("\n--- File Type Distribution (Top {}) ---", options.top()));
+        for (ext, size, count) in distribution.iter() {
             println!(
                 "\n[>] {} \n  Count: {} \n  Size: {:.2} GB",
                 ext, count, size
@@ -345,63 +801,417 @@ impl StorageAnalyser {
         Ok(())
     }
 
-    pub fn print_largest_files(&mut self, drive: &str) -> io::Result<()> {
-        println!("\n--- Largest Files ---");
-        let files = self.get_largest_files(drive)?;
-        for file in files.iter().take(10) {
+    pub fn print_largest_files(&mut self, drive: &str, options: &ListingOptions) -> io::Result<()> {
+        let files = Self::finish_files(self.get_largest_files(drive)?, options);
+        if self.json() {
+            return Self::print_json(&Self::file_rows(files));
+        }
+        note(format!("\n--- Largest Files (Top {}) ---", options.top()));
+        for file in files.iter() {
             Self::print_file_info(file)
         }
         Ok(())
     }
 
     /// returns largest folders up to 3 levels deep
-    ///
-    /// excludes hidden folders (those starting with '.')
-    pub fn print_largest_folders(&mut self, drive: &str) -> io::Result<()> {
-        println!("\n--- Largest Folders (Top 10) ---");
-
+    pub fn print_largest_folders(&mut self, drive: &str, options: &ListingOptions) -> io::Result<()> {
         if !self.folder_cache.contains_key(drive) {
-            collect_and_cache_files(drive, &mut self.file_cache, &mut self.folder_cache)?;
+            self.ensure_scanned(drive)?;
         }
 
-        let folders = self.get_largest_folders(drive)?;
+        let folders = Self::finish_folders(self.get_largest_folders(drive)?, options);
+        if self.json() {
+            let rows: Vec<ListingRow> = folders
+                .into_iter()
+                .map(|folder| ListingRow { name: folder.folder, size: folder.size, modified: None })
+                .collect();
+            return Self::print_json(&rows);
+        }
+        note(format!("\n--- Largest Folders (Top {}) ---", options.top()));
 
-        let mut cnt: i8 = 0;
-        for folder in folders.iter().take(10) {
+        let mut cnt: usize = 0;
+        for folder in folders.iter() {
             cnt += 1;
             println!("\n[{}] {}", cnt, folder.folder);
-            println!("  Size: {:.2} GB", folder.size_gb);
+            println!("  Size: {:.2} GB", folder.size as f64 / GB_TO_BYTES);
             println!("  Files: {}", folder.file_count);
         }
 
         Ok(())
     }
 
-    pub fn print_old_large_files(&mut self, drive: &str) -> io::Result<()> {
-        println!("\n--- Old Large Files (>6 months old) ---");
-        let files = self.get_old_large_files(drive)?;
-        for file in files.iter().take(10) {
+    pub fn print_old_large_files(&mut self, drive: &str, options: &ListingOptions) -> io::Result<()> {
+        let files = Self::finish_files(self.get_old_large_files(drive, options)?, options);
+        if self.json() {
+            return Self::print_json(&Self::file_rows(files));
+        }
+        note(format!("\n--- Old Large Files (>{} old) ---", options.age(false)));
+        for file in files.iter() {
             Self::print_file_info(file)
         }
         Ok(())
     }
 
-    pub fn print_recent_large_files(&mut self, drive: &str) -> io::Result<()> {
-        println!("\n--- Recent Large Files ---");
-        let files = self.get_recent_large_files(drive)?;
-        for file in files.iter().take(10) {
+    pub fn print_recent_large_files(&mut self, drive: &str, options: &ListingOptions) -> io::Result<()> {
+        let files = Self::finish_files(self.get_recent_large_files(drive, options)?, options);
+        if self.json() {
+            return Self::print_json(&Self::file_rows(files));
+        }
+        note(format!("\n--- Recent Large Files (<{} old) ---", options.age(true)));
+        for file in files.iter() {
             Self::print_file_info(file)
         }
         Ok(())
     }
 
+    /// searches the cached scans of the drives, largest matches first
+    pub fn print_search_results(&mut self, drives: &[String], query: &SearchQuery, top: usize) -> io::Result<()> {
+        for drive in drives {
+            self.cache_entries(drive)?;
+            if !self.name_index.contains_key(drive)
+                && let Some(index) = TrigramIndex::restore(drive, &self.entry_cache[drive])
+            {
+                self.name_index.insert(drive.to_string(), index);
+            }
+        }
+
+        let start = Instant::now();
+        let literals = query.literals();
+        let mut matches: Vec<&ScanEntry> = Vec::new();
+        for drive in drives {
+            let entries = &self.entry_cache[drive];
+            match self.name_index.get(drive).and_then(|index| index.candidates(&literals)) {
+                Some(candidates) => matches.extend(
+                    candidates.into_iter().map(|i| &entries[i]).filter(|entry| query.matches(entry)),
+                ),
+                None => matches.extend(entries.iter().filter(|entry| query.matches(entry))),
+            }
+        }
+        matches.retain(|entry| self.attribute_filter.allows(entry.attributes));
+        matches.sort_by_key(|entry| Reverse(entry.size));
+        if self.json() {
+            return Self::print_json(&matches.into_iter().take(top).collect::<Vec<_>>());
+        }
+        note(format!("\n--- Search Results for \"{}\" ---", query.pattern));
+        note(format!("Found {} matches in {:?}", matches.len(), start.elapsed()));
+
+        for entry in matches.iter().take(top) {
+            Self::print_entry_info(entry)
+        }
+        Ok(())
+    }
+
+    /// a `limit` in the query wins over `top`
+    pub fn print_query_results(&mut self, drive: &str, query: &Query, top: usize) -> io::Result<()> {
+        let json = self.json();
+        let filter = self.attribute_filter;
+        let entries: Vec<&ScanEntry> =
+            self.scan_entries(drive)?.iter().filter(|entry| filter.allows(entry.attributes)).collect();
+
+        let start = Instant::now();
+        let total = entries.iter().filter(|e| query.matches(e)).count();
+        let matches = query.apply(entries);
+        let shown = query.limit.unwrap_or(top);
+        if json {
+            return Self::print_json(&matches.into_iter().take(shown).collect::<Vec<_>>());
+        }
+        note("\n--- Query Results ---");
+        note(format!("Found {} matches in {:?}", total, start.elapsed()));
+
+        for entry in matches.iter().take(shown) {
+            Self::print_entry_info(entry)
+        }
+        Ok(())
+    }
+
+    pub fn print_compression_estimate(&mut self, drive: &str, blocks: usize, top: usize) -> io::Result<()> {
+        note("\n--- Compression Estimate ---");
+        let entries = self.scan_entries(drive)?;
+        let files: Vec<&ScanEntry> = entries.iter().filter(|e| !e.is_directory).collect();
+
+        let start = Instant::now();
+        let estimate = estimate_compression(&files, blocks);
+        println!("Estimated in {:?}", start.elapsed());
+        print_compression_estimate(&estimate, top);
+        Ok(())
+    }
+
+    pub fn print_zero_regions(&mut self, drive: &str, min_size: u64, throttle: Option<u64>, top: usize) -> io::Result<()> {
+        note("\n--- Zero Regions ---");
+        let entries = self.scan_entries(drive)?;
+        let files: Vec<&ScanEntry> = entries.iter().filter(|e| !e.is_directory).collect();
+
+        let (results, unreadable) = time_command(|| scan_zero_regions(&files, min_size, throttle));
+        print_zero_regions(&results, unreadable, top);
+        Ok(())
+    }
+
+    pub fn print_perm_audit(&mut self, target: &str) -> io::Result<()> {
+        note("\n--- Permission Audit ---");
+        if cfg!(target_os = "windows") {
+            println!("perm-audit checks Unix permissions, it has nothing to look at on Windows");
+            return Ok(());
+        }
+        let findings = time_command(|| self.get_permission_findings(target))?;
+
+        let issues = [
+            PermIssue::WorldWritable,
+            PermIssue::Setuid,
+            PermIssue::Setgid,
+            PermIssue::UnknownOwner,
+            PermIssue::PermissiveInHome,
+        ];
+        for issue in issues {
+            let matching: Vec<&PermFinding> = findings.iter().filter(|f| f.issues.contains(&issue)).collect();
+            if matching.is_empty() {
+                continue;
+            }
+            let bytes: u64 = matching.iter().map(|f| f.size).sum();
+            println!("\n{} ({} paths, {:.2} MB):", issue, matching.len(), bytes as f64 / MB_TO_BYTES);
+            for finding in matching {
+                println!(
+                    "{:>6o}  uid {:<6} {:>10.2} MB  {}{}",
+                    finding.permissions.mode & 0o7777,
+                    finding.permissions.uid,
+                    finding.size as f64 / MB_TO_BYTES,
+                    finding.path,
+                    if finding.is_directory { "/" } else { "" }
+                );
+            }
+        }
+        println!("\n{} paths with permission problems under {}", findings.len(), target);
+        Ok(())
+    }
+
+    pub fn print_attributes(&mut self, drive: &str) -> io::Result<()> {
+        note("\n--- File Attributes ---");
+        self.ensure_scanned(drive)?;
+        let files = self.file_cache.get(drive).into_iter().flatten();
+        let (counts, total) = count_attributes(files.map(|f| (f.attributes, f.size)));
+        print_attribute_counts(&counts, total);
+        Ok(())
+    }
+
+    pub fn print_links(&mut self, drive: &str, follow: bool, top: usize) -> io::Result<()> {
+        note("\n--- Links ---");
+        let links = time_command(|| walk_links(drive, follow));
+        print_links(&links, top);
+        Ok(())
+    }
+
+    pub fn print_scan_errors(&mut self, drive: &str, top: usize) -> io::Result<()> {
+        self.ensure_scanned(drive)?;
+        let Some(log) = self.scan_errors.get(drive) else {
+            return Ok(());
+        };
+        if self.json() {
+            return Self::print_json(&log.errors());
+        }
+        note("\n--- Scan Errors ---");
+        print_scan_errors(log, top);
+        Ok(())
+    }
+
     pub fn print_empty_folders(&mut self, drive: &str) -> io::Result<()> {
-        println!("\n--- Empty Folders ---");
-        let empty_folders = self.get_empty_folders(drive)?;
-        println!("Found {} empty folders.", empty_folders.len());
-        for folder in empty_folders.iter() {
-            println!(" - {}", folder);
+        note("\n--- Empty Folders ---");
+        let trees = self.get_empty_folder_trees(drive, false)?;
+        println!("Found {} empty folder trees.", trees.len());
+        for tree in trees.iter() {
+            println!(" - {}", tree_summary(tree));
+        }
+        Ok(())
+    }
+
+
+    // -- every fixed drive at once -- //
+
+    fn print_report(&mut self, report: DriveReport, drive: &str, options: &ListingOptions) -> io::Result<()> {
+        match report {
+            DriveReport::DriveSpace => self.print_drive_space_overview(drive),
+            DriveReport::FileTypes => self.print_file_type_distribution(drive, options),
+            DriveReport::LargestFiles => self.print_largest_files(drive, options),
+            DriveReport::LargestFolders => self.print_largest_folders(drive, options),
+            DriveReport::RecentLargeFiles => self.print_recent_large_files(drive, options),
+            DriveReport::OldLargeFiles => self.print_old_large_files(drive, options),
+            DriveReport::FullAnalysis => self.analyze_drive(drive),
+        }
+    }
+
+    /// Runs a listing on every fixed drive: the drives are scanned in parallel, then each gets
+    /// its own section, followed by one ranking across all of them and the machine's totals.
+    pub fn print_all_drives(&mut self, report: DriveReport, options: &ListingOptions) -> io::Result<()> {
+        let drives = self.fixed_drives();
+        if drives.is_empty() {
+            return Err(Error::new(io::ErrorKind::NotFound, "No fixed drives found"));
+        }
+        if report != DriveReport::DriveSpace {
+            self.scan_drives(&drives);
+        }
+
+        for drive in &drives {
+            note(format!("\n===== Drive {} =====", drive));
+            if let Err(e) = self.print_report(report, drive, options) {
+                fail(format!("Error: {}", e));
+            }
+        }
+
+        note("\n===== All Drives =====");
+        match report {
+            DriveReport::DriveSpace => {}
+            DriveReport::FileTypes => self.print_merged_file_types(&drives, options),
+            DriveReport::LargestFiles => {
+                self.print_merged_files("Largest Files", &drives, options, |this, drive| this.get_largest_files(drive))
+            }
+            DriveReport::LargestFolders => self.print_merged_folders(&drives, options),
+            DriveReport::RecentLargeFiles => self.print_merged_files(
+                &format!("Recent Large Files (<{} old)", options.age(true)),
+                &drives,
+                options,
+                |this, drive| this.get_recent_large_files(drive, options),
+            ),
+            DriveReport::OldLargeFiles => self.print_merged_files(
+                &format!("Old Large Files (>{} old)", options.age(false)),
+                &drives,
+                options,
+                |this, drive| this.get_old_large_files(drive, options),
+            ),
+            DriveReport::FullAnalysis => {
+                self.print_merged_folders(&drives, options);
+                self.print_merged_file_types(&drives, options);
+                self.print_merged_files("Largest Files", &drives, options, |this, drive| this.get_largest_files(drive));
+            }
         }
+        self.print_machine_summary(&drives);
         Ok(())
     }
+
+    /// Measures what the `check` rules look at, drive space and (when a rule needs it) the scanned
+    /// folders and large files, then evaluates them and keeps the measurements for the next check.
+    /// Drives that couldn't be scanned in full are left out of the scanned measurements.
+    pub fn run_check(&mut self, config: &CheckConfig) -> io::Result<CheckReport> {
+        let drives = if config.drives.is_empty() {
+            self.fixed_drives()
+        } else {
+            config.drives.iter().map(|letter| format!("{}:/", letter)).collect()
+        };
+        if drives.is_empty() {
+            return Err(Error::new(io::ErrorKind::NotFound, "No fixed drives found"));
+        }
+
+        let mut measured = Measurements::default();
+        for drive in &drives {
+            let space = self.get_drive_space(drive)?;
+            measured.drives.push(DriveMeasure {
+                drive: drive.clone(),
+                used_bytes: (space.used_space * GB_TO_BYTES) as u64,
+                free_percent: space.free_space_percent,
+            });
+        }
+
+        if config.needs_scan() {
+            self.scan_drives(&drives);
+            let large_file_bytes = (config.large_file_gb * GB_TO_BYTES) as u64;
+            for drive in &drives {
+                if !self.file_cache.contains_key(drive) || self.incomplete.contains(drive) {
+                    measured.unscanned.push(drive.clone());
+                    continue;
+                }
+                measured.folders.extend(
+                    self.folder_cache.get(drive).into_iter().flatten().map(|f| (f.folder.clone(), f.size as f64 / GB_TO_BYTES)),
+                );
+                measured.large_files.extend(
+                    self.file_cache
+                        .get(drive)
+                        .into_iter()
+                        .flatten()
+                        .filter(|f| f.size >= large_file_bytes)
+                        .map(|f| f.full_path.clone()),
+                );
+            }
+            measured.scanned = true;
+        }
+
+        let state_file = app_file(CHECK_STATE_FILE);
+        let previous = CheckState::load(&state_file);
+        let results = evaluate(config, &measured, previous.as_ref());
+        measured.to_state(previous.as_ref()).save(&state_file)?;
+        Ok(CheckReport { results, unscanned: measured.unscanned })
+    }
+
+    // the top N of every drive are enough to find the top N across all of them
+    fn print_merged_files(
+        &mut self,
+        title: &str,
+        drives: &[String],
+        options: &ListingOptions,
+        mut get_files: impl FnMut(&mut Self, &str) -> io::Result<Vec<FileInfo>>,
+    ) {
+        let files: Vec<FileInfo> = drives
+            .iter()
+            .filter_map(|drive| get_files(self, drive).ok())
+            .flat_map(|files| Self::finish_files(files, options))
+            .collect();
+        note(format!("\n--- {}, All Drives (Top {}) ---", title, options.top()));
+        for file in Self::finish_files(files, options).iter() {
+            Self::print_file_info(file)
+        }
+    }
+
+    fn print_merged_folders(&mut self, drives: &[String], options: &ListingOptions) {
+        let folders: Vec<FolderSize> = drives
+            .iter()
+            .filter_map(|drive| self.get_largest_folders(drive).ok())
+            .flat_map(|folders| Self::finish_folders(folders, options))
+            .collect();
+        note(format!("\n--- Largest Folders, All Drives (Top {}) ---", options.top()));
+        for (rank, folder) in Self::finish_folders(folders, options).iter().enumerate() {
+            println!("\n[{}] {}", rank + 1, folder.folder);
+            println!("  Size: {:.2} GB", folder.size as f64 / GB_TO_BYTES);
+            println!("  Files: {}", folder.file_count);
+        }
+    }
+
+    // sizes and counts per extension added up over the drives
+    fn print_merged_file_types(&mut self, drives: &[String], options: &ListingOptions) {
+        let mut types: HashMap<String, (f64, usize)> = HashMap::new();
+        for drive in drives {
+            for (ext, size, count) in self.get_file_type_distribution(drive, options).unwrap_or_default() {
+                let total = types.entry(ext).or_insert((0.0, 0));
+                total.0 += size;
+                total.1 += count;
+            }
+        }
+        let types = types.into_iter().map(|(ext, (size, count))| (ext, size, count)).collect();
+        note(format!("\n--- File Type Distribution, All Drives (Top {}) ---", options.top()));
+        for (ext, size, count) in Self::finish_file_types(types, options).iter() {
+            println!("\n[>] {} \n  Count: {} \n  Size: {:.2} GB", ext, count, size);
+        }
+    }
+
+    /// total, used and free space added up over every fixed drive, with a line per drive
+    fn print_machine_summary(&self, drives: &[String]) {
+        note(format!("\n--- Machine Summary ({} fixed drives) ---", drives.len()));
+        let (mut total, mut used, mut free) = (0.0, 0.0, 0.0);
+        for drive in drives {
+            match self.get_drive_space(drive) {
+                Ok(space) => {
+                    println!(
+                        "{}  {:>10.2} GB total  {:>10.2} GB used  {:>10.2} GB free ({:.2}%)",
+                        drive, space.total_size, space.used_space, space.free_space, space.free_space_percent
+                    );
+                    total += space.total_size;
+                    used += space.used_space;
+                    free += space.free_space;
+                }
+                Err(e) => fail(format!("{}  couldn't read its space: {}", drive, e)),
+            }
+        }
+        println!("Total Size: {:.2} GB", total);
+        println!("Used Space: {:.2} GB", used);
+        println!(
+            "Free Space: {:.2} GB ({:.2}%)",
+            free,
+            if total > 0.0 { free / total * 100.0 } else { 0.0 }
+        );
+    }
 }
@@ -11,16 +11,29 @@ pub struct DriveAnalysis {
 #[derive(Debug, Clone, Serialize)]
 pub struct FolderSize {
     pub folder: String,
-    pub size_gb: f64,
+    /// in bytes
+    pub size: u64,
     pub file_count: usize,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FileInfo {
     pub full_path: String,
-    pub size_mb: f64,
+    /// in bytes
+    pub size: u64,
     pub last_modified: Option<String>,
     pub last_accessed: Option<String>,
+    /// mode and owner, only filled in on Unix
+    pub permissions: Option<UnixPermissions>,
+    /// FILE_ATTRIBUTE_* flags, see `FileAttribute`
+    pub attributes: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct UnixPermissions {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
 }
 
 #[derive(Debug, Default)]
@@ -28,3 +41,400 @@ pub struct FileTypeStats {
     pub total_size: u64,
     pub count: usize,
 }
+
+/// how likely it is that a deleted file's data is still on disk
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Recovery {
+    /// the data lives inside the MFT record itself
+    Resident,
+    /// none of the file's clusters have been handed out again
+    Intact,
+    /// some of the clusters are allocated again, holds the overwritten percentage
+    Partial(f64),
+    Overwritten,
+    /// no data runs left to check
+    Unknown,
+}
+
+impl std::fmt::Display for Recovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Recovery::Resident => write!(f, "resident in MFT"),
+            Recovery::Intact => write!(f, "likely recoverable"),
+            Recovery::Partial(pct) => write!(f, "partially overwritten ({:.0}%)", pct),
+            Recovery::Overwritten => write!(f, "overwritten"),
+            Recovery::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedFile {
+    pub record: u64,
+    pub name: String,
+    pub parent_path: String,
+    pub size: u64,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub recovery: Recovery,
+}
+
+/// the usual leftovers of timestomping, found by comparing $STANDARD_INFORMATION to $FILE_NAME
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum TimestampFlag {
+    SiCreatedBeforeFn,
+    SiModifiedBeforeFn,
+    SiChangedBeforeFn,
+    SiAccessedBeforeFn,
+    ZeroedSubseconds,
+    BeforeVolumeCreation,
+    InFuture,
+    NullEpoch,
+}
+
+impl std::fmt::Display for TimestampFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            TimestampFlag::SiCreatedBeforeFn => "SI created earlier than FN",
+            TimestampFlag::SiModifiedBeforeFn => "SI modified earlier than FN",
+            TimestampFlag::SiChangedBeforeFn => "SI MFT changed earlier than FN",
+            TimestampFlag::SiAccessedBeforeFn => "SI accessed earlier than FN",
+            TimestampFlag::ZeroedSubseconds => "zeroed sub-second precision",
+            TimestampFlag::BeforeVolumeCreation => "created before the volume",
+            TimestampFlag::InFuture => "timestamp in the future",
+            TimestampFlag::NullEpoch => "1601 epoch (zeroed timestamp)",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimestampAnomaly {
+    pub path: String,
+    pub si_created: Option<String>,
+    pub si_modified: Option<String>,
+    pub fn_created: Option<String>,
+    pub fn_modified: Option<String>,
+    pub flags: Vec<TimestampFlag>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FragmentedFile {
+    pub path: String,
+    pub size: u64,
+    pub extents: usize,
+    /// biggest jump between two consecutive extents, in bytes
+    pub largest_gap: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FragmentationSummary {
+    pub files: usize,
+    pub fragmented_files: usize,
+    pub total_extents: usize,
+    pub total_bytes: u64,
+    pub fragmented_bytes: u64,
+}
+
+impl FragmentationSummary {
+    /// percentage of the non-resident data that sits in fragmented files
+    pub fn score(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.fragmented_bytes as f64 / self.total_bytes as f64 * 100.0
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ClusterLayout {
+    pub cluster_size: u64,
+    pub total_clusters: u64,
+    pub allocated_clusters: u64,
+    pub free_extents: u64,
+    pub largest_free_lcn: u64,
+    pub largest_free_clusters: u64,
+    pub mft_size: u64,
+    /// `(lcn, clusters)` of every piece of $MFT, read from its data runs
+    pub mft_extents: Vec<(u64, u64)>,
+    /// free clusters right after the last piece, what the MFT can grow into without fragmenting
+    pub mft_growth_clusters: u64,
+    /// rows of the ASCII allocation map
+    pub map: Vec<String>,
+}
+
+/// one file or folder from a cached scan, the common shape both backends can be searched in
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanEntry {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    pub is_directory: bool,
+    /// unix timestamp (seconds)
+    pub modified: Option<i64>,
+    /// FILE_ATTRIBUTE_* flags, see `FileAttribute`
+    pub attributes: u32,
+}
+
+/// the NTFS attribute flags the reports know about, as stored in $STANDARD_INFORMATION
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum FileAttribute {
+    ReadOnly,
+    Hidden,
+    System,
+    Temporary,
+    Compressed,
+    Offline,
+    NotContentIndexed,
+    Encrypted,
+}
+
+impl FileAttribute {
+    pub const ALL: [FileAttribute; 8] = [
+        FileAttribute::Hidden,
+        FileAttribute::System,
+        FileAttribute::ReadOnly,
+        FileAttribute::Compressed,
+        FileAttribute::Encrypted,
+        FileAttribute::Offline,
+        FileAttribute::Temporary,
+        FileAttribute::NotContentIndexed,
+    ];
+
+    /// the FILE_ATTRIBUTE_* bit
+    pub fn mask(self) -> u32 {
+        match self {
+            FileAttribute::ReadOnly => 0x1,
+            FileAttribute::Hidden => 0x2,
+            FileAttribute::System => 0x4,
+            FileAttribute::Temporary => 0x100,
+            FileAttribute::Compressed => 0x800,
+            FileAttribute::Offline => 0x1000,
+            FileAttribute::NotContentIndexed => 0x2000,
+            FileAttribute::Encrypted => 0x4000,
+        }
+    }
+
+    pub fn is_set(self, attributes: u32) -> bool {
+        attributes & self.mask() != 0
+    }
+}
+
+impl std::fmt::Display for FileAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            FileAttribute::ReadOnly => "read-only",
+            FileAttribute::Hidden => "hidden",
+            FileAttribute::System => "system",
+            FileAttribute::Temporary => "temporary",
+            FileAttribute::Compressed => "compressed",
+            FileAttribute::Offline => "offline",
+            FileAttribute::NotContentIndexed => "not content indexed",
+            FileAttribute::Encrypted => "encrypted",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// things in a name or path that trip up Windows tools, even though NTFS allows them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum PathIssue {
+    TooLong,
+    TrailingDotOrSpace,
+    ReservedName,
+    InvalidUtf16,
+    CaseCollision,
+    ControlCharacter,
+    ForbiddenCharacter,
+}
+
+impl std::fmt::Display for PathIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            PathIssue::TooLong => "path longer than 260 characters",
+            PathIssue::TrailingDotOrSpace => "trailing dot or space",
+            PathIssue::ReservedName => "reserved device name",
+            PathIssue::InvalidUtf16 => "invalid UTF-16",
+            PathIssue::CaseCollision => "differs only by case from a sibling",
+            PathIssue::ControlCharacter => "control character",
+            PathIssue::ForbiddenCharacter => "character Win32 forbids (<>:\"|?*)",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathProblem {
+    pub path: String,
+    pub size: u64,
+    pub is_directory: bool,
+    pub issues: Vec<PathIssue>,
+}
+
+/// a folder whose whole subtree holds no files, reported once at its topmost point
+#[derive(Debug, Clone, Serialize)]
+pub struct EmptyTree {
+    pub root: String,
+    /// empty folders below the root
+    pub nested: Vec<String>,
+    /// zero-byte or junk files that were treated as nothing (only in junk mode)
+    pub junk_files: Vec<String>,
+}
+
+/// files with identical contents, the first path is the one that's kept on a dedupe
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub sha256: String,
+    pub paths: Vec<String>,
+}
+
+/// a large file with all-zero blocks in it, or one that's sparse already
+#[derive(Debug, Clone, Serialize)]
+pub struct ZeroRegionFile {
+    pub path: String,
+    pub size: u64,
+    /// bytes in all-zero 64 KB blocks, holes of a sparse file included
+    pub zero_bytes: u64,
+    /// bytes actually taking up space on disk
+    pub allocated: u64,
+    pub sparse: bool,
+}
+
+impl ZeroRegionFile {
+    /// zero bytes that still take up space, so making the file sparse would free them
+    pub fn reclaimable(&self) -> u64 {
+        let holes = self.size.saturating_sub(self.allocated);
+        self.zero_bytes.saturating_sub(holes)
+    }
+}
+
+/// what `perm-audit` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum PermIssue {
+    WorldWritable,
+    Setuid,
+    Setgid,
+    UnknownOwner,
+    PermissiveInHome,
+}
+
+impl std::fmt::Display for PermIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            PermIssue::WorldWritable => "world-writable (no sticky bit)",
+            PermIssue::Setuid => "setuid",
+            PermIssue::Setgid => "setgid",
+            PermIssue::UnknownOwner => "owner doesn't exist",
+            PermIssue::PermissiveInHome => "too permissive for a home directory",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermFinding {
+    pub path: String,
+    pub size: u64,
+    pub is_directory: bool,
+    pub permissions: UnixPermissions,
+    pub issues: Vec<PermIssue>,
+}
+
+/// what kind of link or reparse point an entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum LinkKind {
+    Symlink,
+    Junction,
+    /// a whole volume mounted into a folder (or a bind mount on Linux)
+    MountPoint,
+    /// OneDrive and other cloud files that may not be on disk at all
+    CloudPlaceholder,
+    /// a file whose data lives in the Data Deduplication chunk store
+    Dedup,
+    /// any other reparse tag
+    Other(u32),
+}
+
+impl std::fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkKind::Symlink => write!(f, "symlink"),
+            LinkKind::Junction => write!(f, "junction"),
+            LinkKind::MountPoint => write!(f, "mount point"),
+            LinkKind::CloudPlaceholder => write!(f, "cloud placeholder"),
+            LinkKind::Dedup => write!(f, "dedup"),
+            LinkKind::Other(tag) => write!(f, "reparse tag {:#010x}", tag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LinkStatus {
+    Ok,
+    /// the target doesn't exist
+    Broken,
+    /// the target is one of the link's own parent folders
+    Cycle,
+    /// couldn't be checked (images, or links without a path target)
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkInfo {
+    pub path: String,
+    pub kind: LinkKind,
+    pub target: Option<String>,
+    pub status: LinkStatus,
+    /// bytes reached by following the link that aren't part of the scan already,
+    /// only filled in when links are followed
+    pub followed_bytes: Option<u64>,
+}
+
+/// Why part of a scan couldn't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum ScanErrorKind {
+    PermissionDenied,
+    /// gone between being listed and being read
+    NotFound,
+    /// a followed link leading back to one of its own parents
+    LinkLoop,
+    /// a name that isn't valid Unicode, so it has no usable path. only the MFT explorer
+    /// reports it, the folder walk keeps a lossy copy of the name instead
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    InvalidName,
+    Other,
+}
+
+impl std::fmt::Display for ScanErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ScanErrorKind::PermissionDenied => write!(f, "permission denied"),
+            ScanErrorKind::NotFound => write!(f, "not found"),
+            ScanErrorKind::LinkLoop => write!(f, "link loop"),
+            ScanErrorKind::InvalidName => write!(f, "invalid name"),
+            ScanErrorKind::Other => write!(f, "other I/O error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanError {
+    /// the folder that couldn't be read, or the folder holding the files that couldn't
+    pub path: String,
+    pub kind: ScanErrorKind,
+    pub count: usize,
+    /// true when the folder itself couldn't be listed, so nothing below it was scanned
+    pub subtree: bool,
+}
+
+/// One row of a size listing the way `--format json` prints it, sizes in bytes.
+/// `name` is the path for files and folders, the extension for file types.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListingRow {
+    pub name: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+}
@@ -0,0 +1,277 @@
+// finds large files that are mostly zeros (VM disks, preallocated downloads, databases)
+// and works out what making them sparse would give back, next to files already sparse
+use super::types::{ScanEntry, ZeroRegionFile};
+use crate::utility::constants::MB_TO_BYTES;
+use crate::utility::output::note;
+use crate::utility::progress::{CancelGuard, INCOMPLETE_RESULTS, cancelled};
+use crate::utility::utils::parse_size;
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// sparse ranges are allocated in 64 KB units on NTFS, smaller zero runs can't be freed
+pub const ZERO_BLOCK: usize = 64 * 1024;
+pub const DEFAULT_MIN_ZERO_SCAN_SIZE: u64 = 100 * 1_048_576;
+const READ_CHUNK: usize = 16 * ZERO_BLOCK;
+
+pub const ZERO_REGIONS_USAGE: &str = "Usage: zero-regions [drive] [-min-size N] [-throttle MB/s]";
+
+/// what `zero-regions` was asked to do
+#[derive(Debug, PartialEq)]
+pub struct ZeroRegionArgs {
+    pub drive: String,
+    pub min_size: u64,
+    /// read limit in bytes per second
+    pub throttle: Option<u64>,
+}
+
+impl ZeroRegionArgs {
+    /// parses everything after `zero-regions`
+    pub fn parse(words: &[String]) -> Result<Self, String> {
+        let drive = match words.first() {
+            Some(drive) if !drive.starts_with('-') => drive.clone(),
+            _ => return Err(format!("Drive letter required. {}", ZERO_REGIONS_USAGE)),
+        };
+        let mut args = ZeroRegionArgs {
+            drive,
+            min_size: DEFAULT_MIN_ZERO_SCAN_SIZE,
+            throttle: None,
+        };
+        let mut rest = words[1..].iter();
+        while let Some(word) = rest.next() {
+            match word.as_str() {
+                "-min-size" => {
+                    args.min_size = rest
+                        .next()
+                        .and_then(|n| parse_size(n))
+                        .ok_or("-min-size needs a size, like 500MB")?;
+                }
+                "-throttle" => {
+                    args.throttle = rest
+                        .next()
+                        .and_then(|n| n.parse::<f64>().ok())
+                        .filter(|&mb| mb > 0.0)
+                        .map(|mb| (mb * MB_TO_BYTES) as u64);
+                    if args.throttle.is_none() {
+                        return Err("-throttle needs MB per second above 0".to_string());
+                    }
+                }
+                other => return Err(format!("Unknown option '{}'. {}", other, ZERO_REGIONS_USAGE)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// keeps reads under a byte rate by sleeping whenever they get ahead of it
+struct Throttle {
+    limit: Option<u64>,
+    start: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    fn new(limit: Option<u64>) -> Self {
+        Throttle { limit, start: Instant::now(), bytes: 0 }
+    }
+
+    fn consumed(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+        if let Some(limit) = self.limit {
+            let allowed = Duration::from_secs_f64(self.bytes as f64 / limit as f64);
+            let elapsed = self.start.elapsed();
+            if allowed > elapsed {
+                thread::sleep(allowed - elapsed);
+            }
+        }
+    }
+}
+
+/// how many whole ZERO_BLOCK blocks of `data` are nothing but zeros
+fn count_zero_blocks(data: &[u8]) -> usize {
+    data.chunks(ZERO_BLOCK)
+        .filter(|block| block.len() == ZERO_BLOCK && block.iter().all(|&b| b == 0))
+        .count()
+}
+
+/// bytes in all-zero blocks of a file, block aligned from the start of the file
+fn zero_bytes(path: &Path, throttle: &mut Throttle) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; READ_CHUNK];
+    let mut zero_blocks = 0u64;
+    loop {
+        // fill the whole chunk so blocks stay aligned even after short reads
+        let mut filled = 0;
+        while filled < READ_CHUNK {
+            match file.read(&mut buffer[filled..])? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+        if filled == 0 {
+            break;
+        }
+        if cancelled() {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        zero_blocks += count_zero_blocks(&buffer[..filled]) as u64;
+        throttle.consumed(filled);
+    }
+    Ok(zero_blocks * ZERO_BLOCK as u64)
+}
+
+/// (sparse flag, bytes actually allocated on disk)
+#[cfg(target_os = "windows")]
+fn sparse_info(path: &Path) -> io::Result<(bool, u64)> {
+    use std::os::windows::{ffi::OsStrExt, fs::MetadataExt};
+    use winapi::um::{
+        errhandlingapi::SetLastError, fileapi::GetCompressedFileSizeW, winnt::FILE_ATTRIBUTE_SPARSE_FILE,
+    };
+
+    let sparse = fs::metadata(path)?.file_attributes() & FILE_ATTRIBUTE_SPARSE_FILE != 0;
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut high = 0u32;
+    // u32::MAX is also a real low half, only a last error set by the call means it failed
+    let low = unsafe {
+        SetLastError(0);
+        GetCompressedFileSizeW(wide.as_ptr(), &mut high)
+    };
+    if low == u32::MAX {
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(0) {
+            return Err(error);
+        }
+    }
+    Ok((sparse, (high as u64) << 32 | low as u64))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn sparse_info(path: &Path) -> io::Result<(bool, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    let allocated = metadata.blocks() * 512;
+    Ok((allocated < metadata.len(), allocated))
+}
+
+/// Reads every file at or above `min_size` and counts its all-zero blocks.
+/// Files that can't be read are skipped, the count of those is returned alongside.
+/// Ctrl+C stops the reading, what was found so far is returned and noted as incomplete.
+pub fn scan_zero_regions(files: &[&ScanEntry], min_size: u64, throttle: Option<u64>) -> (Vec<ZeroRegionFile>, usize) {
+    let candidates: Vec<&&ScanEntry> = files
+        .iter()
+        .filter(|f| !f.is_directory && f.size >= min_size)
+        .collect();
+    let total: u64 = candidates.iter().map(|f| f.size).sum();
+    note(format!(
+        "Reading {} files over {:.0} MB ({:.2} GB){}..",
+        candidates.len(),
+        min_size as f64 / MB_TO_BYTES,
+        total as f64 / (MB_TO_BYTES * 1024.0),
+        throttle.map_or(String::new(), |t| format!(", throttled to {:.0} MB/s", t as f64 / MB_TO_BYTES))
+    ));
+
+    let _cancel = CancelGuard::begin();
+    let mut throttle = Throttle::new(throttle);
+    let mut results = Vec::new();
+    let mut unreadable = 0;
+    for file in candidates {
+        if cancelled() {
+            break;
+        }
+        let path = Path::new(&file.path);
+        let scanned = sparse_info(path).and_then(|(sparse, allocated)| {
+            Ok(ZeroRegionFile {
+                path: file.path.clone(),
+                size: fs::metadata(path)?.len(),
+                zero_bytes: zero_bytes(path, &mut throttle)?,
+                allocated,
+                sparse,
+            })
+        });
+        match scanned {
+            Ok(result) if result.zero_bytes > 0 || result.sparse => results.push(result),
+            Ok(_) => {}
+            // the file cut short by Ctrl+C wasn't unreadable
+            Err(_) if cancelled() => {}
+            Err(_) => unreadable += 1,
+        }
+    }
+    if cancelled() {
+        note(INCOMPLETE_RESULTS);
+    }
+    results.sort_by_key(|r| std::cmp::Reverse(r.reclaimable()));
+    (results, unreadable)
+}
+
+fn gb(bytes: u64) -> f64 {
+    bytes as f64 / (MB_TO_BYTES * 1024.0)
+}
+
+/// Prints the top `count` files by reclaimable bytes, then the files that are already sparse.
+pub fn print_zero_regions(results: &[ZeroRegionFile], unreadable: usize, count: usize) {
+    let candidates: Vec<&ZeroRegionFile> = results.iter().filter(|r| r.reclaimable() > 0).collect();
+    println!("Sparse candidates (zero bytes that could be freed):");
+    for file in candidates.iter().take(count) {
+        println!(
+            "{:<60} {:>8.2} GB  zeros {:.2} GB ({:.0}%)  reclaimable {:.2} GB",
+            file.path,
+            gb(file.size),
+            gb(file.zero_bytes),
+            file.zero_bytes as f64 / file.size.max(1) as f64 * 100.0,
+            gb(file.reclaimable())
+        );
+    }
+
+    let sparse: Vec<&ZeroRegionFile> = results.iter().filter(|r| r.sparse).collect();
+    println!("\nAlready sparse:");
+    for file in sparse.iter().take(count) {
+        println!(
+            "{:<60} {:>8.2} GB  allocated {:.2} GB",
+            file.path,
+            gb(file.size),
+            gb(file.allocated)
+        );
+    }
+
+    println!(
+        "\n{} sparse candidates, {:.2} GB reclaimable. {} files already sparse, saving {:.2} GB. {} unreadable",
+        candidates.len(),
+        gb(candidates.iter().map(|r| r.reclaimable()).sum()),
+        sparse.len(),
+        gb(sparse.iter().map(|r| r.size.saturating_sub(r.allocated)).sum()),
+        unreadable
+    );
+}
+
+#[cfg(test)]
+mod zero_regions_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+
+    #[test]
+    fn test_zero_blocks() {
+        let mut data = vec![0u8; ZERO_BLOCK * 3 + 100];
+        data[ZERO_BLOCK + 5] = 1;
+        // the trailing 100 bytes don't make a whole block
+        assert_eq!(count_zero_blocks(&data), 2);
+
+        let dir = ScratchDir::new("zeros");
+        let path = dir.join("zeros.bin");
+        fs::write(&path, &data).unwrap();
+        assert_eq!(zero_bytes(&path, &mut Throttle::new(None)).unwrap(), 2 * ZERO_BLOCK as u64);
+    }
+
+    #[test]
+    fn test_zero_region_args() {
+        let words = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let args = ZeroRegionArgs::parse(&words("d -min-size 1gb -throttle 50")).unwrap();
+        assert_eq!(args.min_size, 1_073_741_824);
+        assert_eq!(args.throttle, Some(50 * 1_048_576));
+        assert!(ZeroRegionArgs::parse(&words("d -throttle 0")).is_err());
+        assert!(ZeroRegionArgs::parse(&words("-throttle 5")).is_err());
+    }
+}
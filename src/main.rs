@@ -4,6 +4,12 @@ mod utility;
 use crate::utility::{constants::*, funzy::display_boot_sequence};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // a command on the command line runs on its own, no boot sequence and no shell
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = shell::run_from_args(&args) {
+        std::process::exit(code);
+    }
+
     #[cfg(debug_assertions)]
     {
         println!("--- WARNING :: DEV PROFILE ACTIVE ---");
@@ -15,9 +21,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // where the main code will run
         display_boot_sequence();
 
-        shell::ntfs_bash_commands();
+        shell::run_shell();
     }
-    // shell::ntfs_bash_commands();
 
     Ok(())
 }
@@ -0,0 +1,77 @@
+// what the shell's commands run against. the storage analyser walks the folders and works
+// anywhere; the NTFS explorer reads the MFT instead, which is faster. the commands only the
+// MFT knows about (deleted files, fragmentation and the like) read it raw with either one
+use super::registry::{CommandError, CommandResult};
+use crate::analyser::{
+    StorageAnalyser,
+    attributes::AttributeFilter,
+    ignore::ScanFilter,
+    listing::ListingOptions,
+    mft_reports,
+    query::Query,
+    search::SearchQuery,
+};
+use crate::utility::output::OutputFormat;
+
+/// One way of answering the shell's analysis commands. Drives are passed on as typed,
+/// the backend checks them. Commands a backend can't run say so instead of doing nothing.
+pub trait Backend {
+    /// the include/exclude patterns and attribute filter of the command about to run
+    fn set_filters(&mut self, scan_filter: ScanFilter, attribute_filter: AttributeFilter);
+
+    /// fails when the backend can't print that format
+    fn set_output_format(&mut self, format: OutputFormat) -> Result<(), String>;
+
+    /// the folder walking analyser, for `all`, `check` and `perm-audit`
+    fn walker(&mut self) -> &mut StorageAnalyser;
+
+    fn drive_space(&mut self, drive: &str) -> CommandResult;
+    // the listings, filtered, sorted and cut the way `options` says
+    fn file_type_dist(&mut self, drive: &str, options: &ListingOptions) -> CommandResult;
+    fn largest_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult;
+    fn largest_folders(&mut self, drive: &str, options: &ListingOptions) -> CommandResult;
+    fn recent_large_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult;
+    fn old_large_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult;
+    fn full_analysis(&mut self, drive: &str) -> CommandResult;
+
+    fn empty_folders(&mut self, drive: &str, top: usize, junk_as_empty: bool) -> CommandResult;
+    /// the folders `empty-folders -delete` would remove
+    fn empty_folder_candidates(&mut self, drive: &str) -> CommandResult<Vec<String>>;
+    /// `also` leaves files out on top of the command's own attribute filter
+    fn query_file_paths(&mut self, drive: &str, query: &Query, also: AttributeFilter) -> CommandResult<Vec<String>>;
+
+    fn compression_estimate(&mut self, drive: &str, blocks: usize, top: usize) -> CommandResult;
+    fn zero_regions(&mut self, drive: &str, min_size: u64, throttle: Option<u64>, top: usize) -> CommandResult;
+    fn attributes(&mut self, target: &str) -> CommandResult;
+    fn links(&mut self, target: &str, follow: bool, top: usize) -> CommandResult;
+    fn scan_errors(&mut self, drive: &str, top: usize) -> CommandResult;
+
+    /// every scanned drive without one
+    fn search(&mut self, drive: Option<&str>, query: &SearchQuery, top: usize) -> CommandResult;
+    fn query(&mut self, drive: &str, query: &Query, top: usize) -> CommandResult;
+    fn set_name_index(&mut self, drive: &str, enable: bool) -> CommandResult;
+    fn rescan(&mut self, drive: &str) -> CommandResult;
+
+    // -- what only the MFT knows -- //
+
+    // read from the raw volume or image by mft_reports, so every backend runs them the same way
+    fn deleted_files(&mut self, target: &str, top: usize) -> CommandResult {
+        Ok(mft_reports::print_deleted_files(target, top)?)
+    }
+
+    fn timestamp_anomalies(&mut self, target: &str, top: usize) -> CommandResult {
+        Ok(mft_reports::print_timestamp_anomalies(target, top)?)
+    }
+
+    fn fragmentation(&mut self, target: &str, top: usize) -> CommandResult {
+        Ok(mft_reports::print_fragmentation(target, top)?)
+    }
+
+    fn cluster_map(&mut self, target: &str, rows: usize) -> CommandResult {
+        Ok(mft_reports::print_cluster_map(target, rows)?)
+    }
+
+    fn path_issues(&mut self, target: &str, top: usize) -> CommandResult {
+        Ok(mft_reports::print_path_issues(target, top)?)
+    }
+}
@@ -0,0 +1,576 @@
+// every command the shell knows: its name, usage line, help text and handler, in the order
+// `help` lists them. a new command only needs an entry here and, if it analyses something,
+// a method on `Backend`
+use super::backend::Backend;
+use super::registry::{Args, Builtin, CommandError, CommandResult, Registry};
+use super::types::*;
+use crate::analyser::{
+    attributes::AttributeFilter,
+    compression::DEFAULT_SAMPLE_BLOCKS,
+    health_check::{CHECK_CONFIG_FILE, CheckConfig, print_check_report},
+    listing::ListingOptions,
+    query::{QUERY_USAGE, Query},
+    search::SearchQuery,
+    storage::DriveReport,
+    zero_regions::ZeroRegionArgs,
+};
+use crate::utility::dedupe::{DedupeArgs, dedupe_files, find_duplicates, undo_last_dedupe};
+use crate::utility::folder_cleanup::{delete_empty_folders, undo_last_deletion};
+use crate::utility::output::{alert, note};
+use crate::utility::progress::INCOMPLETE_RESULTS;
+use crate::utility::quarantine::{ARCHIVE_TOP, ArchiveArgs, archive_files, restore_archive};
+use crate::utility::utils::{app_file, time_command};
+use lazy_static::lazy_static;
+use std::{env, path::{Path, PathBuf}, process};
+
+/// rows of the cluster map, it's a picture rather than a listing so `--top` doesn't apply
+const CLUSTER_MAP_ROWS: usize = 16;
+
+macro_rules! add_command {
+    ($reg:ident, $name:expr, title: $title:expr, cmd_args: $args:expr, description: $desc:expr,
+     $(json: $json:expr,)? $(listing: $listing:expr,)? run: $run:expr $(,)?) => {{
+        $reg.register(Builtin {
+            name: $name,
+            info: CommandInfo {
+                title: $title,
+                cmd_args: $args,
+                description: $desc,
+            },
+            json: add_command!(@flag $($json)?),
+            listing: add_command!(@flag $($listing)?),
+            handler: $run,
+        });
+    }};
+    (@flag) => { false };
+    (@flag $flag:expr) => { $flag };
+}
+
+// help entries for things that aren't commands of their own
+macro_rules! add_topic {
+    ($reg:ident, $name:expr, title: $title:expr, cmd_args: $args:expr, description: $desc:expr $(,)?) => {{
+        $reg.register_topic($name, CommandInfo {
+            title: $title,
+            cmd_args: $args,
+            description: $desc,
+        });
+    }};
+}
+
+lazy_static! {
+    pub static ref COMMANDS: Registry = {
+        let mut m = Registry::default();
+
+        add_command!{
+            m, "help",
+            title      : "Help",
+            cmd_args   : "help [cmd name]",
+            description: "Displays all commands descriptions \n\
+                        if an argument is given, it gives the command description of the said argument",
+            run        : help,
+        }
+        add_command!{
+            m, "exit",
+            title      : "Exit",
+            cmd_args   : "exit [exit code]",
+            description: "hey, you, yes you, if you can read this and understand it, \n\
+                        then there is no need for an explanation of what this command does",
+            run        : exit,
+        }
+        add_command!{
+            m, "echo",
+            title      : "Echo",
+            cmd_args   : "echo [string]",
+            description: "Repeats what you say, probably",
+            run        : echo,
+        }
+        add_command!{
+            m, "type",
+            title      : "Type",
+            cmd_args   : "type [cmd name]",
+            description: "It just tells you if the command exists, and how it's used if it does",
+            run        : type_command,
+        }
+        add_command!{
+            m, "pwd",
+            title      : "pwd",
+            cmd_args   : "pwd",
+            description: "Shows the location the program is ran in",
+            run        : pwd,
+        }
+        add_command!{
+            m, "drive-space",
+            title      : "Drive Space",
+            cmd_args   : "drive-space [drive | all]",
+            description: "Shows the amount of space in a drive, what else do you want?",
+            json       : true,
+            run        : |backend, args| listing(backend, args, DriveReport::DriveSpace, |backend, drive, _| backend.drive_space(drive)),
+        }
+        add_command!{
+            m, "file-type-dist",
+            title      : "File Type Distribution",
+            cmd_args   : "file-type-dist [drive | all] [listing flags]",
+            description: "Shows the distribution of the file formats taking the largest space \n\
+                        sizes are per format (10 MB and up by default), --path picks which files are counted",
+            json       : true,
+            listing    : true,
+            run        : |backend, args| listing(backend, args, DriveReport::FileTypes, |backend, drive, options| backend.file_type_dist(drive, options)),
+        }
+        add_command!{
+            m, "largest-files",
+            title      : "Largest Files",
+            cmd_args   : "largest-files [drive | all] [listing flags]",
+            description: "Shows the largest files, the top 20 unless given --top",
+            json       : true,
+            listing    : true,
+            run        : |backend, args| listing(backend, args, DriveReport::LargestFiles, |backend, drive, options| backend.largest_files(drive, options)),
+        }
+        add_command!{
+            m, "largest-folder",
+            title      : "Largest Folder",
+            cmd_args   : "largest-folder [drive | all] [listing flags]",
+            description: "Shows the largest folders up to 3 levels deep (100 MB and up by default)",
+            json       : true,
+            listing    : true,
+            run        : |backend, args| listing(backend, args, DriveReport::LargestFolders, |backend, drive, options| backend.largest_folders(drive, options)),
+        }
+        add_command!{
+            m, "recent-large-files",
+            title      : "Recent Large Files",
+            cmd_args   : "recent-large-files [drive | all] [listing flags]",
+            description: "Shows most recent files within last 30 days (or --age) that are large",
+            json       : true,
+            listing    : true,
+            run        : |backend, args| listing(backend, args, DriveReport::RecentLargeFiles, |backend, drive, options| backend.recent_large_files(drive, options)),
+        }
+        add_command!{
+            m, "old-large-files",
+            title      : "Old Large Files",
+            cmd_args   : "old-large-files [drive | all] [listing flags]",
+            description: "Shows older than 6 months (or --age) files that are your m- i mean large",
+            json       : true,
+            listing    : true,
+            run        : |backend, args| listing(backend, args, DriveReport::OldLargeFiles, |backend, drive, options| backend.old_large_files(drive, options)),
+        }
+        add_command!{
+            m, "full-drive-analysis",
+            title      : "Full Drive Analysis",
+            cmd_args   : "full-drive-analysis [drive | all]",
+            description: "cant you read? \n\
+                        all instead of a drive scans every fixed drive in parallel, then shows each drive, \n\
+                        one ranking across all of them and the machine's total/used/free space \n\
+                        (works the same for drive-space, file-type-dist, largest-*, recent- and old-large-files)",
+            run        : |backend, args| listing(backend, args, DriveReport::FullAnalysis, |backend, drive, _| backend.full_analysis(drive)),
+        }
+        add_command!{
+            m, "empty-folders",
+            title      : "Empty Folders",
+            cmd_args   : "empty-folders [drive] [-junk] [-delete [-dry-run]]",
+            description: "searches for empty folders and lists them all\
+                        (not all empty folders should be deleted) \n\
+                        folders holding only empty folders are shown once at the top with a nested count, \n\
+                        -junk also counts folders with only zero-byte files or Thumbs.db/desktop.ini/.DS_Store \n\
+                        if you're not sure just search the folder path and see if you can delete it \n\
+                        -delete previews the list, asks for confirmation and journals what it removes",
+            run        : empty_folders,
+        }
+        add_command!{
+            m, "undo",
+            title      : "Undo",
+            cmd_args   : "undo",
+            description: "Recreates the folders removed by the last empty-folders -delete, with their original timestamps",
+            run        : |_, _| Ok(undo_last_deletion()?),
+        }
+        add_command!{
+            m, "archive",
+            title      : "Archive",
+            cmd_args   : "archive <old-large-files | recent-large-files | largest-files> [drive] [--top N] [-to dir] [-pack]",
+            description: "Moves the top N files of a result set (default 10) into outputs/quarantine, or -to another folder \n\
+                        -pack puts them in a compressed tar instead, every file is hashed into a manifest for restore",
+            run        : archive,
+        }
+        add_command!{
+            m, "restore",
+            title      : "Restore",
+            cmd_args   : "restore [session folder]",
+            description: "Puts the files of an archive session back with their timestamps, the newest session by default",
+            run        : restore,
+        }
+        add_command!{
+            m, "dedupe",
+            title      : "Dedupe",
+            cmd_args   : "dedupe [drive] [-min-size N] [-reflink] [-dry-run]  |  dedupe -undo",
+            description: "Finds files with identical contents (1 MB and up by default) and replaces the extra copies \n\
+                        with hard links, or copy-on-write clones with -reflink. -undo turns the last run back into copies",
+            run        : dedupe,
+        }
+        add_command!{
+            m, "deleted-files",
+            title      : "Deleted Files",
+            cmd_args   : "deleted-files [drive | image path]",
+            description: "Lists deleted files whose MFT records haven't been reused yet \n\
+                        and estimates if their data is still on disk (works on raw NTFS images too)",
+            run        : |backend, args| backend.deleted_files(args.target()?, args.top()),
+        }
+        add_command!{
+            m, "timestamp-anomalies",
+            title      : "Timestamp Anomalies",
+            cmd_args   : "timestamp-anomalies [drive | image path]",
+            description: "Compares the $STANDARD_INFORMATION and $FILE_NAME timestamps of every file \n\
+                        and flags signs of timestomping (SI older than FN, round seconds, 1601 dates, etc.)",
+            run        : |backend, args| backend.timestamp_anomalies(args.target()?, args.top()),
+        }
+        add_command!{
+            m, "fragmentation",
+            title      : "Fragmentation",
+            cmd_args   : "fragmentation [drive | image path]",
+            description: "Decodes the data runs of every file and shows the most fragmented files and folders \n\
+                        along with a volume-wide fragmentation score",
+            run        : |backend, args| backend.fragmentation(args.target()?, args.top()),
+        }
+        add_command!{
+            m, "cluster-map",
+            title      : "Cluster Map",
+            cmd_args   : "cluster-map [drive | image path]",
+            description: "Draws a map of allocated vs free clusters from $Bitmap \n\
+                        and shows the largest free region, free extent count and where the MFT sits",
+            run        : |backend, args| backend.cluster_map(args.target()?, CLUSTER_MAP_ROWS),
+        }
+        add_command!{
+            m, "path-issues",
+            title      : "Path Issues",
+            cmd_args   : "path-issues [drive | image path]",
+            description: "Lists names that break Win32 tools: paths over 260 characters, trailing dots or spaces, \n\
+                        reserved device names, invalid UTF-16, case-only collisions and control characters",
+            run        : |backend, args| backend.path_issues(args.target()?, args.top()),
+        }
+        add_command!{
+            m, "estimate-compression",
+            title      : "Estimate Compression",
+            cmd_args   : "estimate-compression [drive] [-blocks N]",
+            description: "Compresses 64 KB blocks sampled across the drive (500 by default) with lz4, deflate and zstd \n\
+                        and estimates the savings per codec, folder and file type with 95% bounds",
+            run        : estimate_compression,
+        }
+        add_command!{
+            m, "zero-regions",
+            title      : "Zero Regions",
+            cmd_args   : "zero-regions [drive] [-min-size N] [-throttle MB/s]",
+            description: "Reads files of 100 MB and up (or -min-size) for all-zero 64 KB blocks and shows what making them \n\
+                        sparse would free, along with the files that are sparse already. -throttle caps the read rate",
+            run        : zero_regions,
+        }
+        add_command!{
+            m, "perm-audit",
+            title      : "Permission Audit",
+            cmd_args   : "perm-audit [target]",
+            description: "Linux only. Lists world-writable paths without the sticky bit, setuid/setgid binaries, \n\
+                        files owned by users that don't exist and overly open files in home directories, with sizes",
+            run        : perm_audit,
+        }
+        add_command!{
+            m, "attributes",
+            title      : "Attributes",
+            cmd_args   : "attributes [target]",
+            description: "Counts entries and bytes per attribute: hidden, system, read-only, compressed, encrypted, \n\
+                        offline, temporary and not content indexed. Listings show everything, --exclude-hidden \n\
+                        leaves out hidden entries and --exclude-system system ones",
+            run        : attributes,
+        }
+        add_topic!{
+            m, "ignore",
+            title      : "Include / Exclude Patterns",
+            cmd_args   : "<command> [--exclude pattern] [--include pattern]",
+            description: "Gitignore-style patterns for any scan, on top of analyser.ignore and .analyserignore files. \n\
+                        Excluded folders aren't walked or counted, --include keeps only the files matching",
+        }
+        add_topic!{
+            m, "listing",
+            title      : "Listing Flags",
+            cmd_args   : "<listing> [--top N] [--min-size 500mb] [--max-size 2gb] [--sort size|name|modified|count] [--reverse] [--path folder] [--age 30d]",
+            description: "Work on file-type-dist, largest-files, largest-folder, recent- and old-large-files, on one drive or all. \n\
+                        --path keeps what's under a folder (c:/users, or /users on any drive), --age moves the window \n\
+                        of the recent and old listings. --top works on every command that lists something",
+        }
+        add_command!{
+            m, "links",
+            title      : "Links",
+            cmd_args   : "links [target] [-follow]",
+            description: "Lists symlinks, junctions, mount points, cloud placeholders and dedup reparse points with \n\
+                        their targets, flagging broken ones. -follow adds up what they point at outside the drive",
+            run        : links,
+        }
+        add_command!{
+            m, "scan-errors",
+            title      : "Scan Errors",
+            cmd_args   : "scan-errors [drive]",
+            description: "Lists what the last scan of a drive couldn't read, folders it couldn't enter first, \n\
+                        and how much of the used space the scan doesn't account for",
+            json       : true,
+            run        : scan_errors,
+        }
+        add_command!{
+            m, "check",
+            title      : "Check",
+            cmd_args   : "check [config]",
+            description: "Checks the rules in a config file (analyser.check by default): free space, folder size, \n\
+                        growth and new large files since the last check. Exits with 3 when any rule trips",
+            run        : check,
+        }
+        add_command!{
+            m, "find",
+            title      : "Find",
+            cmd_args   : "find <pattern> [drive] [-regex] [-size min..max] [-ext mp4,mkv] [-after date] [-before date] [-dirs]",
+            description: "Searches the cached scan for names matching a substring, glob (* and ?) or regex \n\
+                        patterns with a slash match the full path, without a drive it searches every scanned drive",
+            json       : true,
+            run        : find,
+        }
+        add_command!{
+            m, "index",
+            title      : "Name Index",
+            cmd_args   : "index [off] [drive]",
+            description: "Builds a trigram index of the drive's names so find only checks likely matches \n\
+                        reports its build time and memory use, kept up to date by rescan, off drops it",
+            run        : index,
+        }
+        add_command!{
+            m, "query",
+            title      : "Query",
+            cmd_args   : "query [drive] <expression> [order by <field> [asc|desc]] [limit N]",
+            description: "Filters the scan with an expression over size, name, path, ext, modified and type \n\
+                        e.g. query C \"size > 1GiB and ext in (mkv, mp4) and modified < -6mo\" order by size desc limit 50",
+            json       : true,
+            run        : query,
+        }
+        add_command!{
+            m, "rescan",
+            title      : "Rescan",
+            cmd_args   : "rescan [drive]",
+            description: "Throws away the cached scan of a drive and scans it again. \n\
+                        Ctrl+C during any scan stops it early, leaving its results marked incomplete",
+            run        : rescan,
+        }
+        m
+    };
+}
+
+// -- handlers, for the commands that need more than a line -- //
+
+fn help(_: &mut dyn Backend, args: &Args) -> CommandResult {
+    match args.words.first() {
+        Some(name) => COMMANDS.print_help(name),
+        None => {
+            COMMANDS.print_all_help();
+            Ok(())
+        }
+    }
+}
+
+fn exit(_: &mut dyn Backend, args: &Args) -> CommandResult {
+    match args.list()[..] {
+        [] => process::exit(0), // Default exit code if none provided
+        [code] => match code.parse::<i32>() {
+            Ok(code) => process::exit(code),
+            Err(_) => Err(CommandError::usage(format!("{} isn't an exit code", code))),
+        },
+        _ => Err(CommandError::usage("one exit code at most")),
+    }
+}
+
+fn echo(_: &mut dyn Backend, args: &Args) -> CommandResult {
+    if args.list()[..] == ["i", "am", "an", "idiot"] {
+        println!("you are an idiot")
+    } else {
+        println!("{}", args.words.join(" ")) // prints a newline if no arguments given
+    }
+    Ok(())
+}
+
+// like the shell builtin, says what each name is and fails if any of them isn't anything
+fn type_command(_: &mut dyn Backend, args: &Args) -> CommandResult {
+    if args.words.is_empty() {
+        return Err(CommandError::usage("command name required"));
+    }
+    let mut missing = Vec::new();
+    for name in &args.words {
+        if let Some(command) = COMMANDS.get(name) {
+            println!("{} is a command: {}", name, command.info().cmd_args);
+        } else if COMMANDS.topic(name).is_some() {
+            println!("{} is a help topic, not a command: help {}", name, name);
+        } else {
+            missing.push(format!("type: {}: not found", name));
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(CommandError::Failed(missing.join("\n").into()))
+    }
+}
+
+fn pwd(_: &mut dyn Backend, _: &Args) -> CommandResult {
+    let path = env::current_dir()
+        .map_err(|e| CommandError::Failed(format!("pwd: error getting current directory: {}", e).into()))?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+// `all` walks every fixed drive in parallel, reading several MFTs at once isn't any faster
+fn listing(
+    backend: &mut dyn Backend,
+    args: &Args,
+    report: DriveReport,
+    print: fn(&mut dyn Backend, &str, &ListingOptions) -> CommandResult,
+) -> CommandResult {
+    match args.drive()? {
+        // one listing per drive and a merged one don't make a single json value
+        "all" if backend.walker().json() => Err(CommandError::usage("all has no json output, name a drive instead")),
+        "all" => Ok(time_command(|| backend.walker().print_all_drives(report, &args.listing))?),
+        drive => print(backend, drive, &args.listing),
+    }
+}
+
+fn empty_folders(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let drive = args.drive()?;
+    if let Some(other) = args.list()[1..].iter().find(|word| !["-junk", "-delete", "-dry-run"].contains(word)) {
+        return Err(CommandError::usage(format!("unknown option '{}'", other)));
+    }
+    if args.has("-delete") {
+        let folders = backend.empty_folder_candidates(drive)?;
+        Ok(delete_empty_folders(&folders, args.has("-dry-run"))?)
+    } else {
+        backend.empty_folders(drive, args.top(), args.has("-junk"))
+    }
+}
+
+// raw words again, so a `-to` folder keeps its case
+fn archive(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let words: Vec<&str> = args.raw.iter().map(String::as_str).collect();
+    let archive = ArchiveArgs::parse(&words).map_err(CommandError::Usage)?;
+    let mut query = Query::parse(archive.query).expect("built-in query should parse");
+    query.limit = Some(args.listing.top_or(ARCHIVE_TOP));
+    // pagefile.sys, hiberfil.sys and friends top every size listing but are never worth moving
+    let paths = backend.query_file_paths(&archive.drive, &query, AttributeFilter::NO_HIDDEN_OR_SYSTEM)?;
+    Ok(archive_files(&paths, &archive.destination, archive.pack)?)
+}
+
+fn restore(_: &mut dyn Backend, args: &Args) -> CommandResult {
+    let session = args.raw_from(0);
+    let session = (!session.is_empty()).then(|| Path::new(&session));
+    Ok(restore_archive(session)?)
+}
+
+fn dedupe(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    if args.list()[..] == ["-undo"] {
+        return Ok(undo_last_dedupe()?);
+    }
+    let dedupe = DedupeArgs::parse(&args.words).map_err(CommandError::Usage)?;
+    let query = Query::parse(&format!("type = file and size >= {}", dedupe.min_size))
+        .expect("built-in query should parse");
+    let paths = backend.query_file_paths(&dedupe.drive, &query, AttributeFilter::default())?;
+    let (groups, complete) = find_duplicates(paths, dedupe.min_size);
+    if !complete {
+        // a partial search only gets previewed, replacing wasn't what Ctrl+C asked for
+        note(INCOMPLETE_RESULTS);
+    }
+    Ok(dedupe_files(&groups, dedupe.mode, dedupe.dry_run || !complete)?)
+}
+
+fn estimate_compression(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let drive = args.drive()?;
+    let blocks = match args.list()[1..] {
+        [] => DEFAULT_SAMPLE_BLOCKS,
+        ["-blocks", n] => n
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| CommandError::usage("-blocks needs a number above 0"))?,
+        _ => return Err(CommandError::usage("unexpected arguments")),
+    };
+    backend.compression_estimate(drive, blocks, args.top())
+}
+
+fn zero_regions(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let zero = ZeroRegionArgs::parse(&args.words).map_err(CommandError::Usage)?;
+    backend.zero_regions(&zero.drive, zero.min_size, zero.throttle, args.top())
+}
+
+// paths are case sensitive on Linux, so the target comes from the raw input
+fn perm_audit(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    match args.raw_from(0).as_str() {
+        "" => Err(CommandError::usage("target path required")),
+        target => Ok(backend.walker().print_perm_audit(target)?),
+    }
+}
+
+// image paths keep their case, so the target comes from the raw input
+fn attributes(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    match args.list()[..] {
+        [_] => backend.attributes(args.raw_word(0).unwrap_or_default()),
+        _ => Err(CommandError::usage("target required")),
+    }
+}
+
+fn links(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let follow = match args.list()[..] {
+        [_] => false,
+        [_, "-follow"] => true,
+        _ => return Err(CommandError::usage("target required")),
+    };
+    backend.links(args.raw_word(0).unwrap_or_default(), follow, args.top())
+}
+
+fn scan_errors(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    match args.list()[..] {
+        [drive] => backend.scan_errors(drive, args.top()),
+        _ => Err(CommandError::usage("drive letter required")),
+    }
+}
+
+// drive space comes from the OS and the scan from the walker, the MFT isn't needed for either.
+// tripped rules raise the exit code so a scheduler can act on them, and a drive that couldn't
+// be scanned fails the check, since its rules were never really checked
+fn check(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let config = match args.raw_from(0).as_str() {
+        "" => app_file(CHECK_CONFIG_FILE),
+        config => PathBuf::from(config),
+    };
+    let config = CheckConfig::load(&config).map_err(|e| CommandError::Failed(e.into()))?;
+    let report = backend.walker().run_check(&config)?;
+    print_check_report(&report);
+    if report.results.iter().any(|r| r.tripped) {
+        alert();
+    }
+    if !report.unscanned.is_empty() {
+        return Err(CommandError::Failed(format!("Couldn't scan {}", report.unscanned.join(", ")).into()));
+    }
+    Ok(())
+}
+
+fn find(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let (query, drive) = SearchQuery::from_args(&args.raw).map_err(CommandError::usage)?;
+    backend.search(drive.as_deref(), &query, args.top())
+}
+
+// the expression is re-read from the raw input so quoted values keep their spacing.
+// text comparisons ignore case either way, query.rs lowercases both sides
+fn query(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let Some(drive) = args.words.first() else {
+        return Err(CommandError::usage(format!("Drive letter required. {}", QUERY_USAGE)));
+    };
+    let query = Query::parse(&args.raw_from(1)).map_err(|e| CommandError::usage(format!("{}. {}", e, QUERY_USAGE)))?;
+    backend.query(drive, &query, args.top())
+}
+
+fn index(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    match args.list()[..] {
+        ["off", drive] => backend.set_name_index(drive, false),
+        [drive] => backend.set_name_index(drive, true),
+        _ => Err(CommandError::usage("drive letter required")),
+    }
+}
+
+fn rescan(backend: &mut dyn Backend, args: &Args) -> CommandResult {
+    let drive = args.drive()?;
+    time_command(|| backend.rescan(drive))
+}
@@ -0,0 +1,62 @@
+// running a single command straight from the command line, for scripts and scheduled jobs.
+//
+// `rusty-analyser largest-files C --top 50 --format json` runs exactly what typing
+// `largest-files C --top 50 --format json` into the shell does, with stdout holding only the
+// results and the exit code saying how it went (see `utility::output`)
+use super::backend::Backend;
+use super::builtins::COMMANDS;
+#[cfg(target_os = "windows")]
+use super::ntfs_commands::NtfsBackend;
+use super::registry::Shell;
+use crate::analyser::listing::TOP_FLAG;
+use crate::utility::{
+    constants::VERSION,
+    output::{EXIT_OK, FORMAT_FLAG, set_results_only, take_status},
+    progress::install_interrupt_handler,
+};
+
+const USAGE: &str = "Usage: rusty-analyser [command] [args..] [--top N] [--format text|json]";
+
+/// Runs the command given on the command line and returns the exit code,
+/// `None` when there's no command so the interactive shell starts instead.
+pub fn run_from_args(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        None => None,
+        Some("-h" | "--help") => {
+            println!("{}\n", USAGE);
+            println!("Without a command the interactive shell starts. Every shell command works here too,");
+            println!("{} and {} (json for: {}) work on any of them.", TOP_FLAG, FORMAT_FLAG, COMMANDS.json_commands().join(", "));
+            println!("Listings also take --min-size, --max-size, --sort, --reverse, --path and --age (see the listing topic below).");
+            println!("Exit codes: 0 ok, 1 the command failed, 2 usage error, 3 a check rule tripped, 130 cancelled with Ctrl+C.");
+            COMMANDS.print_all_help();
+            Some(EXIT_OK)
+        }
+        Some("-v" | "--version") => {
+            println!("rusty-analyser {}", VERSION);
+            Some(EXIT_OK)
+        }
+        Some(_) => {
+            set_results_only(true);
+            install_interrupt_handler();
+            // the arguments go through as they are, so a quoted path with spaces stays one word
+            Shell::new(&COMMANDS, default_backend()).execute_words(args.to_vec());
+            Some(take_status())
+        }
+    }
+}
+
+/// Starts the interactive shell, on the NTFS explorer where there is one and the folder walker elsewhere.
+pub fn run_shell() {
+    Shell::new(&COMMANDS, default_backend()).run();
+}
+
+/// the backend both the shell and one-off commands run on
+#[cfg(target_os = "windows")]
+fn default_backend() -> Box<dyn Backend> {
+    Box::new(NtfsBackend::new())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn default_backend() -> Box<dyn Backend> {
+    Box::new(crate::analyser::StorageAnalyser::new())
+}
@@ -1,205 +1,126 @@
-use super::help_cmd::*;
+use super::backend::Backend;
+use super::registry::{CommandError, CommandResult};
 use crate::analyser::StorageAnalyser;
-use crate::analyser::ntfs_explorer;
-use crate::utility::utils::{save_empty_folders_to_file, time_command, validate_and_format_drive};
-use colored::Colorize;
-use std::{
-    env,
-    io::{self, Write},
-    process,
-};
-use whoami::fallible;
-
-macro_rules! vfd {
-    // pattern to accept an arbitrary closure block
-    ($drive:expr, $action:expr) => {
-        validate_and_format_drive($drive, $action)
-    };
-    // pattern for the simpler case of passing an instance and method name
-    ($drive:expr, $instance:expr, $method:ident) => {
-        validate_and_format_drive($drive, |d| $instance.$method(d))
-    };
+use crate::analyser::attributes::AttributeFilter;
+use crate::analyser::empty_tree::tree_summary;
+use crate::analyser::ignore::ScanFilter;
+use crate::analyser::listing::ListingOptions;
+use crate::analyser::query::Query;
+use crate::analyser::search::SearchQuery;
+use crate::utility::output::{OutputFormat, fail};
+use crate::utility::utils::{INVALID_DRIVE, format_drive, save_empty_folders_to_file, time_command};
+
+/// the drive as the storage analyser keys it ("C:/")
+pub(super) fn drive_path(drive: &str) -> CommandResult<String> {
+    format_drive(drive).ok_or_else(|| CommandError::usage(INVALID_DRIVE))
 }
 
-fn prompter_fn() {
-    let _user: String = whoami::username();
-    let _host: String = fallible::hostname().unwrap();
-    let prompt: String = format!(
-        "\n{}{}{}\n{} ",
-        _user.bright_green(),
-        "@".bright_white(),
-        _host.bright_blue(),
-        "$".bright_cyan()
-    );
-    print!("{}", prompt);
-    io::stdout().flush().unwrap();
-}
+impl Backend for StorageAnalyser {
+    fn set_filters(&mut self, scan_filter: ScanFilter, attribute_filter: AttributeFilter) {
+        self.set_scan_filter(scan_filter);
+        self.set_attribute_filter(attribute_filter);
+    }
 
-fn print_command_help(command: &String) {
-    if let Some(info) = COMMAND_DESCRIPTIONS.get(command.as_str()) {
-        print!(
-            "\n\
-            {}\n\
-            {}\n\
-            {}\n",
-            //info.title.bright_white(),
-            info.cmd_args.bright_blue(),
-            "-------------".green().bold(),
-            info.description
-        );
-    } else {
-        println!("Command not found: {}", command);
+    fn set_output_format(&mut self, format: OutputFormat) -> Result<(), String> {
+        StorageAnalyser::set_output_format(self, format);
+        Ok(())
     }
-}
 
-fn print_all_help() {
-    // for if I want to sort it alphabetically (probably still works, probably) :
-    // let mut commands: Vec<_> = COMMAND_DESCRIPTIONS.iter().collect();
-    // commands.sort_by_key(|(cmd, _)| *cmd);
-
-    for (_, info) in COMMAND_DESCRIPTIONS.iter() {
-        print!(
-            "\n\
-            {}\n\
-            {}\n\
-            {}\n",
-            //info.title.bright_white(),
-            info.cmd_args.bright_blue(),
-            "-------------".green().bold(),
-            info.description
-        );
-        println!(); // add an extra newline between commands
+    fn walker(&mut self) -> &mut StorageAnalyser {
+        self
+    }
+
+    fn drive_space(&mut self, drive: &str) -> CommandResult {
+        Ok(self.print_drive_space_overview(&drive_path(drive)?)?)
+    }
+
+    fn file_type_dist(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        let drive = drive_path(drive)?;
+        Ok(time_command(|| self.print_file_type_distribution(&drive, options))?)
+    }
+
+    fn largest_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.print_largest_files(&drive_path(drive)?, options)?)
     }
-}
 
-pub fn bash_commands() {
-    prompter_fn();
-
-    // wait for user input
-    let stdin = io::stdin();
-    let mut input = String::new();
-    let mut analyser: StorageAnalyser = StorageAnalyser::new();
-    loop {
-        stdin.read_line(&mut input).unwrap();
-        let command: Vec<String> = input
-            .trim()
-            .split_whitespace()
-            .map(|s| s.to_lowercase())
-            .collect();
-
-        if command.is_empty() {
-            input.clear();
-            prompter_fn();
-            continue;
+    fn largest_folders(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.print_largest_folders(&drive_path(drive)?, options)?)
+    }
+
+    fn recent_large_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.print_recent_large_files(&drive_path(drive)?, options)?)
+    }
+
+    fn old_large_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.print_old_large_files(&drive_path(drive)?, options)?)
+    }
+
+    fn full_analysis(&mut self, drive: &str) -> CommandResult {
+        Ok(self.analyze_drive(&drive_path(drive)?)?)
+    }
+
+    fn empty_folders(&mut self, drive: &str, _top: usize, junk_as_empty: bool) -> CommandResult {
+        let trees = self.get_empty_folder_trees(&drive_path(drive)?, junk_as_empty)?;
+        let empty_folders: Vec<String> = trees.iter().map(tree_summary).collect();
+        println!("Found {} empty folder trees.", empty_folders.len());
+        for folder in &empty_folders {
+            println!(" - {}", folder);
         }
+        save_empty_folders_to_file(&empty_folders)?;
+        Ok(())
+    }
 
-        match command.iter().map(|s| s.as_str()).collect::<Vec<_>>()[..] {
-            // some default commands
-            ["exit", ..] => match command.get(1) {
-                Some(code) => process::exit(code.parse::<i32>().unwrap()),
-                None => process::exit(0), // Default exit code if none provided
-            },
-            ["echo", ..] => match command.get(1..) {
-                Some(words) => {
-                    if words == ["i", "am", "an", "idiot"] {
-                        println!("you are an idiot")
-                    } else {
-                        println!("{}", words.join(" "))
-                    }
-                }
-                None => println!(), // prints a newline if no arguments given
-            },
-            ["pwd"] => match env::current_dir() {
-                Ok(path) => println!("{}", path.display()),
-                Err(e) => println!("pwd: error getting current directory: {}", e),
-            },
-            ["help", ..] => match command.get(1) {
-                Some(cword) => print_command_help(cword),
-                None => print_all_help(),
-            },
-
-            // drive analysis commands
-            ["drive-space", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, analyser, print_drive_space_overview),
-                None => println!(
-                    "drive letter required. Usage: drive-space [drive]"),
-            },
-
-            ["file-type-dist", ..] => match command.get(1) {
-                Some(drive) => time_command(|| vfd!(drive, analyser, print_file_type_distribution)),
-                None => println!(
-                    "drive letter required. Usage: file-type-dist [drive]"),
-            },
-
-            ["largest-files", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, analyser, print_largest_files),
-                None => println!(
-                    "drive letter required. Usage: largest-files [drive]"),
-            },
-
-            ["largest-folder", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, analyser, print_largest_folders),
-                None => println!(
-                    "drive letter required. Usage: largest-folder [drive]"),
-            },
-
-            ["recent-large-files", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, analyser, print_recent_large_files),
-                None => println!(
-                    "drive letter required. Usage: recent-large-files [drive]"
-                ),
-            },
-
-            ["old-large-files", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, analyser, print_old_large_files),
-                None => println!(
-                    "drive letter required. Usage: old-large-files [drive]"
-                ),
-            },
-
-            ["full-drive-analysis", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, analyser, analyze_drive),
-                None => println!(
-                    "drive letter required. Usage: full-drive-analysis [drive]"),
-            },
-
-            ["empty-folders", ..] => {
-                if command.contains(&"-delete".to_string()) {
-                    // placeholder for later implementation
-                    println!("Deletion functionality for empty folders is not yet implemented.");
-                } else {
-                    match command.get(1) {
-                        Some(drive) => vfd!(drive, |d| {
-                            let empty_folders = analyser.get_empty_folders(d)?;
-                            println!("Found {} empty folders.", empty_folders.len());
-                            for folder in &empty_folders {
-                                println!(" - {}", folder);
-                            }
-                            save_empty_folders_to_file(&empty_folders)?;
-                            Ok(())
-                        }),
-                        None => println!(
-                            "drive letter required. Usage: empty-folders [drive]"),
-                    }
-                }
-            }
-
-            ["rescan", ..] => match command.get(1) {
-                Some(drive) => vfd!(drive, |d| {
-                    time_command(|| {
-                        analyser.rescan_drive(d)?;
-                        println!("Rescan complete for drive {}", d);
-                        Ok(())
-                    })
-                }),
-                None => println!("Drive letter required. Usage: rescan [drive]"),
-            },
-
-            _ => {
-                println!("{}: command not found", command[0]);
-            }
+    fn empty_folder_candidates(&mut self, drive: &str) -> CommandResult<Vec<String>> {
+        Ok(self.get_empty_folders(&drive_path(drive)?)?)
+    }
+
+    fn query_file_paths(&mut self, drive: &str, query: &Query, also: AttributeFilter) -> CommandResult<Vec<String>> {
+        Ok(StorageAnalyser::query_file_paths(self, &drive_path(drive)?, query, also)?)
+    }
+
+    fn compression_estimate(&mut self, drive: &str, blocks: usize, top: usize) -> CommandResult {
+        Ok(self.print_compression_estimate(&drive_path(drive)?, blocks, top)?)
+    }
+
+    fn zero_regions(&mut self, drive: &str, min_size: u64, throttle: Option<u64>, top: usize) -> CommandResult {
+        Ok(self.print_zero_regions(&drive_path(drive)?, min_size, throttle, top)?)
+    }
+
+    fn attributes(&mut self, target: &str) -> CommandResult {
+        Ok(self.print_attributes(&drive_path(target)?)?)
+    }
+
+    fn links(&mut self, target: &str, follow: bool, top: usize) -> CommandResult {
+        Ok(self.print_links(&drive_path(target)?, follow, top)?)
+    }
+
+    fn scan_errors(&mut self, drive: &str, top: usize) -> CommandResult {
+        Ok(self.print_scan_errors(&drive_path(drive)?, top)?)
+    }
+
+    fn search(&mut self, drive: Option<&str>, query: &SearchQuery, top: usize) -> CommandResult {
+        let drives = match drive {
+            Some(drive) => vec![drive_path(drive)?],
+            None => self.cached_drives(),
+        };
+        if drives.is_empty() {
+            return Err(CommandError::usage("No drives scanned yet, give find a drive letter to scan first"));
         }
-        input.clear();
-        prompter_fn();
+        Ok(self.print_search_results(&drives, query, top)?)
+    }
+
+    fn query(&mut self, drive: &str, query: &Query, top: usize) -> CommandResult {
+        Ok(self.print_query_results(&drive_path(drive)?, query, top)?)
+    }
+
+    fn set_name_index(&mut self, drive: &str, enable: bool) -> CommandResult {
+        Ok(StorageAnalyser::set_name_index(self, &drive_path(drive)?, enable)?)
+    }
+
+    fn rescan(&mut self, drive: &str) -> CommandResult {
+        let drive = drive_path(drive)?;
+        self.rescan_drive(&drive)?;
+        println!("Rescan complete for drive {}", drive);
+        Ok(())
     }
 }
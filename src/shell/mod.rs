@@ -1,8 +1,11 @@
 #![allow(unused_imports)]
+pub mod backend;
+pub mod builtins;
+pub mod cli;
 pub mod commands;
-pub mod help_cmd;
+pub mod registry;
 pub mod types;
+#[cfg(target_os = "windows")]
 mod ntfs_commands;
 
-pub use commands::bash_commands;
-pub use ntfs_commands::ntfs_bash_commands;
\ No newline at end of file
+pub use cli::{run_from_args, run_shell};
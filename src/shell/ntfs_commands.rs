@@ -1,177 +1,136 @@
-use super::help_cmd::*;
-use crate::analyser::ntfs_explorer::NtfsExplorer;
-use crate::utility::utils::{save_empty_folders_to_file, time_command, validate_and_format_drive};
-use colored::Colorize;
-use std::{
-    env,
-    io::{self, Write},
-    process,
+use super::backend::Backend;
+use super::commands::drive_path;
+use super::registry::CommandResult;
+use crate::analyser::{
+    StorageAnalyser,
+    attributes::AttributeFilter,
+    ignore::ScanFilter,
+    listing::ListingOptions,
+    ntfs_explorer::NtfsExplorer,
+    query::Query,
+    search::SearchQuery,
 };
-use whoami::fallible;
-
-fn prompter_fn() {
-    let _user: String = whoami::username();
-    let _host: String = fallible::hostname().unwrap();
-    let prompt: String = format!(
-        "\n{}{}{}\n{} ",
-        _user.bright_green(),
-        "@".bright_white(),
-        _host.bright_blue(),
-        "$".bright_cyan()
-    );
-    print!("{}", prompt);
-    io::stdout().flush().unwrap();
+use crate::utility::output::OutputFormat;
+
+/// The MFT reading explorer, with a folder walker on the side for what the MFT can't answer.
+pub(super) struct NtfsBackend {
+    explorer: NtfsExplorer,
+    // the MFT has no Unix permissions, perm-audit walks the tree like the storage shell does
+    walker: Option<StorageAnalyser>,
+    scan_filter: ScanFilter,
+    attribute_filter: AttributeFilter,
+    output_format: OutputFormat,
 }
 
-fn print_command_help(command: &String) {
-    if let Some(info) = COMMAND_DESCRIPTIONS.get(command.as_str()) {
-        print!(
-            "\n\
-            {}\n\
-            {}\n\
-            {}\n",
-            //info.title.bright_white(),
-            info.cmd_args.bright_blue(),
-            "-------------".green().bold(),
-            info.description
-        );
-    } else {
-        println!("Command not found: {}", command);
+impl NtfsBackend {
+    pub(super) fn new() -> Self {
+        NtfsBackend {
+            explorer: NtfsExplorer::new(),
+            walker: None,
+            scan_filter: ScanFilter::default(),
+            attribute_filter: AttributeFilter::default(),
+            output_format: OutputFormat::default(),
+        }
     }
 }
 
-fn print_all_help() {
-    // for if I want to sort it alphabetically (probably still works, probably) :
-    // let mut commands: Vec<_> = COMMAND_DESCRIPTIONS.iter().collect();
-    // commands.sort_by_key(|(cmd, _)| *cmd);
-
-    for (_, info) in COMMAND_DESCRIPTIONS.iter() {
-        print!(
-            "\n\
-            {}\n\
-            {}\n\
-            {}\n",
-            //info.title.bright_white(),
-            info.cmd_args.bright_blue(),
-            "-------------".green().bold(),
-            info.description
-        );
-        println!(); // add an extra newline between commands
+impl Backend for NtfsBackend {
+    fn set_filters(&mut self, scan_filter: ScanFilter, attribute_filter: AttributeFilter) {
+        self.explorer.set_scan_filter(scan_filter.clone());
+        self.explorer.set_attribute_filter(attribute_filter);
+        self.scan_filter = scan_filter;
+        self.attribute_filter = attribute_filter;
     }
-}
 
-pub fn ntfs_bash_commands() {
-    prompter_fn();
-
-    // wait for user input
-    let stdin = io::stdin();
-    let mut input = String::new();
-    let explorer = NtfsExplorer::new();
-    loop {
-        stdin.read_line(&mut input).unwrap();
-        let command: Vec<String> = input
-            .trim()
-            .split_whitespace()
-            .map(|s| s.to_lowercase())
-            .collect();
-
-        if command.is_empty() {
-            input.clear();
-            prompter_fn();
-            continue;
-        }
+    fn set_output_format(&mut self, format: OutputFormat) -> Result<(), String> {
+        self.explorer.set_output_format(format);
+        self.output_format = format;
+        Ok(())
+    }
 
-        match command.iter().map(|s| s.as_str()).collect::<Vec<_>>()[..] {
-            // some default commands
-            ["exit", ..] => match command.get(1) {
-                Some(code) => process::exit(code.parse::<i32>().unwrap()),
-                None => process::exit(0), // Default exit code if none provided
-            },
-            ["echo", ..] => match command.get(1..) {
-                Some(words) => {
-                    if words == ["i", "am", "an", "idiot"] {
-                        println!("you are an idiot")
-                    } else {
-                        println!("{}", words.join(" "))
-                    }
-                }
-                None => println!(), // prints a newline if no arguments given
-            },
-            ["pwd"] => match env::current_dir() {
-                Ok(path) => println!("{}", path.display()),
-                Err(e) => println!("pwd: error getting current directory: {}", e),
-            },
-            ["help", ..] => match command.get(1) {
-                Some(cword) => print_command_help(cword),
-                None => print_all_help(),
-            },
-
-            // drive analysis commands
-            ["drive-space", ..] => match command.get(1) {
-                Some(drive) => match explorer.print_drive_space(drive) {
-                    Ok(()) => { /* all good, nothing else to do, or is there? */ }
-                    Err(e) => { eprintln!("{}", e) },
-                },
-                None => println!(
-                    "drive letter required. Usage: drive-space [drive]"),
-            },
-
-            ["file-type-dist", ..] => match command.get(1) {
-                Some(drive) => explorer.print_file_type_dist(drive, 20).unwrap(),
-                None => println!(
-                    "drive letter required. Usage: file-type-dist [drive]"),
-            },
-
-            ["largest-files", ..] => match command.get(1) {
-                Some(drive) => explorer.print_largest_files(drive, 20).unwrap(),
-                None => println!(
-                    "drive letter required. Usage: largest-files [drive]"),
-            },
-
-            ["largest-folder", ..] => match command.get(1) {
-                Some(drive) => explorer.print_largest_folders(drive, 20).unwrap(),
-                None => println!(
-                    "drive letter required. Usage: largest-folder [drive]"),
-            },
-
-            ["recent-large-files", ..] => match command.get(1) {
-                Some(drive) => explorer.print_recent_large_files(drive, 20).unwrap(),
-                None => println!(
-                    "drive letter required. Usage: recent-large-files [drive]"
-                ),
-            },
-
-            ["old-large-files", ..] => match command.get(1) {
-                Some(drive) => explorer.print_old_large_files(drive, 20).unwrap(),
-                None => println!(
-                    "drive letter required. Usage: old-large-files [drive]"
-                ),
-            },
-
-            ["full-drive-analysis", ..] => match command.get(1) {
-                Some(_drive) => println!("not implemented yet even tho it takes only a few minutes"),
-                None => println!(
-                    "drive letter required. Usage: full-drive-analysis [drive]"),
-            },
-
-            ["empty-folders", ..] => {
-                if command.contains(&"-delete".to_string()) {
-                    // placeholder for later implementation
-                    println!("Deletion functionality for empty folders is not yet implemented.");
-                } else {
-                    match command.get(1) {
-                        Some(drive) => explorer.print_empty_folders(drive, 20).unwrap(),
-                        None => println!(
-                            "drive letter required. Usage: empty-folders [drive]"),
-                    }
-                }
-            }
-
-            _ => {
-                println!("{}: command not found", command[0]);
-            }
-        }
-        input.clear();
-        prompter_fn();
+    fn walker(&mut self) -> &mut StorageAnalyser {
+        let walker = self.walker.get_or_insert_with(StorageAnalyser::new);
+        walker.set_scan_filter(self.scan_filter.clone());
+        walker.set_attribute_filter(self.attribute_filter);
+        walker.set_output_format(self.output_format);
+        walker
+    }
+
+    fn drive_space(&mut self, drive: &str) -> CommandResult {
+        Ok(self.explorer.print_drive_space(drive)?)
+    }
+
+    fn file_type_dist(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.explorer.print_file_type_dist(drive, options)?)
+    }
+
+    fn largest_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.explorer.print_largest_files(drive, options)?)
+    }
+
+    fn largest_folders(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.explorer.print_largest_folders(drive, options)?)
+    }
+
+    fn recent_large_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.explorer.print_recent_large_files(drive, options)?)
+    }
+
+    fn old_large_files(&mut self, drive: &str, options: &ListingOptions) -> CommandResult {
+        Ok(self.explorer.print_old_large_files(drive, options)?)
+    }
+
+    // the walker's report covers the same listings, the MFT wouldn't add anything to it
+    fn full_analysis(&mut self, drive: &str) -> CommandResult {
+        let drive = drive_path(drive)?;
+        Ok(self.walker().analyze_drive(&drive)?)
+    }
+
+    fn empty_folders(&mut self, drive: &str, top: usize, junk_as_empty: bool) -> CommandResult {
+        Ok(self.explorer.print_empty_folders(drive, top, junk_as_empty)?)
+    }
+
+    fn empty_folder_candidates(&mut self, drive: &str) -> CommandResult<Vec<String>> {
+        Ok(self.explorer.empty_folder_candidates(drive)?)
+    }
+
+    fn query_file_paths(&mut self, drive: &str, query: &Query, also: AttributeFilter) -> CommandResult<Vec<String>> {
+        Ok(self.explorer.query_file_paths(drive, query, also)?)
+    }
+
+    fn compression_estimate(&mut self, drive: &str, blocks: usize, top: usize) -> CommandResult {
+        Ok(self.explorer.print_compression_estimate(drive, blocks, top)?)
+    }
+
+    fn zero_regions(&mut self, drive: &str, min_size: u64, throttle: Option<u64>, top: usize) -> CommandResult {
+        Ok(self.explorer.print_zero_regions(drive, min_size, throttle, top)?)
+    }
+
+    fn attributes(&mut self, target: &str) -> CommandResult {
+        Ok(self.explorer.print_attributes(target)?)
+    }
+
+    fn links(&mut self, target: &str, follow: bool, top: usize) -> CommandResult {
+        Ok(self.explorer.print_links(target, follow, top)?)
+    }
+
+    fn scan_errors(&mut self, drive: &str, top: usize) -> CommandResult {
+        Ok(self.explorer.print_scan_errors(drive, top)?)
+    }
+
+    fn search(&mut self, drive: Option<&str>, query: &SearchQuery, top: usize) -> CommandResult {
+        Ok(self.explorer.print_search(drive, query, top)?)
+    }
+
+    fn query(&mut self, drive: &str, query: &Query, top: usize) -> CommandResult {
+        Ok(self.explorer.print_query(drive, query, top)?)
+    }
+
+    fn set_name_index(&mut self, drive: &str, enable: bool) -> CommandResult {
+        Ok(self.explorer.set_name_index(drive, enable)?)
+    }
+
+    fn rescan(&mut self, drive: &str) -> CommandResult {
+        Ok(self.explorer.rescan_drive(drive)?)
     }
 }
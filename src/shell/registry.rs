@@ -0,0 +1,389 @@
+// the shell itself: one registry of commands that both backends plug into.
+//
+// every command says once what it's called, what it takes, what it does and how it runs
+// (see `builtins`), and help, `type`, usage errors and dispatch are all read from that.
+// the backend decides what the analysis commands run against, see `backend`
+use super::backend::Backend;
+use super::types::CommandInfo;
+use crate::analyser::{attributes::AttributeFilter, ignore::ScanFilter, listing::ListingOptions};
+use crate::utility::output::{OutputFormat, fail, take_status, usage};
+use crate::utility::progress::install_interrupt_handler;
+use crate::utility::utils::split_command_line;
+use colored::Colorize;
+use indexmap::IndexMap;
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Write},
+};
+use whoami::fallible;
+
+/// Why a command didn't do its thing.
+#[derive(Debug)]
+pub enum CommandError {
+    /// missing or wrong arguments, reported with the command's usage line
+    Usage(String),
+    /// the command ran into an error
+    Failed(Box<dyn Error>),
+}
+
+impl CommandError {
+    pub fn usage(message: impl Display) -> Self {
+        CommandError::Usage(message.to_string())
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(error: io::Error) -> Self {
+        CommandError::Failed(error.into())
+    }
+}
+
+impl From<Box<dyn Error>> for CommandError {
+    fn from(error: Box<dyn Error>) -> Self {
+        CommandError::Failed(error)
+    }
+}
+
+pub type CommandResult<T = ()> = Result<T, CommandError>;
+
+/// What a command was given, with the flags every command takes already out of it.
+pub struct Args {
+    /// the words after the command's name, lowercased
+    pub words: Vec<String>,
+    /// the same words as typed, for paths and values that keep their case
+    pub raw: Vec<String>,
+    /// `--top` for every command, and the size range, order and path of listings
+    pub listing: ListingOptions,
+}
+
+impl Args {
+    /// the words as `&str`s, to match on
+    pub fn list(&self) -> Vec<&str> {
+        self.words.iter().map(String::as_str).collect()
+    }
+
+    /// the first word that isn't a flag
+    pub fn drive(&self) -> CommandResult<&str> {
+        self.words
+            .first()
+            .filter(|word| !word.starts_with('-'))
+            .map(String::as_str)
+            .ok_or_else(|| CommandError::usage("drive letter required"))
+    }
+
+    /// the first word as typed, a drive letter or the path of a raw NTFS image
+    pub fn target(&self) -> CommandResult<&str> {
+        self.raw
+            .first()
+            .map(String::as_str)
+            .ok_or_else(|| CommandError::usage("drive letter or image path required"))
+    }
+
+    /// a word the way it was typed, for image paths and other case sensitive targets
+    pub fn raw_word(&self, index: usize) -> Option<&str> {
+        self.raw.get(index).map(String::as_str)
+    }
+
+    /// the words as typed from `index` on, joined back up with spaces
+    pub fn raw_from(&self, index: usize) -> String {
+        self.raw.get(index..).unwrap_or_default().join(" ")
+    }
+
+    pub fn has(&self, flag: &str) -> bool {
+        self.words.iter().any(|word| word == flag)
+    }
+
+    /// rows to show, `--top` or the default
+    pub fn top(&self) -> usize {
+        self.listing.top()
+    }
+}
+
+pub type Handler = fn(&mut dyn Backend, &Args) -> CommandResult;
+
+/// A shell command.
+pub trait Command: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// its title, usage line and description for `help`
+    fn info(&self) -> &CommandInfo;
+
+    /// whether `--format json` works with it
+    fn json(&self) -> bool {
+        false
+    }
+
+    /// whether it takes the listing flags beyond `--top` (`--min-size`, `--sort`, `--path`...)
+    fn listing(&self) -> bool {
+        false
+    }
+
+    fn run(&self, backend: &mut dyn Backend, args: &Args) -> CommandResult;
+}
+
+/// A command from the table in `builtins`.
+pub struct Builtin {
+    pub name: &'static str,
+    pub info: CommandInfo,
+    pub json: bool,
+    pub listing: bool,
+    pub handler: Handler,
+}
+
+impl Command for Builtin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn info(&self) -> &CommandInfo {
+        &self.info
+    }
+
+    fn json(&self) -> bool {
+        self.json
+    }
+
+    fn listing(&self) -> bool {
+        self.listing
+    }
+
+    fn run(&self, backend: &mut dyn Backend, args: &Args) -> CommandResult {
+        (self.handler)(backend, args)
+    }
+}
+
+/// Every command by name, in the order `help` lists them, plus help topics
+/// for things that aren't commands of their own (like the flags every command takes).
+#[derive(Default)]
+pub struct Registry {
+    commands: IndexMap<&'static str, Box<dyn Command>>,
+    topics: IndexMap<&'static str, CommandInfo>,
+}
+
+impl Registry {
+    pub fn register(&mut self, command: impl Command + 'static) {
+        let name = command.name();
+        assert!(
+            !self.commands.contains_key(name) && !self.topics.contains_key(name),
+            "{} registered twice",
+            name
+        );
+        self.commands.insert(name, Box::new(command));
+    }
+
+    pub fn register_topic(&mut self, name: &'static str, info: CommandInfo) {
+        assert!(!self.commands.contains_key(name) && !self.topics.contains_key(name), "{} registered twice", name);
+        self.topics.insert(name, info);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Command> {
+        self.commands.get(name).map(|command| command.as_ref())
+    }
+
+    pub fn topic(&self, name: &str) -> Option<&CommandInfo> {
+        self.topics.get(name)
+    }
+
+    /// the commands `--format json` works with
+    pub fn json_commands(&self) -> Vec<&'static str> {
+        self.commands.values().filter(|c| c.json()).map(|c| c.name()).collect()
+    }
+
+    /// the commands the listing flags work with
+    pub fn listing_commands(&self) -> Vec<&'static str> {
+        self.commands.values().filter(|c| c.listing()).map(|c| c.name()).collect()
+    }
+
+    /// Takes a command line apart, `None` when it's empty. The flags every command takes come
+    /// out of the words as typed first, so neither `words` nor `raw` of the arguments has them.
+    pub fn parse(&self, mut words: Vec<String>) -> Result<Option<Invocation<'_>>, String> {
+        // --include / --exclude patterns and --exclude-hidden / --exclude-system work on any command
+        let scan_filter = ScanFilter::take_flags(&mut words)?;
+        let attribute_filter = AttributeFilter::take_flags(&mut words);
+        // and so do --format and the listing flags, checked against the command below
+        let format = OutputFormat::take_flag(&mut words)?;
+        let listing = ListingOptions::take_flags(&mut words)?;
+
+        let Some(name) = words.first().map(|name| name.to_lowercase()) else {
+            return Ok(None);
+        };
+        let Some(command) = self.get(&name) else {
+            return Err(format!("{}: command not found", name));
+        };
+        if format == OutputFormat::Json && !command.json() {
+            let json = self.json_commands();
+            return Err(format!("{} has no json output, it's available for: {}", command.name(), json.join(", ")));
+        }
+        if listing.filters() && !command.listing() {
+            return Err(format!(
+                "{} only takes --top, the other listing flags work with: {}",
+                command.name(),
+                self.listing_commands().join(", ")
+            ));
+        }
+
+        let raw = words.split_off(1);
+        let args = Args { words: raw.iter().map(|word| word.to_lowercase()).collect(), raw, listing };
+        Ok(Some(Invocation { command, args, scan_filter, attribute_filter, format }))
+    }
+
+    fn print_entry(info: &CommandInfo) {
+        print!(
+            "\n\
+            {}\n\
+            {}\n\
+            {}\n",
+            //info.title.bright_white(),
+            info.cmd_args.bright_blue(),
+            "-------------".green().bold(),
+            info.description
+        );
+    }
+
+    /// `help <name>`, for commands and topics alike
+    pub fn print_help(&self, name: &str) -> CommandResult {
+        match self.get(name).map(|c| c.info()).or_else(|| self.topic(name)) {
+            Some(info) => {
+                Self::print_entry(info);
+                Ok(())
+            }
+            None => Err(CommandError::usage(format!("no command named {}", name))),
+        }
+    }
+
+    pub fn print_all_help(&self) {
+        // for if I want to sort it alphabetically (probably still works, probably) :
+        // let mut commands: Vec<_> = self.commands.values().collect();
+        // commands.sort_by_key(|c| c.name());
+
+        let commands = self.commands.values().map(|c| c.info());
+        for info in commands.chain(self.topics.values()) {
+            Self::print_entry(info);
+            println!(); // add an extra newline between commands
+        }
+    }
+}
+
+fn prompter_fn() {
+    let _user: String = whoami::username();
+    let _host: String = fallible::hostname().unwrap();
+    let prompt: String = format!(
+        "\n{}{}{}\n{} ",
+        _user.bright_green(),
+        "@".bright_white(),
+        _host.bright_blue(),
+        "$".bright_cyan()
+    );
+    print!("{}", prompt);
+    io::stdout().flush().unwrap();
+}
+
+/// A command line taken apart by `Registry::parse`.
+pub struct Invocation<'r> {
+    pub command: &'r dyn Command,
+    pub args: Args,
+    pub scan_filter: ScanFilter,
+    pub attribute_filter: AttributeFilter,
+    pub format: OutputFormat,
+}
+
+/// The shell, running the registry's commands against one backend.
+pub struct Shell {
+    registry: &'static Registry,
+    backend: Box<dyn Backend>,
+}
+
+impl Shell {
+    pub fn new(registry: &'static Registry, backend: Box<dyn Backend>) -> Self {
+        Shell { registry, backend }
+    }
+
+    /// Reads and runs commands until `exit`.
+    pub fn run(&mut self) {
+        prompter_fn();
+
+        // wait for user input
+        let stdin = io::stdin();
+        let mut input = String::new();
+        // Ctrl+C stops a running scan and comes back here instead of quitting
+        install_interrupt_handler();
+        loop {
+            stdin.read_line(&mut input).unwrap();
+            self.execute(&input);
+            // the shell carries on whatever happened, the status only matters to one-off commands
+            take_status();
+            input.clear();
+            prompter_fn();
+        }
+    }
+
+    /// Runs one line typed into the shell.
+    pub fn execute(&mut self, input: &str) {
+        self.execute_words(split_command_line(input));
+    }
+
+    /// Runs a command line already split into words, the shell's or the program's arguments.
+    /// Errors and usage mistakes are reported through `output::fail` / `output::usage`.
+    pub fn execute_words(&mut self, words: Vec<String>) {
+        let Invocation { command, args, scan_filter, attribute_filter, format } = match self.registry.parse(words) {
+            Ok(Some(invocation)) => invocation,
+            Ok(None) => return,
+            Err(e) => return usage(e),
+        };
+        self.backend.set_filters(scan_filter, attribute_filter);
+        if let Err(e) = self.backend.set_output_format(format) {
+            return usage(e);
+        }
+
+        match command.run(self.backend.as_mut(), &args) {
+            Ok(()) => {}
+            // parsers with their own usage text already include it
+            Err(CommandError::Usage(message)) if message.contains("Usage:") => usage(message),
+            Err(CommandError::Usage(message)) => usage(format!("{}. Usage: {}", message, command.info().cmd_args)),
+            Err(CommandError::Failed(e)) => fail(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::super::builtins::COMMANDS;
+    use crate::utility::utils::split_command_line;
+
+    #[test]
+    fn test_registry() {
+        // dispatch and help come from the same entries, so every name is a real command
+        assert!(COMMANDS.get("largest-folder").is_some());
+        assert!(COMMANDS.get("largest folder").is_none());
+        assert!(COMMANDS.get("type").is_some());
+        assert!(COMMANDS.get("ignore").is_none());
+        assert!(COMMANDS.topic("ignore").is_some());
+        assert!(COMMANDS.print_help("nope").is_err());
+        assert!(COMMANDS.json_commands().contains(&"largest-files"));
+        assert!(!COMMANDS.json_commands().contains(&"dedupe"));
+        assert!(COMMANDS.listing_commands().contains(&"old-large-files"));
+        assert!(!COMMANDS.listing_commands().contains(&"drive-space"));
+    }
+
+    #[test]
+    fn test_parse_takes_flags_out_of_raw() {
+        let parse = |line: &str| COMMANDS.parse(split_command_line(line));
+        let invocation = parse("perm-audit /tmp/Rv/data --top 1").unwrap().unwrap();
+        assert_eq!(invocation.command.name(), "perm-audit");
+        assert_eq!(invocation.args.raw, ["/tmp/Rv/data"]);
+        assert_eq!(invocation.args.words, ["/tmp/rv/data"]);
+        assert_eq!(invocation.args.top(), 1);
+
+        // flags in front of the command and its arguments come out too, values keep their case
+        let invocation = parse("--Exclude Temp/ query C \"name ~ 'My  Notes'\" --top 3").unwrap().unwrap();
+        assert_eq!(invocation.args.raw_from(1), "name ~ 'My  Notes'");
+        assert_eq!(invocation.scan_filter.excludes, ["Temp/"]);
+        assert_eq!(invocation.args.top(), 3);
+
+        assert!(parse("   ").unwrap().is_none());
+        assert!(parse("nope C").is_err());
+        assert!(parse("dedupe C --format json").is_err());
+        assert!(parse("drive-space C --min-size 1gb").is_err());
+    }
+}
@@ -2,12 +2,21 @@
 pub const GB_TO_BYTES: f64 = 1_073_741_824.0;
 pub const MB_TO_BYTES: f64 = 1_048_576.0;
 
-// minimum size thresholds for analysis
+// minimum size thresholds for analysis, when the listing isn't given --min-size
 pub const MIN_FOLDER_SIZE_GB: f64 = 0.1;
 pub const MIN_FILE_TYPE_SIZE_GB: f64 = 0.01;
 
+// the age listings are just canned queries, --age moves their window
+pub const RECENT_LARGE_FILES_AGE: &str = "30d";
+pub const OLD_LARGE_FILES_AGE: &str = "6mo";
+pub const RECENT_LARGE_FILES_QUERY: &str = "type = file and modified > -30d order by size desc";
+pub const OLD_LARGE_FILES_QUERY: &str = "type = file and modified < -6mo order by size desc";
+pub const LARGEST_FILES_QUERY: &str = "type = file order by size desc";
+
 // time format
 pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// seconds between the FILETIME epoch (1601-01-01) and the unix epoch
+pub const FILETIME_EPOCH_OFFSET_SECS: i64 = 11_644_473_600;
 
 // cargo.toml shit
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,447 @@
+// finding files with identical contents and replacing the extra copies with links.
+//
+// candidates are grouped by size, then by sha256, and every pair is compared byte for byte
+// again right before a copy is replaced. each replaced copy goes into a journal with its
+// timestamps so `dedupe -undo` can turn the links back into independent files
+use super::folder_cleanup::{
+    confirm, field_to_time, open_journal_session, read_last_session, rewrite_last_session, set_times,
+    time_to_field,
+};
+use super::progress::{CancelGuard, cancelled};
+use super::quarantine::sha256_of;
+use super::utils::{app_file, parse_size};
+use crate::analyser::types::DuplicateGroup;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub const DEDUPE_JOURNAL: &str = "outputs/DedupeJournal.txt";
+/// files below this aren't worth a link, unless `-min-size` says otherwise
+pub const DEFAULT_MIN_DUPLICATE_SIZE: u64 = 1_048_576;
+
+pub const DEDUPE_USAGE: &str = "Usage: dedupe [drive] [-min-size N] [-reflink] [-dry-run]  |  dedupe -undo";
+
+/// what `dedupe` was asked to do
+#[derive(Debug, PartialEq)]
+pub struct DedupeArgs {
+    pub drive: String,
+    pub min_size: u64,
+    pub mode: LinkMode,
+    pub dry_run: bool,
+}
+
+impl DedupeArgs {
+    /// parses everything after `dedupe` (not `-undo`, the shells handle that one)
+    pub fn parse(words: &[String]) -> Result<Self, String> {
+        let drive = match words.first() {
+            Some(drive) if !drive.starts_with('-') => drive.clone(),
+            _ => return Err(format!("Drive letter required. {}", DEDUPE_USAGE)),
+        };
+        let mut args = DedupeArgs {
+            drive,
+            min_size: DEFAULT_MIN_DUPLICATE_SIZE,
+            mode: LinkMode::HardLink,
+            dry_run: false,
+        };
+        let mut rest = words[1..].iter();
+        while let Some(word) = rest.next() {
+            match word.as_str() {
+                "-min-size" => {
+                    args.min_size = rest
+                        .next()
+                        .and_then(|n| parse_size(n))
+                        .ok_or("-min-size needs a size, like 10MB")?;
+                }
+                "-reflink" => args.mode = LinkMode::Reflink,
+                "-dry-run" => args.dry_run = true,
+                other => return Err(format!("Unknown option '{}'. {}", other, DEDUPE_USAGE)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkMode {
+    /// one file with several names, only works within a volume
+    HardLink,
+    /// separate files sharing their data blocks (ReFS, Btrfs, XFS, APFS)
+    Reflink,
+}
+
+impl LinkMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            LinkMode::HardLink => "hardlink",
+            LinkMode::Reflink => "reflink",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "hardlink" => Some(LinkMode::HardLink),
+            "reflink" => Some(LinkMode::Reflink),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct DedupeEntry {
+    mode: LinkMode,
+    size: u64,
+    kept: String,
+    path: String,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+}
+
+impl DedupeEntry {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.mode.as_str(),
+            self.size,
+            time_to_field(self.created),
+            time_to_field(self.modified),
+            time_to_field(self.accessed),
+            self.kept,
+            self.path
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(7, '\t');
+        Some(DedupeEntry {
+            mode: LinkMode::parse(fields.next()?)?,
+            size: fields.next()?.parse().ok()?,
+            created: field_to_time(fields.next()?),
+            modified: field_to_time(fields.next()?),
+            accessed: field_to_time(fields.next()?),
+            kept: fields.next()?.to_string(),
+            path: fields.next()?.to_string(),
+        })
+    }
+}
+
+/// (volume, file index, link count), two paths with the same volume and index are one file
+#[cfg(target_os = "windows")]
+fn file_id(path: &Path) -> io::Result<(u64, u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::{BY_HANDLE_FILE_INFORMATION, GetFileInformationByHandle};
+
+    let file = File::open(path)?;
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((
+        info.dwVolumeSerialNumber as u64,
+        (info.nFileIndexHigh as u64) << 32 | info.nFileIndexLow as u64,
+        info.nNumberOfLinks as u64,
+    ))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn file_id(path: &Path) -> io::Result<(u64, u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino(), metadata.nlink()))
+}
+
+/// compares two files byte for byte
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut left, mut right) = (vec![0u8; 1 << 16], vec![0u8; 1 << 16]);
+    loop {
+        let read = a.read(&mut left)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut right[..read])?;
+        if left[..read] != right[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Groups files by content. Only sizes that show up more than once get hashed,
+/// and files that are already links to each other count once.
+/// The oldest copy of each group comes first, that's the one a dedupe keeps.
+/// Ctrl+C stops the hashing early, the returned flag is false then and the groups are partial.
+pub fn find_duplicates(paths: impl IntoIterator<Item = String>, min_size: u64) -> (Vec<DuplicateGroup>, bool) {
+    let _cancel = CancelGuard::begin();
+    let mut by_size: HashMap<u64, Vec<(String, Option<SystemTime>)>> = HashMap::new();
+    for path in paths {
+        if cancelled() {
+            break;
+        }
+        if let Ok(metadata) = fs::metadata(&path)
+            && metadata.is_file()
+            && metadata.len() >= min_size.max(1)
+        {
+            by_size.entry(metadata.len()).or_default().push((path, metadata.modified().ok()));
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (size, files) in by_size.into_iter().filter(|(_, files)| files.len() > 1) {
+        let mut by_hash: HashMap<String, Vec<(String, Option<SystemTime>)>> = HashMap::new();
+        let mut seen_ids = Vec::new();
+        for (path, modified) in files {
+            if cancelled() {
+                break;
+            }
+            if let Ok((volume, index, _)) = file_id(Path::new(&path)) {
+                if seen_ids.contains(&(volume, index)) {
+                    continue;
+                }
+                seen_ids.push((volume, index));
+            }
+            if let Ok(hash) = sha256_of(Path::new(&path)) {
+                by_hash.entry(hash).or_default().push((path, modified));
+            }
+        }
+        for (sha256, mut files) in by_hash.into_iter().filter(|(_, files)| files.len() > 1) {
+            files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            groups.push(DuplicateGroup {
+                size,
+                sha256,
+                paths: files.into_iter().map(|(path, _)| path).collect(),
+            });
+        }
+    }
+    // biggest savings first
+    groups.sort_by_key(|g| std::cmp::Reverse(g.size * (g.paths.len() as u64 - 1)));
+    (groups, !cancelled())
+}
+
+/// a sibling path for the link to be made at before it replaces the copy
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".dedupe-tmp");
+    path.with_file_name(name)
+}
+
+/// Replaces `path` with a link to `kept` and returns its journal entry plus the bytes
+/// that were actually freed (nothing when `path` had other hard links keeping its data alive).
+fn replace_with_link(kept: &Path, path: &Path, mode: LinkMode) -> io::Result<(DedupeEntry, u64)> {
+    let (kept_volume, kept_index, _) = file_id(kept)?;
+    let (volume, index, links) = file_id(path)?;
+    if (kept_volume, kept_index) == (volume, index) {
+        return Err(io::Error::other("already the same file"));
+    }
+    if !same_contents(kept, path)? {
+        return Err(io::Error::other("contents changed since the scan"));
+    }
+
+    let metadata = fs::metadata(path)?;
+    let kept_metadata = fs::metadata(kept)?;
+    let entry = DedupeEntry {
+        mode,
+        size: metadata.len(),
+        kept: kept.to_string_lossy().to_string(),
+        path: path.to_string_lossy().to_string(),
+        created: metadata.created().ok(),
+        modified: metadata.modified().ok(),
+        accessed: metadata.accessed().ok(),
+    };
+
+    // the link is made next to the copy and then renamed over it,
+    // so the copy is never missing if something fails halfway
+    let temp = temp_path(path);
+    let linked = match mode {
+        LinkMode::HardLink => fs::hard_link(kept, &temp),
+        LinkMode::Reflink => reflink_copy::reflink(kept, &temp),
+    };
+    if let Err(e) = linked {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    match mode {
+        // a hard link shares the kept file's metadata, make sure linking didn't touch it
+        LinkMode::HardLink => set_times(
+            kept,
+            kept_metadata.created().ok(),
+            kept_metadata.modified().ok(),
+            kept_metadata.accessed().ok(),
+        )?,
+        // a clone is its own file, so it can keep the replaced copy's times
+        LinkMode::Reflink => set_times(path, entry.created, entry.modified, entry.accessed)?,
+    }
+    let freed = if links == 1 { entry.size } else { 0 };
+    Ok((entry, freed))
+}
+
+/// Shows the duplicate groups and what replacing them would save, asks for a `yes`,
+/// then links every extra copy to the first file of its group.
+/// with `dry_run` it stops after the preview.
+pub fn dedupe_files(groups: &[DuplicateGroup], mode: LinkMode, dry_run: bool) -> io::Result<()> {
+    println!("\n--- Duplicate Files ---");
+    for group in groups {
+        println!("{:.2} MB x {}  (sha256 {})", group.size as f64 / 1_048_576.0, group.paths.len(), &group.sha256[..16]);
+        println!("   keep    {}", group.paths[0]);
+        for path in &group.paths[1..] {
+            println!("   replace {}", path);
+        }
+    }
+    let copies: usize = groups.iter().map(|g| g.paths.len() - 1).sum();
+    let reclaimable: u64 = groups.iter().map(|g| g.size * (g.paths.len() as u64 - 1)).sum();
+    println!(
+        "{} groups, {} copies would become {}s, up to {:.2} GB reclaimed",
+        groups.len(),
+        copies,
+        mode.as_str(),
+        reclaimable as f64 / 1_073_741_824.0
+    );
+
+    if dry_run || groups.is_empty() {
+        return Ok(());
+    }
+    if mode == LinkMode::HardLink {
+        println!(
+            "Warning: hard links share one file, so editing any linked copy changes every copy in its group. \
+             Use -reflink where the file system supports it to keep copies independent."
+        );
+    }
+    if !confirm(&format!("Replace these {} copies? Type 'yes' to continue: ", copies))? {
+        println!("Nothing was replaced.");
+        return Ok(());
+    }
+
+    let journal_file = app_file(DEDUPE_JOURNAL);
+    let mut journal = open_journal_session(&journal_file)?;
+    let (mut replaced, mut failed, mut freed) = (0, 0, 0u64);
+    for group in groups {
+        let kept = Path::new(&group.paths[0]);
+        for path in &group.paths[1..] {
+            match replace_with_link(kept, Path::new(path), mode) {
+                Ok((entry, bytes)) => {
+                    writeln!(journal, "{}", entry.to_line())?;
+                    replaced += 1;
+                    freed += bytes;
+                }
+                Err(e) => {
+                    eprintln!("Skipped {}: {}", path, e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!(
+        "Replaced {} copies ({} skipped), {:.2} GB reclaimed. Journal: {} (run `dedupe -undo` to reverse)",
+        replaced,
+        failed,
+        freed as f64 / 1_073_741_824.0,
+        journal_file.display()
+    );
+    Ok(())
+}
+
+/// Turns the links made by the last dedupe back into separate copies with their
+/// original timestamps, then drops that session from the journal. copies that couldn't
+/// be restored stay in it, so another `dedupe -undo` can retry them.
+pub fn undo_last_dedupe() -> io::Result<()> {
+    let journal_file = app_file(DEDUPE_JOURNAL);
+    let Some((lines, start)) = read_last_session(&journal_file)? else {
+        println!("Nothing to undo, the dedupe journal is empty or missing.");
+        return Ok(());
+    };
+    let entries: Vec<DedupeEntry> = lines[start + 1..]
+        .iter()
+        .filter_map(|l| DedupeEntry::from_line(l))
+        .collect();
+
+    let mut remaining = Vec::new();
+    for entry in &entries {
+        let (kept, path) = (Path::new(&entry.kept), Path::new(&entry.path));
+        let temp = temp_path(path);
+        // copy to the side first, so the link only goes away once a full copy exists
+        let result = fs::copy(kept, &temp)
+            .and_then(|_| match same_contents(kept, &temp)? {
+                true => fs::rename(&temp, path),
+                false => Err(io::Error::other("copy doesn't match")),
+            })
+            .and_then(|()| set_times(path, entry.created, entry.modified, entry.accessed));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp);
+            eprintln!("Failed to restore {}: {}", entry.path, e);
+            remaining.push(entry.to_line());
+        }
+    }
+
+    rewrite_last_session(&journal_file, &lines, start, &remaining)?;
+    println!(
+        "Restored {} of {} copies from the last dedupe.",
+        entries.len() - remaining.len(),
+        entries.len()
+    );
+    if !remaining.is_empty() {
+        println!(
+            "The {} that failed are still in the journal, run `dedupe -undo` again to retry.",
+            remaining.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod dedupe_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+
+    #[test]
+    fn test_dedupe_args() {
+        let words = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let args = DedupeArgs::parse(&words("c -min-size 10mb -reflink -dry-run")).unwrap();
+        assert_eq!(args.min_size, 10 * 1_048_576);
+        assert_eq!(args.mode, LinkMode::Reflink);
+        assert!(args.dry_run);
+        assert!(DedupeArgs::parse(&words("c")).is_ok_and(|a| a.mode == LinkMode::HardLink && !a.dry_run));
+        assert!(DedupeArgs::parse(&words("-reflink")).is_err());
+        assert!(DedupeArgs::parse(&words("c -min-size lots")).is_err());
+    }
+
+    #[test]
+    fn test_find_and_link() {
+        let dir = ScratchDir::new("dedupe");
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, data).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let a = write("a.bin", b"same contents here");
+        let b = write("b.bin", b"same contents here");
+        let c = write("c.bin", b"same length, other");
+        let small = write("d.bin", b"x");
+        write("e.bin", b"x");
+
+        let (groups, complete) = find_duplicates(vec![a.clone(), b.clone(), c, small], 2);
+        assert!(complete);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].size, 18);
+        assert_eq!(groups[0].paths.len(), 2);
+
+        let (entry, freed) = replace_with_link(Path::new(&a), Path::new(&b), LinkMode::HardLink).unwrap();
+        assert_eq!(freed, 18);
+        assert_eq!(DedupeEntry::from_line(&entry.to_line()), Some(entry));
+        assert_eq!(fs::read(&b).unwrap(), b"same contents here");
+        // now they're one file, so there's nothing left to find
+        assert!(find_duplicates(vec![a.clone(), b.clone()], 2).0.is_empty());
+        assert!(replace_with_link(Path::new(&a), Path::new(&b), LinkMode::HardLink).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+// deleting empty folders safely, and putting them back again.
+//
+// every run gets a session in the journal, one line per deleted folder with its
+// timestamps, so `undo` can recreate the last session exactly as it was
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::utility::utils::app_file;
+
+pub const JOURNAL_FILE: &str = "outputs/EmptyFolderJournal.txt";
+const SESSION_MARKER: &str = "# session ";
+
+/// folders that should never be touched, even when they're empty
+pub fn is_protected_folder(path: &str) -> bool {
+    let lower = path.replace('/', "\\").to_lowercase();
+    let components: Vec<&str> = lower.split('\\').filter(|c| !c.is_empty()).collect();
+
+    // a drive root or a top level folder like C:\Windows
+    if components.len() <= 1 {
+        return true;
+    }
+    if matches!(
+        components[1],
+        "windows" | "program files" | "program files (x86)" | "programdata" | "recovery" | "boot"
+    ) {
+        return true;
+    }
+    components.iter().any(|c| {
+        c.starts_with('$') || *c == "system volume information" || *c == "appdata"
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct JournalEntry {
+    path: String,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+}
+
+// timestamps get written as unix nanoseconds, `-` when the OS didn't give one
+pub fn time_to_field(time: Option<SystemTime>) -> String {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos().to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+pub fn field_to_time(field: &str) -> Option<SystemTime> {
+    field.parse::<u64>().ok().map(|nanos| UNIX_EPOCH + Duration::from_nanos(nanos))
+}
+
+impl JournalEntry {
+    // tabs can't appear in Windows paths, so they're safe as a separator
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            time_to_field(self.created),
+            time_to_field(self.modified),
+            time_to_field(self.accessed),
+            self.path
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(4, '\t');
+        Some(JournalEntry {
+            created: field_to_time(fields.next()?),
+            modified: field_to_time(fields.next()?),
+            accessed: field_to_time(fields.next()?),
+            path: fields.next()?.to_string(),
+        })
+    }
+}
+
+/// asks a question on stdin, only a typed `yes` counts
+pub fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("yes"))
+}
+
+/// Appends a new session marker to a journal and returns it ready for entries.
+/// (the dedupe journal uses the same session layout)
+pub fn open_journal_session(journal_file: &Path) -> io::Result<File> {
+    if let Some(dir) = journal_file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut journal = OpenOptions::new().create(true).append(true).open(journal_file)?;
+    let session = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    writeln!(journal, "{}{}", SESSION_MARKER, session)?;
+    Ok(journal)
+}
+
+/// Every line of a journal plus where its last session starts,
+/// `None` when there's no journal or no session in it.
+pub fn read_last_session(journal_file: &Path) -> io::Result<Option<(Vec<String>, usize)>> {
+    let lines: Vec<String> = match File::open(journal_file) {
+        Ok(file) => BufReader::new(file).lines().collect::<io::Result<_>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(lines
+        .iter()
+        .rposition(|l| l.starts_with(SESSION_MARKER))
+        .map(|start| (lines, start)))
+}
+
+/// Rewrites a journal so its last session (everything from `start` on) only keeps
+/// `remaining`, dropping the session altogether when nothing is left in it.
+pub fn rewrite_last_session(
+    journal_file: &Path,
+    lines: &[String],
+    start: usize,
+    remaining: &[String],
+) -> io::Result<()> {
+    let mut journal = File::create(journal_file)?;
+    for line in &lines[..start] {
+        writeln!(journal, "{}", line)?;
+    }
+    if !remaining.is_empty() {
+        writeln!(journal, "{}", lines[start])?;
+        for line in remaining {
+            writeln!(journal, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+fn depth(path: &str) -> usize {
+    path.split(['\\', '/']).filter(|c| !c.is_empty()).count()
+}
+
+/// Deletes the given folders after showing what would go and asking for a `yes`.
+///
+/// protected folders are dropped from the list up front, and each folder is checked
+/// again right before it's removed, so anything that gained files since the scan is skipped.
+/// with `dry_run` it stops after the preview.
+pub fn delete_empty_folders(folders: &[String], dry_run: bool) -> io::Result<()> {
+    let mut targets: Vec<&String> = folders.iter().filter(|f| !is_protected_folder(f)).collect();
+    // deepest first, so a parent emptied by its children goes after them
+    targets.sort_by_key(|f| std::cmp::Reverse(depth(f)));
+
+    println!("\n--- Dry Run: Empty Folders To Delete ---");
+    for folder in &targets {
+        println!(" - {}", folder);
+    }
+    println!(
+        "{} folders would be deleted ({} protected folders skipped).",
+        targets.len(),
+        folders.len() - targets.len()
+    );
+
+    if dry_run || targets.is_empty() {
+        return Ok(());
+    }
+    if !confirm(&format!("Delete these {} folders? Type 'yes' to continue: ", targets.len()))? {
+        println!("Nothing was deleted.");
+        return Ok(());
+    }
+
+    let journal_file = app_file(JOURNAL_FILE);
+    let mut journal = open_journal_session(&journal_file)?;
+
+    let (mut deleted, mut skipped, mut failed) = (0, 0, 0);
+    for folder in targets {
+        let path = Path::new(folder);
+        // re-check: still a real directory (not a link) and still empty
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => metadata,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        if !fs::read_dir(path).map(|mut entries| entries.next().is_none()).unwrap_or(false) {
+            println!("Skipped (no longer empty): {}", folder);
+            skipped += 1;
+            continue;
+        }
+
+        let entry = JournalEntry {
+            path: folder.clone(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+        };
+        // remove_dir refuses non-empty folders, so a file sneaking in now still can't be lost
+        match fs::remove_dir(path) {
+            Ok(()) => {
+                writeln!(journal, "{}", entry.to_line())?;
+                deleted += 1;
+            }
+            Err(e) => {
+                eprintln!("Failed to delete {}: {}", folder, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Deleted {} folders, skipped {}, failed {}. Journal: {} (run `undo` to restore)",
+        deleted,
+        skipped,
+        failed,
+        journal_file.display()
+    );
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn open_for_times(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use winapi::um::winbase::FILE_FLAG_BACKUP_SEMANTICS;
+    // directories can only be opened with backup semantics (files don't mind it)
+    OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn open_for_times(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+/// puts a file's or folder's timestamps back, creation time only on Windows
+pub fn set_times(
+    path: &Path,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+) -> io::Result<()> {
+    let mut times = fs::FileTimes::new();
+    if let Some(modified) = modified {
+        times = times.set_modified(modified);
+    }
+    if let Some(accessed) = accessed {
+        times = times.set_accessed(accessed);
+    }
+    #[cfg(target_os = "windows")]
+    if let Some(created) = created {
+        use std::os::windows::fs::FileTimesExt;
+        times = times.set_created(created);
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = created;
+    open_for_times(path)?.set_times(times)
+}
+
+/// Recreates the folders deleted in the last session with their original timestamps,
+/// then drops that session from the journal. folders that couldn't be recreated stay
+/// in it, so another `undo` can retry them.
+pub fn undo_last_deletion() -> io::Result<()> {
+    let journal_file = app_file(JOURNAL_FILE);
+    let Some((lines, start)) = read_last_session(&journal_file)? else {
+        println!("Nothing to undo, the deletion journal is empty or missing.");
+        return Ok(());
+    };
+    let entries: Vec<JournalEntry> = lines[start + 1..]
+        .iter()
+        .filter_map(|l| JournalEntry::from_line(l))
+        .collect();
+
+    // shallowest first so parents exist, then times deepest first,
+    // since creating a child bumps its parent's modified time
+    let mut failed = Vec::new();
+    for entry in entries.iter().rev() {
+        if let Err(e) = fs::create_dir_all(&entry.path) {
+            eprintln!("Failed to recreate {}: {}", entry.path, e);
+            failed.push(entry);
+        }
+    }
+    for entry in &entries {
+        if failed.contains(&entry) {
+            continue;
+        }
+        if let Err(e) = set_times(Path::new(&entry.path), entry.created, entry.modified, entry.accessed) {
+            eprintln!("Recreated {} but couldn't restore its timestamps: {}", entry.path, e);
+        }
+    }
+
+    // back in deletion order, so a retry still recreates parents first
+    let remaining: Vec<String> = failed.iter().rev().map(|entry| entry.to_line()).collect();
+    rewrite_last_session(&journal_file, &lines, start, &remaining)?;
+    println!(
+        "Restored {} of {} folders from the last deletion.",
+        entries.len() - failed.len(),
+        entries.len()
+    );
+    if !failed.is_empty() {
+        println!("The {} that failed are still in the journal, run `undo` again to retry.", failed.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod folder_cleanup_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+
+    #[test]
+    fn test_protected_folders() {
+        assert!(is_protected_folder("C:\\"));
+        assert!(is_protected_folder("C:/Windows/Temp/empty"));
+        assert!(is_protected_folder("C:\\Program Files (x86)\\Vendor\\cache"));
+        assert!(is_protected_folder("C:\\$Recycle.Bin\\S-1-5-21"));
+        assert!(is_protected_folder("C:\\Users\\me\\AppData\\Local\\Temp\\x"));
+        assert!(!is_protected_folder("C:\\Users\\me\\Downloads\\old"));
+        assert!(!is_protected_folder("D:/Projects/build/empty"));
+    }
+
+    #[test]
+    fn test_journal_lines() {
+        let entry = JournalEntry {
+            path: "C:\\Users\\me\\Downloads\\old stuff".to_string(),
+            created: Some(UNIX_EPOCH + Duration::from_nanos(1_600_000_000_123_456_700)),
+            modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            accessed: None,
+        };
+        let line = entry.to_line();
+        assert_eq!(JournalEntry::from_line(&line), Some(entry));
+        assert_eq!(JournalEntry::from_line("# session 1700000000"), None);
+    }
+
+    #[test]
+    fn test_rewrite_last_session() {
+        let dir = ScratchDir::new("journal");
+        let journal_file = dir.join("journal.txt");
+        let lines: Vec<String> =
+            ["# session 1", "a", "# session 2", "b", "c"].iter().map(|l| l.to_string()).collect();
+
+        rewrite_last_session(&journal_file, &lines, 2, &["c".to_string()]).unwrap();
+        let kept: Vec<String> = ["# session 1", "a", "# session 2", "c"].iter().map(|l| l.to_string()).collect();
+        assert_eq!(read_last_session(&journal_file).unwrap(), Some((kept, 2)));
+
+        rewrite_last_session(&journal_file, &lines, 2, &[]).unwrap();
+        assert_eq!(
+            read_last_session(&journal_file).unwrap(),
+            Some((vec!["# session 1".to_string(), "a".to_string()], 0))
+        );
+    }
+}
@@ -157,6 +157,18 @@ fn maybe_skip() -> bool {
     }
 }
 
+// no non-blocking key reads without conio, so elsewhere the animation just plays out
+#[cfg(not(target_os = "windows"))]
+fn maybe_skip() -> bool {
+    false
+}
+
+#[cfg(not(target_os = "windows"))]
+fn sleep_with_key_check(total_ms: u64) -> bool {
+    sleep(Duration::from_millis(total_ms));
+    false
+}
+
 // sleep in 50ms slices while checking for the skip key.
 #[cfg(target_os = "windows")]
 fn sleep_with_key_check(total_ms: u64) -> bool {
@@ -1,4 +1,11 @@
 #![allow(unused_imports)]
 pub mod constants;
+pub mod dedupe;
+pub mod folder_cleanup;
 pub mod funzy;
+pub mod output;
+pub mod progress;
+pub mod quarantine;
+#[cfg(test)]
+pub mod scratch;
 pub mod utils;
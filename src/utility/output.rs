@@ -0,0 +1,119 @@
+// where output goes and how a run ends.
+//
+// run as a one-off command (`rusty-analyser largest-files C`) stdout only carries results, so they
+// can be piped; notes about getting them (cache hits, timings, scan summaries) and errors go to
+// stderr, and the exit code says how it went. the shell keeps everything on the terminal as before
+use crate::utility::progress::cancelled;
+use serde::Serialize;
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+pub const EXIT_OK: i32 = 0;
+/// the command ran into an error
+pub const EXIT_FAILED: i32 = 1;
+/// unknown command, missing or invalid arguments
+pub const EXIT_USAGE: i32 = 2;
+/// ran fine, but a `check` rule tripped
+pub const EXIT_ALERT: i32 = 3;
+/// stopped with Ctrl+C, same as a shell would report
+pub const EXIT_CANCELLED: i32 = 130;
+
+pub const FORMAT_FLAG: &str = "--format";
+
+static RESULTS_ONLY: AtomicBool = AtomicBool::new(false);
+static STATUS: AtomicI32 = AtomicI32::new(EXIT_OK);
+
+/// Sends notes to stderr from now on, leaving stdout to the results.
+pub fn set_results_only(results_only: bool) {
+    RESULTS_ONLY.store(results_only, Ordering::SeqCst);
+}
+
+/// A line about the work rather than its result: stdout in the shell, stderr for one-off commands.
+pub fn note(message: impl Display) {
+    if RESULTS_ONLY.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Reports an error from a command and remembers it for the exit code.
+pub fn fail(error: impl Display) {
+    eprintln!("{}", error);
+    STATUS.fetch_max(EXIT_FAILED, Ordering::SeqCst);
+}
+
+/// Reports a command used the wrong way and remembers it for the exit code.
+pub fn usage(message: impl Display) {
+    eprintln!("{}", message);
+    STATUS.fetch_max(EXIT_USAGE, Ordering::SeqCst);
+}
+
+/// Remembers that a `check` rule tripped, for the exit code. The report itself is the output.
+pub fn alert() {
+    STATUS.fetch_max(EXIT_ALERT, Ordering::SeqCst);
+}
+
+/// The exit code for everything since the last call, starting over afterwards.
+pub fn take_status() -> i32 {
+    let status = STATUS.swap(EXIT_OK, Ordering::SeqCst);
+    if cancelled() && status == EXIT_OK { EXIT_CANCELLED } else { status }
+}
+
+/// How a listing is shown, taken from `--format text|json` anywhere in a command.
+/// What goes in it (`--top`, sizes, order) is up to `analyser::listing`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    /// takes `--format` out of a command, wherever it appears in it
+    pub fn take_flag(words: &mut Vec<String>) -> Result<Self, String> {
+        let mut format = OutputFormat::default();
+        let mut rest = Vec::with_capacity(words.len());
+        let mut iter = words.drain(..);
+        while let Some(word) = iter.next() {
+            match word.to_lowercase().as_str() {
+                FORMAT_FLAG => match iter.next().map(|format| format.to_lowercase()).as_deref() {
+                    Some("text") => format = OutputFormat::Text,
+                    Some("json") => format = OutputFormat::Json,
+                    _ => return Err(format!("{} needs text or json", FORMAT_FLAG)),
+                },
+                _ => rest.push(word),
+            }
+        }
+        drop(iter);
+        *words = rest;
+        Ok(format)
+    }
+}
+
+/// Prints anything serializable as pretty JSON on stdout.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod output_tests {
+    use super::*;
+
+    #[test]
+    fn test_output_flags() {
+        let mut words: Vec<String> = ["largest-files", "c", "--top", "50", "--format", "json"].map(String::from).to_vec();
+        let format = OutputFormat::take_flag(&mut words).unwrap();
+        assert_eq!(words, ["largest-files", "c", "--top", "50"]);
+        assert_eq!(format, OutputFormat::Json);
+
+        let mut words: Vec<String> = ["largest-files", "c", "--format"].map(String::from).to_vec();
+        assert!(OutputFormat::take_flag(&mut words).is_err());
+        let mut words: Vec<String> = ["largest-files", "--format", "xml"].map(String::from).to_vec();
+        assert!(OutputFormat::take_flag(&mut words).is_err());
+    }
+}
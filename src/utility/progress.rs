@@ -0,0 +1,174 @@
+// live progress for the long scans, and Ctrl+C cancelling them instead of the whole program.
+//
+// progress goes to stderr on a single line that keeps getting rewritten, so it never mixes
+// with the results. a cancelled scan stops where it is and keeps what it has so far
+use crate::utility::constants::GB_TO_BYTES;
+use std::{
+    io::{self, Write},
+    process,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+// scans running right now (several with `all`), Ctrl+C only cancels when there's something to cancel
+static SCANNING: AtomicUsize = AtomicUsize::new(0);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+const REDRAW_EVERY: Duration = Duration::from_millis(250);
+const CURRENT_DIR_WIDTH: usize = 60;
+
+/// Makes Ctrl+C cancel a running scan. Outside a scan, or pressed twice, it quits as before.
+pub fn install_interrupt_handler() {
+    let handler = ctrlc::set_handler(|| {
+        if SCANNING.load(Ordering::SeqCst) > 0 && !CANCELLED.swap(true, Ordering::SeqCst) {
+            eprintln!("\nCancelling the scan, press Ctrl+C again to quit..");
+        } else {
+            process::exit(130);
+        }
+    });
+    if let Err(e) = handler {
+        eprintln!("Couldn't set up Ctrl+C handling, it will quit instead of cancelling: {}", e);
+    }
+}
+
+/// whether the running scan should stop
+pub fn cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+/// printed with results that Ctrl+C cut short
+pub const INCOMPLETE_RESULTS: &str = "(incomplete: cancelled part way, these results only cover what was read before Ctrl+C)";
+
+/// Lets Ctrl+C cancel whatever runs from here until it's dropped, for work without a
+/// progress line of its own. Guards held side by side are cancelled together.
+pub struct CancelGuard(());
+
+impl CancelGuard {
+    pub fn begin() -> Self {
+        if SCANNING.fetch_add(1, Ordering::SeqCst) == 0 {
+            CANCELLED.store(false, Ordering::SeqCst);
+        }
+        CancelGuard(())
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        SCANNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// "3m 12s" and the like
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Time left when `seen` of `expected` bytes took `elapsed`, `None` until there's a rate to go on.
+/// Used space also counts things scans don't see (metadata, the MFT itself), so it's capped at 0.
+pub fn estimate_remaining(seen: u64, expected: u64, elapsed: Duration) -> Option<Duration> {
+    if seen == 0 || elapsed.is_zero() {
+        return None;
+    }
+    let rate = seen as f64 / elapsed.as_secs_f64();
+    Some(Duration::from_secs_f64(expected.saturating_sub(seen) as f64 / rate))
+}
+
+/// The counters of one scan. Shared between the rayon threads doing the walking,
+/// and only one of them redraws the line at a time.
+pub struct ScanProgress {
+    start: Instant,
+    entries: AtomicU64,
+    bytes: AtomicU64,
+    /// used space of the drive, for the ETA
+    expected_bytes: Option<u64>,
+    last_draw: Mutex<Instant>,
+    _cancel: CancelGuard,
+}
+
+impl ScanProgress {
+    /// Starts a scan: Ctrl+C cancels it from here until it's dropped.
+    /// Scans running side by side are cancelled together.
+    pub fn begin(expected_bytes: Option<u64>) -> Self {
+        ScanProgress {
+            start: Instant::now(),
+            entries: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            expected_bytes,
+            last_draw: Mutex::new(Instant::now()),
+            _cancel: CancelGuard::begin(),
+        }
+    }
+
+    /// counts an entry of `bytes` in `current_dir`, redrawing the line every so often
+    pub fn record(&self, current_dir: &str, bytes: u64) {
+        let entries = self.entries.fetch_add(1, Ordering::Relaxed) + 1;
+        let seen = self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        let Ok(mut last_draw) = self.last_draw.try_lock() else {
+            return;
+        };
+        if last_draw.elapsed() < REDRAW_EVERY {
+            return;
+        }
+        *last_draw = Instant::now();
+
+        let elapsed = self.start.elapsed();
+        let rate = entries as f64 / elapsed.as_secs_f64().max(0.001);
+        let amount = match self.expected_bytes {
+            Some(expected) => format!(
+                "{:.2} of ~{:.2} GB, ETA {}",
+                seen as f64 / GB_TO_BYTES,
+                expected as f64 / GB_TO_BYTES,
+                estimate_remaining(seen, expected, elapsed).map_or("-".to_string(), format_duration)
+            ),
+            None => format!("{:.2} GB", seen as f64 / GB_TO_BYTES),
+        };
+        // keep the end of long paths, that's the part that changes
+        let chars: Vec<char> = current_dir.chars().collect();
+        let dir: String = if chars.len() > CURRENT_DIR_WIDTH {
+            format!("..{}", chars[chars.len() - CURRENT_DIR_WIDTH + 2..].iter().collect::<String>())
+        } else {
+            current_dir.to_string()
+        };
+        eprint!("\r{} entries ({:.0}/s), {} | {:<width$}", entries, rate, amount, dir, width = CURRENT_DIR_WIDTH);
+        let _ = io::stderr().flush();
+    }
+
+    /// Ends the progress line with a summary. Returns false when the scan was cancelled,
+    /// meaning whatever it collected is incomplete.
+    pub fn finish(&self) -> bool {
+        let complete = !cancelled();
+        eprintln!(
+            "\r{} {} entries, {:.2} GB in {}{}",
+            if complete { "Scanned" } else { "Cancelled after" },
+            self.entries.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed) as f64 / GB_TO_BYTES,
+            format_duration(self.start.elapsed()),
+            " ".repeat(CURRENT_DIR_WIDTH)
+        );
+        complete
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates() {
+        let eta = estimate_remaining(25, 100, Duration::from_secs(10)).unwrap();
+        assert_eq!(eta.as_secs(), 30);
+        assert_eq!(estimate_remaining(120, 100, Duration::from_secs(10)), Some(Duration::ZERO));
+        assert_eq!(estimate_remaining(0, 100, Duration::from_secs(10)), None);
+        assert_eq!(format_duration(Duration::from_secs(192)), "3m 12s");
+        assert_eq!(format_duration(Duration::from_secs(7300)), "2h 01m");
+    }
+}
@@ -0,0 +1,504 @@
+// moving files out of the way instead of deleting them.
+//
+// each `archive` run becomes a session folder inside the quarantine directory holding
+// either the moved files (`files\`) or a `files.tar.gz`, plus a manifest with every
+// file's original path, size, timestamps and sha256 so `restore` can put it back exactly
+use super::folder_cleanup::{confirm, field_to_time, set_times, time_to_field};
+use crate::analyser::query::{RESULT_SETS, result_set_query};
+use crate::utility::utils::app_file;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const QUARANTINE_DIR: &str = "outputs/quarantine";
+const MANIFEST_FILE: &str = "manifest.tsv";
+const FILES_DIR: &str = "files";
+const PACKED_FILE: &str = "files.tar.gz";
+// left in every session `archive` creates, so `restore` knows the folder is its own to remove
+const SESSION_MARKER: &str = ".archive-session";
+/// files `archive` picks without `--top`, fewer than a listing shows since they get moved
+pub const ARCHIVE_TOP: usize = 10;
+
+pub const ARCHIVE_USAGE: &str =
+    "Usage: archive <old-large-files|recent-large-files|largest-files> [drive] [--top N] [-to dir] [-pack]";
+
+/// what `archive` was asked to do, how many files comes from the shared `--top`
+#[derive(Debug, PartialEq)]
+pub struct ArchiveArgs {
+    pub query: &'static str,
+    pub drive: String,
+    pub destination: PathBuf,
+    pub pack: bool,
+}
+
+impl ArchiveArgs {
+    /// Parses everything after `archive`. Takes the raw words so `-to` keeps its case.
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let (set, drive) = match words {
+            [set, drive, ..] if !drive.starts_with('-') => (set.to_lowercase(), drive.to_string()),
+            _ => return Err(format!("Result set and drive letter required. {}", ARCHIVE_USAGE)),
+        };
+        let query = result_set_query(&set)
+            .ok_or_else(|| format!("Unknown result set '{}', expected one of: {}", set, RESULT_SETS.join(", ")))?;
+
+        let mut args = ArchiveArgs {
+            query,
+            drive,
+            destination: app_file(QUARANTINE_DIR),
+            pack: false,
+        };
+        let mut rest = words[2..].iter();
+        while let Some(word) = rest.next() {
+            match word.to_lowercase().as_str() {
+                "-to" => args.destination = PathBuf::from(rest.next().ok_or("-to needs a folder")?),
+                "-pack" => args.pack = true,
+                other => return Err(format!("Unknown option '{}'. {}", other, ARCHIVE_USAGE)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ManifestEntry {
+    original: String,
+    // path inside the session, relative to `files\` or the archive root
+    stored: String,
+    size: u64,
+    sha256: String,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+}
+
+impl ManifestEntry {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.sha256,
+            self.size,
+            time_to_field(self.created),
+            time_to_field(self.modified),
+            time_to_field(self.accessed),
+            self.stored,
+            self.original
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(7, '\t');
+        Some(ManifestEntry {
+            sha256: fields.next()?.to_string(),
+            size: fields.next()?.parse().ok()?,
+            created: field_to_time(fields.next()?),
+            modified: field_to_time(fields.next()?),
+            accessed: field_to_time(fields.next()?),
+            stored: fields.next()?.to_string(),
+            original: fields.next()?.to_string(),
+        })
+    }
+}
+
+pub fn sha256_of(path: &Path) -> io::Result<String> {
+    sha256_of_reader(&mut File::open(path)?)
+}
+
+fn sha256_of_reader(reader: &mut impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// `C:\Users\me\a.iso` -> `C/Users/me/a.iso`, so every drive gets its own subtree
+fn stored_name(original: &str) -> String {
+    original
+        .replace(':', "")
+        .replace('\\', "/")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+/// moves a file, falling back to copy + verify + delete when it has to cross volumes
+fn move_file(from: &Path, to: &Path, sha256: &str) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    if sha256_of(to)? != sha256 {
+        let _ = fs::remove_file(to);
+        return Err(io::Error::other(format!("copy of {} doesn't match the original", from.display())));
+    }
+    fs::remove_file(from)
+}
+
+/// Writes the manifest and syncs it to disk, replacing the previous one in one step
+/// so a session never sits there without one.
+fn write_manifest(session: &Path, entries: &[ManifestEntry]) -> io::Result<()> {
+    let partial = session.join(format!("{}.partial", MANIFEST_FILE));
+    let mut file = File::create(&partial)?;
+    for entry in entries {
+        writeln!(file, "{}", entry.to_line())?;
+    }
+    file.sync_all()?;
+    fs::rename(&partial, session.join(MANIFEST_FILE))
+}
+
+// the sha256 of every member of a packed session, read back from disk
+fn packed_hashes(packed: &Path) -> io::Result<HashMap<String, String>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(packed)?));
+    let mut hashes = HashMap::new();
+    for file in archive.entries()? {
+        let mut file = file?;
+        let stored = file.path()?.to_string_lossy().to_string();
+        hashes.insert(stored, sha256_of_reader(&mut file)?);
+    }
+    Ok(hashes)
+}
+
+fn read_manifest(session: &Path) -> io::Result<Vec<ManifestEntry>> {
+    let file = File::open(session.join(MANIFEST_FILE))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| ManifestEntry::from_line(&line))
+        .collect())
+}
+
+/// Archives `paths` into a new session folder under `session`, no questions asked.
+/// Files that fail to hash or move are reported and left where they are.
+///
+/// The manifest listing every file is on disk before any original is touched, and is
+/// rewritten at the end with only the files that actually made it into the session.
+fn archive_session(paths: &[String], session: &Path, pack: bool) -> io::Result<Vec<ManifestEntry>> {
+    fs::create_dir_all(session)?;
+    File::create(session.join(SESSION_MARKER))?;
+    let mut entries = Vec::new();
+    for path in paths {
+        let original = Path::new(path);
+        let described = fs::metadata(original).and_then(|metadata| {
+            Ok(ManifestEntry {
+                original: path.clone(),
+                stored: stored_name(path),
+                size: metadata.len(),
+                sha256: sha256_of(original)?,
+                created: metadata.created().ok(),
+                modified: metadata.modified().ok(),
+                accessed: metadata.accessed().ok(),
+            })
+        });
+        match described {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Skipped {}: {}", path, e),
+        }
+    }
+
+    write_manifest(session, &entries)?;
+
+    let mut archived = Vec::new();
+    if pack {
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(session.join(PACKED_FILE))?,
+            Compression::default(),
+        ));
+        for entry in &entries {
+            match builder.append_path_with_name(&entry.original, &entry.stored) {
+                Ok(()) => archived.push(entry.clone()),
+                Err(e) => eprintln!("Failed to pack {}: {}", entry.original, e),
+            }
+        }
+        builder.into_inner()?.finish()?.sync_all()?;
+        // only remove the originals once their packed copies read back with the same hash,
+        // a file that changed between hashing and packing stays where it is
+        let hashes = packed_hashes(&session.join(PACKED_FILE))?;
+        archived.retain(|entry| {
+            if hashes.get(&entry.stored) != Some(&entry.sha256) {
+                eprintln!("Packed copy of {} doesn't match it, left it in place", entry.original);
+                return false;
+            }
+            match fs::remove_file(&entry.original) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Packed {} but couldn't remove it: {}", entry.original, e);
+                    false
+                }
+            }
+        });
+    } else {
+        for entry in &entries {
+            let target = session.join(FILES_DIR).join(&entry.stored);
+            match move_file(Path::new(&entry.original), &target, &entry.sha256) {
+                Ok(()) => archived.push(entry.clone()),
+                Err(e) => eprintln!("Failed to move {}: {}", entry.original, e),
+            }
+        }
+    }
+
+    write_manifest(session, &archived)?;
+    Ok(archived)
+}
+
+/// Puts every file of a session back where it came from, checks its hash and
+/// restores its timestamps. Files whose original path is taken again are left alone.
+///
+/// returns how many files were restored
+fn restore_session(session: &Path) -> io::Result<usize> {
+    let entries = read_manifest(session)?;
+    let mut restored = 0;
+
+    let mut put_back = |entry: &ManifestEntry, source: &mut dyn FnMut(&Path) -> io::Result<()>| {
+        let original = Path::new(&entry.original);
+        if original.exists() {
+            eprintln!("Skipped {}: something is already there", entry.original);
+            return;
+        }
+        let result = original
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| source(original))
+            .and_then(|()| match sha256_of(original)? == entry.sha256 {
+                true => set_times(original, entry.created, entry.modified, entry.accessed),
+                false => Err(io::Error::other("restored file doesn't match its hash")),
+            });
+        match result {
+            Ok(()) => restored += 1,
+            Err(e) => eprintln!("Failed to restore {}: {}", entry.original, e),
+        }
+    };
+
+    let packed = session.join(PACKED_FILE);
+    if packed.exists() {
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&packed)?));
+        for file in archive.entries()? {
+            let mut file = file?;
+            let stored = file.path()?.to_string_lossy().to_string();
+            if let Some(entry) = entries.iter().find(|e| e.stored == stored) {
+                put_back(entry, &mut |to| file.unpack(to).map(|_| ()));
+            }
+        }
+    } else {
+        for entry in &entries {
+            let from = session.join(FILES_DIR).join(&entry.stored);
+            put_back(entry, &mut |to| move_file(&from, to, &entry.sha256));
+        }
+    }
+    Ok(restored)
+}
+
+/// Shows what would be archived, asks for a `yes`, then moves (or with `pack`, packs)
+/// the files into a new session under `destination`.
+pub fn archive_files(paths: &[String], destination: &Path, pack: bool) -> io::Result<()> {
+    let total: u64 = paths
+        .iter()
+        .filter_map(|p| fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum();
+    println!("\n--- Files To Archive ---");
+    for path in paths {
+        println!(" - {}", path);
+    }
+    println!(
+        "{} files, {:.2} GB would be {} into {}",
+        paths.len(),
+        total as f64 / 1_073_741_824.0,
+        if pack { "packed" } else { "moved" },
+        destination.display()
+    );
+    if paths.is_empty() || !confirm("Archive these files? Type 'yes' to continue: ")? {
+        println!("Nothing was archived.");
+        return Ok(());
+    }
+
+    let session = new_session(destination)?;
+    let archived = archive_session(paths, &session, pack)?;
+    let bytes: u64 = archived.iter().map(|e| e.size).sum();
+    println!(
+        "Archived {} of {} files ({:.2} GB) to {}. Run `restore {}` to put them back",
+        archived.len(),
+        paths.len(),
+        bytes as f64 / 1_073_741_824.0,
+        session.display(),
+        session.display()
+    );
+    Ok(())
+}
+
+/// Creates the folder of a new session, named after the current second. Another run in the
+/// same second gets `-2`, `-3`.. instead of sharing (and overwriting) the first one's manifest.
+fn new_session(destination: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(destination)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut run = 1;
+    loop {
+        let name = match run {
+            1 => secs.to_string(),
+            _ => format!("{}-{}", secs, run),
+        };
+        let session = destination.join(name);
+        match fs::create_dir(&session) {
+            Ok(()) => return Ok(session),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => run += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// (second, run) of a session folder's name, so `-10` sorts after `-9`
+fn session_order(session: &Path) -> (u64, u32) {
+    let name = session.file_name().unwrap_or_default().to_string_lossy();
+    let (secs, run) = name.split_once('-').unwrap_or((&name, "1"));
+    (secs.parse().unwrap_or(0), run.parse().unwrap_or(0))
+}
+
+/// the newest session folder in the quarantine directory
+fn latest_session(destination: &Path) -> io::Result<Option<PathBuf>> {
+    let sessions = fs::read_dir(destination)?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.join(MANIFEST_FILE).is_file());
+    Ok(sessions.max_by_key(|session| session_order(session)))
+}
+
+/// Clears out a session once every file is back. The folder itself is only removed when
+/// `archive` created it and it held something, otherwise just the manifest and stored files go.
+fn remove_session(session: &Path, total: usize) -> io::Result<()> {
+    if total > 0 && session.join(SESSION_MARKER).is_file() {
+        return fs::remove_dir_all(session);
+    }
+    for artifact in [MANIFEST_FILE, PACKED_FILE] {
+        let artifact = session.join(artifact);
+        if artifact.is_file() {
+            fs::remove_file(artifact)?;
+        }
+    }
+    let files = session.join(FILES_DIR);
+    if files.is_dir() {
+        fs::remove_dir_all(files)?;
+    }
+    Ok(())
+}
+
+/// Restores a session (the newest one in the default quarantine when none is given)
+/// and removes it once every file is back.
+pub fn restore_archive(session: Option<&Path>) -> io::Result<()> {
+    let session = match session {
+        Some(session) => session.to_path_buf(),
+        None => match latest_session(&app_file(QUARANTINE_DIR)) {
+            Ok(Some(session)) => session,
+            _ => {
+                println!("Nothing to restore in {}", app_file(QUARANTINE_DIR).display());
+                return Ok(());
+            }
+        },
+    };
+
+    let total = read_manifest(&session)?.len();
+    let restored = restore_session(&session)?;
+    println!("Restored {} of {} files from {}", restored, total, session.display());
+    if restored == total {
+        remove_session(&session, total)?;
+    } else {
+        println!("Kept {} since some files are still in it", session.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod quarantine_tests {
+    use super::*;
+    use crate::utility::scratch::ScratchDir;
+    use std::collections::HashSet;
+
+    fn scratch(name: &str) -> ScratchDir {
+        let dir = ScratchDir::new(name);
+        fs::create_dir_all(dir.join("data")).unwrap();
+        dir
+    }
+
+    fn round_trip(pack: bool) {
+        let dir = scratch(if pack { "pack" } else { "move" });
+        let file = dir.join("data").join("old.iso");
+        fs::write(&file, b"pretend this is a large file").unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        let session = dir.join("quarantine").join("1");
+        let archived = archive_session(std::slice::from_ref(&path), &session, pack).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].size, 28);
+        assert!(!file.exists());
+        assert_eq!(read_manifest(&session).unwrap(), archived);
+        assert!(!session.join(format!("{}.partial", MANIFEST_FILE)).exists());
+
+        assert_eq!(restore_session(&session).unwrap(), 1);
+        assert_eq!(fs::read(&file).unwrap(), b"pretend this is a large file");
+    }
+
+    #[test]
+    fn test_move_and_restore() {
+        round_trip(false);
+    }
+
+    #[test]
+    fn test_pack_and_restore() {
+        round_trip(true);
+    }
+
+    #[test]
+    fn test_restore_keeps_foreign_folders() {
+        // `restore <folder>` on something archive didn't make only takes its own files away
+        let dir = scratch("foreign");
+        fs::write(dir.join(MANIFEST_FILE), "").unwrap();
+        fs::write(dir.join("data").join("keep.txt"), b"mine").unwrap();
+        restore_archive(Some(&dir)).unwrap();
+        assert!(!dir.join(MANIFEST_FILE).exists());
+        assert!(dir.join("data").join("keep.txt").exists());
+    }
+
+    #[test]
+    fn test_sessions_in_the_same_second() {
+        let dir = ScratchDir::new("sessions");
+        let sessions: Vec<PathBuf> = (0..11).map(|_| new_session(&dir).unwrap()).collect();
+        for session in &sessions {
+            fs::write(session.join(MANIFEST_FILE), "").unwrap();
+        }
+        // each run gets its own folder, and the last one is the newest even past `-9`
+        assert_eq!(sessions.iter().collect::<HashSet<_>>().len(), 11);
+        assert_eq!(latest_session(&dir).unwrap().as_ref(), sessions.last());
+    }
+
+    #[test]
+    fn test_archive_args() {
+        let args = ArchiveArgs::parse(&["Old-Large-Files", "C", "-to", "D:\\Archive", "-pack"]).unwrap();
+        assert_eq!(args.destination, PathBuf::from("D:\\Archive"));
+        assert!(args.pack);
+        assert!(ArchiveArgs::parse(&["largest-files", "C"]).is_ok_and(|a| !a.pack));
+        assert!(ArchiveArgs::parse(&["largest-files"]).is_err());
+        assert!(ArchiveArgs::parse(&["biggest", "C"]).is_err());
+        // the row count is the shared --top, the old -top isn't taken anymore
+        assert!(ArchiveArgs::parse(&["largest-files", "C", "-top", "5"]).is_err());
+    }
+
+    #[test]
+    fn test_stored_name() {
+        assert_eq!(stored_name("C:\\Users\\me\\a.iso"), "C/Users/me/a.iso");
+        assert_eq!(stored_name("/home/me/a.iso"), "home/me/a.iso");
+    }
+}
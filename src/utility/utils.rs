@@ -1,5 +1,9 @@
 use crate::DATE_FORMAT;
+use crate::analyser::ignore::{IgnoreMatcher, ScanFilter};
+use crate::analyser::scan_errors::ScanErrorLog;
 use crate::analyser::types::*;
+use crate::utility::output::{fail, note, usage};
+use crate::utility::progress::{ScanProgress, cancelled};
 use crate::utility::constants::*;
 use chrono::{DateTime, TimeZone, Utc};
 use rayon::prelude::*;
@@ -15,8 +19,18 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 use std::error::Error;
+use std::path::PathBuf;
 use walkdir::WalkDir;
 
+/// Where a config or state file like `outputs/check-state.tsv` lives: next to the executable, so
+/// a run from Task Scheduler or cron finds the same files as one started from the install folder.
+pub fn app_file(relative: &str) -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(relative)))
+        .unwrap_or_else(|| PathBuf::from(relative))
+}
+
 // helper function to convert system time to formatted string
 pub fn system_time_to_string(system_time: SystemTime) -> String {
     let datetime: DateTime<Utc> = system_time
@@ -26,6 +40,103 @@ pub fn system_time_to_string(system_time: SystemTime) -> String {
     datetime.format(DATE_FORMAT).to_string()
 }
 
+// helper function to convert a raw NTFS FILETIME (100ns ticks since 1601) to formatted string
+pub fn filetime_to_string(filetime: u64) -> Option<String> {
+    if filetime == 0 {
+        return None;
+    }
+    let secs = (filetime / 10_000_000) as i64 - FILETIME_EPOCH_OFFSET_SECS;
+    let nanos = (filetime % 10_000_000) as u32 * 100;
+    DateTime::from_timestamp(secs, nanos).map(|dt| dt.format(DATE_FORMAT).to_string())
+}
+
+// helper function to convert a unix timestamp (seconds) to formatted string
+pub fn timestamp_to_string(secs: i64) -> Option<String> {
+    DateTime::from_timestamp(secs, 0).map(|dt| dt.format(DATE_FORMAT).to_string())
+}
+
+/// parses a size like `500`, `100kb`, `2.5gb` or `1tib` into bytes (units are 1024 based)
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim().to_lowercase();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier: f64 = match unit.trim() {
+        "" | "b" => 1.0,
+        "k" | "kb" | "kib" => 1024.0,
+        "m" | "mb" | "mib" => MB_TO_BYTES,
+        "g" | "gb" | "gib" => GB_TO_BYTES,
+        "t" | "tb" | "tib" => GB_TO_BYTES * 1024.0,
+        _ => return None,
+    };
+    number.parse::<f64>().ok().map(|n| (n * multiplier) as u64)
+}
+
+/// parses an age like `12h`, `30d`, `2w`, `6mo` or `1y` into seconds
+pub fn parse_duration(text: &str) -> Option<i64> {
+    let text = text.trim().to_lowercase();
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = text.split_at(split);
+    let seconds_per_unit: i64 = match unit {
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        "mo" => 30 * 86_400,
+        "y" => 365 * 86_400,
+        _ => return None,
+    };
+    number.parse::<i64>().ok()?.checked_mul(seconds_per_unit)
+}
+
+/// parses a `YYYY-MM-DD` date into a unix timestamp (seconds, at midnight UTC)
+pub fn parse_date(text: &str) -> Option<i64> {
+    chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+// splits a line typed into the shell into words, as typed. a quote at the start of a word
+// keeps it together up to the closing one: double quotes are dropped like a shell does,
+// single quotes stay since queries use them for strings
+pub fn split_command_line(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        if quote.is_none() && c.is_whitespace() {
+            words.extend(word.take());
+            continue;
+        }
+        let starts_word = word.is_none();
+        let word = word.get_or_insert_with(String::new);
+        let toggles = if quote == Some(c) {
+            quote = None;
+            true
+        } else if starts_word && (c == '"' || c == '\'') {
+            quote = Some(c);
+            true
+        } else {
+            false
+        };
+        if !(toggles && c == '"') {
+            word.push(c);
+        }
+    }
+    words.extend(word);
+    words
+}
+
+// the current time as a FILETIME, for comparing against raw NTFS timestamps
+pub fn filetime_now() -> u64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    (secs + FILETIME_EPOCH_OFFSET_SECS as u64) * 10_000_000
+}
+
 use std::time::Instant;
 
 pub fn time_command<F, R>(command: F) -> R
@@ -35,26 +146,36 @@ where
     let start = Instant::now();
     let result = command();
     let elapsed = start.elapsed();
-    println!("Execution time: {:?}", elapsed);
+    note(format!("Execution time: {:?}", elapsed));
     result
 }
 
-pub fn calculate_folder_size(path: &Path) -> io::Result<FolderSize> {
+/// Size of everything under `path`. Whatever can't be read goes into `errors`.
+pub fn calculate_folder_size(path: &Path, ignore: &IgnoreMatcher, errors: &Mutex<ScanErrorLog>) -> io::Result<FolderSize> {
     let files: Vec<_> = WalkDir::new(path)
         .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !ignore.is_excluded(&e.path().to_string_lossy(), e.file_type().is_dir()))
         .par_bridge()
-        .filter_map(Result::ok)
+        .filter_map(|entry| entry.map_err(|e| errors.lock().unwrap().record_walk(&e)).ok())
         .filter(|e| e.file_type().is_file())
+        .filter(|e| ignore.is_included(&e.path().to_string_lossy()))
         .collect();
 
     let total_size: u64 = files
         .par_iter()
-        .map(|entry| entry.metadata().map(|m| m.len()).unwrap_or(0))
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                errors.lock().unwrap().record_walk(&e);
+                0
+            }
+        })
         .sum();
+    errors.lock().unwrap().scanned_bytes += total_size;
 
     Ok(FolderSize {
         folder: path.to_string_lossy().to_string(),
-        size_gb: total_size as f64 / GB_TO_BYTES,
+        size: total_size,
         file_count: files.len(),
     })
 }
@@ -64,9 +185,9 @@ pub fn save_empty_folders_to_file(empty_folders: &[String]) -> io::Result<()> {
     let output_dir = Path::new("outputs");
     if !output_dir.exists() {
         create_dir_all(output_dir)?;
-        println!("Created outputs directory.");
+        note("Created outputs directory.");
     } else {
-        println!("Outputs directory already exists.");
+        note("Outputs directory already exists.");
     }
 
     // define the output file path.
@@ -93,58 +214,132 @@ pub fn save_empty_folders_to_file(empty_folders: &[String]) -> io::Result<()> {
     Ok(())
 }
 
+#[cfg(not(target_os = "windows"))]
+pub fn unix_permissions(metadata: &std::fs::Metadata) -> Option<UnixPermissions> {
+    use std::os::unix::fs::MetadataExt;
+    Some(UnixPermissions {
+        mode: metadata.mode(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+    })
+}
+
+// Windows has ACLs instead, perm-audit doesn't look at those
+#[cfg(target_os = "windows")]
+pub fn unix_permissions(_metadata: &std::fs::Metadata) -> Option<UnixPermissions> {
+    None
+}
+
+#[cfg(target_os = "windows")]
+pub fn file_attributes(_relative: &Path, metadata: &std::fs::Metadata) -> u32 {
+    use std::os::windows::fs::MetadataExt;
+    metadata.file_attributes()
+}
+
+/// Unix has no attribute flags, so the closest things stand in: a name starting with a dot
+/// counts as hidden (only the entry itself, like the NTFS flag), and no write bits as read-only.
+/// `relative` is the path below the scanned root, so the root itself is never hidden.
+#[cfg(not(target_os = "windows"))]
+pub fn file_attributes(relative: &Path, metadata: &std::fs::Metadata) -> u32 {
+    let mut attributes = 0;
+    let hidden = relative
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    if hidden {
+        attributes |= FileAttribute::Hidden.mask();
+    }
+    if metadata.permissions().readonly() {
+        attributes |= FileAttribute::ReadOnly.mask();
+    }
+    attributes
+}
+
+/// Walks a drive once and caches its files, and the sizes of its folders up to 3 levels deep.
+///
+/// `expected_bytes` (the drive's used space) is there for the ETA, and for the estimate of
+/// what the scan missed in the error log it leaves in `error_cache`.
+/// Returns false when the scan was cancelled with Ctrl+C, what was found so far is cached anyway.
 pub fn collect_and_cache_files(
     drive: &str,
     file_cache: &mut HashMap<String, Vec<FileInfo>>,
     folder_cache: &mut HashMap<String, Vec<FolderSize>>,
-) -> io::Result<()> {
+    error_cache: &mut HashMap<String, ScanErrorLog>,
+    filter: &ScanFilter,
+    expected_bytes: Option<u64>,
+) -> io::Result<bool> {
     if file_cache.contains_key(drive) || folder_cache.contains_key(drive) {
-        println!("Cached scan found! Proceeding..");
-        return Ok(());
+        note("Cached scan found! Proceeding..");
+        return Ok(true);
     }
 
-    println!("No cache found, scanning..");
+    note("No cache found, scanning.. (Ctrl+C to stop early)");
+    let progress = ScanProgress::begin(expected_bytes);
+    // unreadable folders and files end up here instead of silently lowering the totals
+    let errors = Mutex::new(ScanErrorLog::new(expected_bytes));
 
     let file_cache_arc = Arc::new(Mutex::new(Vec::new()));
     let folder_cache_arc = Arc::new(Mutex::new(Vec::new()));
+    // excluded folders are pruned here, so nothing below them gets walked
+    let ignore = filter.matcher(drive);
+    let not_excluded = |e: &walkdir::DirEntry| {
+        e.depth() == 0 || !ignore.is_excluded(&e.path().to_string_lossy(), e.file_type().is_dir())
+    };
 
     let files: Vec<FileInfo> = WalkDir::new(drive)
         .into_iter()
+        .filter_entry(not_excluded)
+        .take_while(|_| !cancelled())
         .par_bridge()
-        .filter_map(Result::ok)
+        .filter_map(|entry| entry.map_err(|e| errors.lock().unwrap().record_walk(&e)).ok())
         .filter(|e| e.file_type().is_file())
+        .filter(|e| ignore.is_included(&e.path().to_string_lossy()))
         .map(|entry| {
-            let metadata = entry.metadata().ok()?;
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    errors.lock().unwrap().record_walk(&e);
+                    return None;
+                }
+            };
+            let parent = entry.path().parent().unwrap_or(entry.path());
+            progress.record(&parent.to_string_lossy(), metadata.len());
             Some(FileInfo {
                 full_path: entry.path().to_string_lossy().to_string(),
-                size_mb: metadata.len() as f64 / MB_TO_BYTES,
+                size: metadata.len(),
                 last_modified: metadata.modified().ok().map(system_time_to_string),
                 last_accessed: metadata.accessed().ok().map(system_time_to_string),
+                permissions: unix_permissions(&metadata),
+                attributes: file_attributes(
+                    entry.path().strip_prefix(drive).unwrap_or(entry.path()),
+                    &metadata,
+                ),
             })
         })
         .flatten()
         .collect();
 
+    let complete = progress.finish();
+    let mut errors = errors.into_inner().unwrap();
+    errors.scanned_bytes = files.iter().map(|f| f.size).sum();
+    note(errors.summary());
+    error_cache.insert(drive.to_string(), errors);
+
+    // folder sizes come from the files just found, so there's no second walk to wait for
+    let folders = folder_sizes_from_files(drive, &files, 3);
     {
         let mut cache = file_cache_arc.lock().unwrap();
         cache.extend(files);
     }
-
-    let folders: Vec<FolderSize> = WalkDir::new(drive)
-        .min_depth(1)
-        .max_depth(3)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_dir())
-        .filter_map(|entry| calculate_folder_size(entry.path()).ok())
-        .collect();
-
     {
         let mut cache = folder_cache_arc.lock().unwrap();
         cache.extend(folders);
     }
 
-    println!("Scanning complete..");
+    if complete {
+        note("Scanning complete..");
+    } else {
+        note("Scan cancelled, everything from it is incomplete. `rescan` runs it again in full..");
+    }
     file_cache.insert(
         drive.to_string(),
         Arc::try_unwrap(file_cache_arc)
@@ -159,44 +354,101 @@ pub fn collect_and_cache_files(
             .into_inner()
             .unwrap(),
     );
-    println!("Caching files and folders..");
+    note("Caching files and folders..");
 
-    Ok(())
+    Ok(complete)
 }
 
-pub fn validate_and_format_drive<F>(drive: &str, action: F)
-where
-    F: FnOnce(&str) -> Result<(), io::Error>,
-{
+/// Total size and file count of every folder up to `depth` levels below `root`,
+/// worked out from a list of files. Paths are joined the way WalkDir joins them.
+pub fn folder_sizes_from_files(root: &str, files: &[FileInfo], depth: usize) -> Vec<FolderSize> {
+    let mut folders: HashMap<String, (u64, usize)> = HashMap::new();
+    for file in files {
+        let path = Path::new(&file.full_path);
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let mut folder = Path::new(root).to_path_buf();
+        // the last component is the file itself
+        let components: Vec<_> = relative.components().collect();
+        for component in components.iter().take(components.len().saturating_sub(1)).take(depth) {
+            folder.push(component);
+            let entry = folders.entry(folder.to_string_lossy().to_string()).or_insert((0, 0));
+            entry.0 += file.size;
+            entry.1 += 1;
+        }
+    }
+    folders
+        .into_iter()
+        .map(|(folder, (size, file_count))| FolderSize { folder, size, file_count })
+        .collect()
+}
+
+pub const INVALID_DRIVE: &str = "Invalid drive format. Please enter a single letter (e.g., 'C')\
+         or a valid drive path (e.g., 'C:/').";
+
+/// "c", "C" or "C:/" as "C:/", the way the caches key drives
+/// given a number in bytes, returns a compressed version of it
+///
+/// examples : `1505210368 --> 1.40 GB` | `815663130 --> 777.88 MB`
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+/// given a file name from the MFT, returns a user-friendly name. two GUIDs glued together
+/// (`{...}{...}`) become "GUID name", and an empty one "No Name" when `empty` is set
+pub fn filter_filename(name: &str, empty: bool) -> String {
+    if name.is_empty() && empty {
+        "No Name".to_string()
+    } else if name.starts_with('{') && name.contains("}{") && name.ends_with('}') {
+        "GUID name".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// collapses the doubled separators of a Windows path put together from MFT names
+pub fn cleanup_path(path: &str) -> String {
+    let mut cleaned = path.replace('\\', "/");
+    while cleaned.contains("//") {
+        cleaned = cleaned.replace("//", "/");
+    }
+    cleaned.replace('/', "\\")
+}
+
+pub fn format_drive(drive: &str) -> Option<String> {
     let drive = drive.to_uppercase();
 
     if drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic()) {
         // user entered just the letter (e.g., "C"), format it properly
-        if let Err(e) = action(format!("{}:/", drive).as_str()) {
-            eprintln!("Error: {}", e);
-        }
+        Some(format!("{}:/", drive))
     } else if drive.len() == 3
         && drive.ends_with(":/")
         && drive.chars().next().unwrap().is_ascii_alphabetic()
     {
         // user entered a valid full path (e.g., "C:/"), use it directly
-        if let Err(e) = action(drive.as_str()) {
-            eprintln!("Error: {}", e);
-        }
+        Some(drive)
     } else {
         // invalid input
-        eprintln!(
-            "Invalid drive format. Please enter a single letter (e.g., 'C')\
-         or a valid drive path (e.g., 'C:/')."
-        );
+        None
     }
 }
 
 
-/// A thin wrapper around `validate_and_format_drive` that:
-/// 1. lets you write actions returning `Result<(), Box<dyn Error>>`  
-/// 2. still runs through all of the drive‑format/validation logic  
-/// 3. prints the same `eprintln!`s on bad format or I/O
+/// Runs `action` on the drive formatted as "C:/", or prints the usage line when `drive`
+/// isn't one. whatever the action returns goes back to the caller, which reports it once.
 pub fn validate_drive<F>(
     drive: &str,
     action: F,
@@ -204,32 +456,33 @@ pub fn validate_drive<F>(
 where
     F: FnOnce(&str) -> Result<(), Box<dyn Error>>,
 {
-    // stash whatever the action returns
-    let mut result: Option<Result<(), Box<dyn Error>>> = None;
-
-    // run the core validator/formatter
-    validate_and_format_drive(drive, |formatted_drive| {
-        match action(formatted_drive) {
-            Ok(()) => {
-                // action succeeded
-                result = Some(Ok(()));
-                Ok(()) // tell the validator "no I/O error"
-            }
-            Err(e) => {
-                // action failed — stash the real Box<dyn Error>
-                result = Some(Err(e));
-                // but return an io::Error so `validate_and_format_drive` will
-                // hit its `if let Err(e) = action(...) { eprintln!(...) }` branch
-                Err(io::Error::new(io::ErrorKind::Other, "action failed"))
-            }
+    match format_drive(drive) {
+        Some(drive) => action(&drive),
+        None => {
+            usage(INVALID_DRIVE);
+            Ok(())
         }
-    });
-    
-    // todo - make the None thing actually do smth and not a lazy shit
-    // now look at what actually happened
-    match result {
-        Some(Ok(()))  => Ok(()),      // drive OK + action OK
-        Some(Err(e))  => Err(e),      // drive OK + action Err(e)
-        None          => Ok(()),  // validator never even called us
-    }
-}
\ No newline at end of file
+    }
+}
+
+/// like `validate_drive`, but also accepts the path of a raw NTFS image file.
+/// drive letters get passed on as a raw device path (e.g. `\\.\C:`), which only Windows has
+pub fn validate_ntfs_source<F>(
+    target: &str,
+    action: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&str) -> Result<(), Box<dyn Error>>,
+{
+    if Path::new(target).is_file() {
+        return action(target);
+    }
+    if !cfg!(target_os = "windows") {
+        usage(format!("{} isn't an NTFS image file, drive letters can only be read on Windows", target));
+        return Ok(());
+    }
+
+    validate_drive(target, |formatted_drive| {
+        action(&format!("\\\\.\\{}:", &formatted_drive[..1]))
+    })
+}